@@ -0,0 +1,125 @@
+use crate::{view::TableFormat, Placement, Puzzle};
+use std::io::{self, Write};
+
+/// How many solutions have each pair of pieces sharing a face.
+pub struct Adjacency {
+    counts: Vec<Vec<u64>>,
+    solutions: u64,
+}
+
+impl Adjacency {
+    pub fn new(puzzle: &Puzzle) -> Self {
+        let n = puzzle.pieces.len();
+        Self {
+            counts: vec![vec![0; n]; n],
+            solutions: 0,
+        }
+    }
+
+    /// Counts the pairs of pieces touching in one solution.
+    pub fn add(&mut self, puzzle: &Puzzle, solution: &Placement) {
+        let grid = puzzle.grid;
+        let mut owner = vec![None; grid.len()];
+        for (id, bits) in solution.placed.iter() {
+            for (index, cell) in owner.iter_mut().enumerate() {
+                if bits.get(index) {
+                    *cell = Some(*id);
+                }
+            }
+        }
+        let n = puzzle.pieces.len();
+        let mut touching = vec![vec![false; n]; n];
+        for cell in grid.cells() {
+            let Some(a) = owner[grid.index(&cell)] else {
+                continue;
+            };
+            let mut next = [cell, cell, cell];
+            next[0].x += 1;
+            next[1].y += 1;
+            next[2].z += 1;
+            for next in next.iter().filter(|next| grid.contains(next)) {
+                if let Some(b) = owner[grid.index(next)].filter(|b| *b != a) {
+                    touching[a][b] = true;
+                    touching[b][a] = true;
+                }
+            }
+        }
+        for (a, row) in touching.iter().enumerate() {
+            for (b, touch) in row.iter().enumerate() {
+                self.counts[a][b] += *touch as u64;
+            }
+        }
+        self.solutions += 1;
+    }
+
+    /// Solutions in which pieces `a` and `b` touch.
+    pub fn count(&self, a: usize, b: usize) -> u64 {
+        self.counts[a][b]
+    }
+
+    /// Solutions counted.
+    pub fn solutions(&self) -> u64 {
+        self.solutions
+    }
+
+    /// Pairs that touch in every solution, or in none.
+    fn pairs(&self, count: u64) -> Vec<(usize, usize)> {
+        let n = self.counts.len();
+        (0..n)
+            .flat_map(|a| (a + 1..n).map(move |b| (a, b)))
+            .filter(|(a, b)| self.counts[*a][*b] == count)
+            .collect()
+    }
+
+    /// The matrix of pair counts by piece id, followed in a table by the
+    /// pairs that are always and never neighbours.
+    pub fn write(
+        &self,
+        out: &mut dyn Write,
+        puzzle: &Puzzle,
+        format: TableFormat,
+    ) -> io::Result<()> {
+        let pieces = &puzzle.pieces;
+        match format {
+            TableFormat::Table => {
+                write!(out, "  ")?;
+                for piece in pieces.iter() {
+                    write!(out, " {:>6}", piece.char_id())?;
+                }
+                writeln!(out)?;
+                for (piece, row) in pieces.iter().zip(self.counts.iter()) {
+                    write!(out, "{:<2}", piece.char_id())?;
+                    for count in row.iter() {
+                        write!(out, " {:>6}", count)?;
+                    }
+                    writeln!(out)?;
+                }
+                writeln!(out, "{} solutions", self.solutions)?;
+                if self.solutions == 0 {
+                    return Ok(());
+                }
+                for (label, count) in [("always", self.solutions), ("never", 0)] {
+                    let pairs: Vec<String> = self
+                        .pairs(count)
+                        .iter()
+                        .map(|(a, b)| format!("{}-{}", pieces[*a].name, pieces[*b].name))
+                        .collect();
+                    let pairs = match pairs.is_empty() {
+                        true => "none".to_string(),
+                        false => pairs.join(" "),
+                    };
+                    writeln!(out, "{} neighbours: {}", label, pairs)?;
+                }
+            }
+            TableFormat::Csv => {
+                let names: Vec<&str> = pieces.iter().map(|p| p.name.as_str()).collect();
+                writeln!(out, "piece,{}", names.join(","))?;
+                for (name, row) in names.iter().zip(self.counts.iter()) {
+                    let row: Vec<String> = row.iter().map(|count| count.to_string()).collect();
+                    writeln!(out, "{},{}", name, row.join(","))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{view::TableFormat, Bitset, Coord, Placement, Puzzle, Solver};
+use rayon::prelude::*;
+use std::{
+    cmp::Reverse,
+    io::{self, Write},
+};
+
+/// The pieces that can still cover one cell.
+pub struct CellCover {
+    pub cell: usize,
+    /// Pieces with at least one placement covering the cell, and how many.
+    pub pieces: Vec<(usize, usize)>,
+}
+
+impl CellCover {
+    pub fn placements(&self) -> usize {
+        self.pieces.iter().map(|(_, count)| count).sum()
+    }
+}
+
+impl Puzzle {
+    /// The corner cells of the grid that are in the target, in index order.
+    pub fn corners(&self) -> Vec<usize> {
+        let (x, y, z) = (self.grid.x - 1, self.grid.y - 1, self.grid.z - 1);
+        let mut corners: Vec<usize> = [0, x]
+            .iter()
+            .flat_map(|cx| [0, y].map(|cy| (*cx, cy)))
+            .flat_map(|(cx, cy)| [0, z].map(|cz| Coord::new(cx, cy, cz)))
+            .map(|corner| self.grid.index(&corner))
+            .filter(|index| self.target.get(*index))
+            .collect();
+        corners.sort();
+        corners.dedup();
+        corners
+    }
+
+    /// How the pieces not in `placement` can cover `cell` without
+    /// overlapping it.
+    pub fn cover(&self, placement: &Placement, cell: usize) -> CellCover {
+        let mut bit = Bitset::empty();
+        bit.set(cell);
+        let pieces = self
+            .pieces
+            .iter()
+            .filter(|piece| placement.placed.iter().all(|(id, _)| *id != piece.piece_id))
+            .map(|piece| {
+                let count = piece
+                    .placements
+                    .iter()
+                    .filter(|bits| !bits.is_disjoint(&bit) && placement.is_valid(bits))
+                    .count();
+                (piece.piece_id, count)
+            })
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        CellCover { cell, pieces }
+    }
+
+    /// How many placements of `piece_id` that fit around `placement` cover
+    /// each cell, by cell index.
+    pub fn reach(&self, placement: &Placement, piece_id: usize) -> Vec<usize> {
+        let mut counts = vec![0; self.grid.len()];
+        for bits in self.pieces[piece_id].placements.iter() {
+            if placement.is_valid(bits) {
+                for (cell, count) in counts.iter_mut().enumerate() {
+                    if bits.get(cell) {
+                        *count += 1;
+                    }
+                }
+            }
+        }
+        counts
+    }
+
+    /// For each way `piece_id` fits around `placement`, how many solutions
+    /// put it there, most first. The top one is the puzzle's keystone, the
+    /// placement most solutions go through.
+    pub fn placement_counts(&self, placement: &Placement, piece_id: usize) -> Vec<(Bitset, usize)> {
+        let remaining: Vec<usize> = (0..self.pieces.len())
+            .filter(|id| *id != piece_id && placement.placed.iter().all(|(placed, _)| placed != id))
+            .collect();
+        // Pieces can only spill into don't-care cells by as much as their
+        // volume exceeds the target, so the search can be skipped for
+        // placements that spill more.
+        let volume: usize = self.pieces.iter().map(|piece| piece.size).sum();
+        let outside = |bits: &Bitset| bits.without(&self.target).len();
+        let slack = volume.saturating_sub(self.target.len() + outside(&placement.occupied));
+        let mut counts: Vec<(Bitset, usize)> = self.pieces[piece_id]
+            .placements
+            .par_iter()
+            .filter(|bits| placement.is_valid(bits))
+            .map(|bits| {
+                if outside(bits) > slack {
+                    return (bits.clone(), 0);
+                }
+                let mut fixed = placement.clone();
+                fixed.place(piece_id, bits.clone());
+                let mut solver = Solver::new();
+                solver.fill(self, &mut fixed, &remaining);
+                (bits.clone(), solver.num_solutions)
+            })
+            .collect();
+        counts.sort_by_key(|(_, solutions)| Reverse(*solutions));
+        counts
+    }
+
+    /// The table of `placement_counts`, with each placement's share of all
+    /// the counts and its cells. The counts add up to the solutions times
+    /// the copies of the piece each uses.
+    pub fn write_placement_counts(
+        &self,
+        out: &mut dyn Write,
+        counts: &[(Bitset, usize)],
+        format: TableFormat,
+    ) -> io::Result<()> {
+        let total: usize = counts.iter().map(|(_, solutions)| solutions).sum();
+        let share = |solutions: usize| match total {
+            0 => 0.0,
+            total => 100.0 * solutions as f64 / total as f64,
+        };
+        match format {
+            TableFormat::Table => {
+                let width = total.to_string().len().max("solutions".len());
+                writeln!(out, "{:>width$} {:>6}  cells", "solutions", "share")?;
+                for (bits, solutions) in counts.iter() {
+                    writeln!(
+                        out,
+                        "{:>width$} {:>5.1}%  {}",
+                        solutions,
+                        share(*solutions),
+                        self.format_cells(bits)
+                    )?;
+                }
+                let used = counts
+                    .iter()
+                    .filter(|(_, solutions)| *solutions > 0)
+                    .count();
+                writeln!(
+                    out,
+                    "{} of {} placements appear in solutions",
+                    used,
+                    counts.len()
+                )?;
+            }
+            TableFormat::Csv => {
+                writeln!(out, "cells,solutions,share")?;
+                for (bits, solutions) in counts.iter() {
+                    writeln!(
+                        out,
+                        "{},{},{:.4}",
+                        self.format_cells(bits),
+                        solutions,
+                        share(*solutions) / 100.0
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Per-cell counts laid out like `show`, with `#` for cells `placement`
+    /// fills and `.` for zero.
+    pub fn write_counts(
+        &self,
+        out: &mut dyn Write,
+        placement: &Placement,
+        counts: &[usize],
+    ) -> io::Result<()> {
+        let width = counts.iter().max().map_or(1, |max| max.to_string().len()) + 1;
+        let bands = self.layer_bands(width);
+        for (n, band) in bands.iter().enumerate() {
+            self.write_band_header(out, width, &bands, n)?;
+            for y in (0..self.grid.y).rev() {
+                if self.view.labels {
+                    write!(out, "{:>3} ", y)?;
+                }
+                for z in band.clone() {
+                    for x in 0..self.grid.x {
+                        let index = self.grid.index(&Coord::new(x, y, z));
+                        let cell = if placement.occupied.get(index) {
+                            "#".to_string()
+                        } else if counts[index] == 0 {
+                            ".".to_string()
+                        } else {
+                            counts[index].to_string()
+                        };
+                        write!(out, "{:<1$}", cell, width)?;
+                    }
+                    write!(out, "  ")?;
+                }
+                writeln!(out)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use crate::{rng::Rng, Bitset, Placement, Puzzle, Solver};
+
+/// Schedule for the simulated annealing packer.
+pub struct Anneal {
+    pub steps: u64,
+    /// Temperature at the first step, in cells.
+    pub start_temperature: f64,
+    /// Temperature at the last step, the geometric schedule ends here.
+    pub end_temperature: f64,
+    pub seed: u64,
+}
+
+impl Anneal {
+    pub fn new(steps: u64, seed: u64) -> Self {
+        Self {
+            steps,
+            start_temperature: 2.0,
+            end_temperature: 0.05,
+            seed,
+        }
+    }
+}
+
+impl Puzzle {
+    fn covered(&self, placement: &Placement) -> usize {
+        placement.occupied.and(&self.target).len()
+    }
+}
+
+/// Places `pieces` in order, each at a random free placement if one is
+/// left. Pieces that do not fit stay unplaced and are returned.
+fn insert(
+    puzzle: &Puzzle,
+    placement: &mut Placement,
+    pieces: &[usize],
+    rng: &mut Rng,
+) -> Vec<usize> {
+    let mut unplaced = Vec::new();
+    for piece_id in pieces.iter() {
+        let free: Vec<&Bitset> = puzzle.pieces[*piece_id]
+            .placements
+            .iter()
+            .filter(|bits| placement.is_valid(bits))
+            .collect();
+        if free.is_empty() {
+            unplaced.push(*piece_id);
+        } else {
+            placement.place(*piece_id, free[rng.below(free.len())].clone());
+        }
+    }
+    unplaced
+}
+
+impl Solver {
+    /// Stochastic packer: starts from a random partial packing and repeatedly
+    /// takes out one or two pieces and puts the unplaced ones back at random,
+    /// keeping worse packings with a probability that falls with the
+    /// temperature. Pieces already in `placement` stay where they are. The
+    /// best packing ends up in `placement`, and is output as a solution if
+    /// it is complete.
+    pub fn anneal_solve(
+        &mut self,
+        puzzle: &Puzzle,
+        placement: &mut Placement,
+        remaining: &[usize],
+        schedule: &Anneal,
+    ) -> bool {
+        let mut rng = Rng::new(schedule.seed);
+        let fixed = placement.placed.len();
+        let mut order = remaining.to_vec();
+        rng.shuffle(&mut order);
+        let mut current = placement.clone();
+        let mut unplaced = insert(puzzle, &mut current, &order, &mut rng);
+        let mut best = current.clone();
+
+        let cooling = (schedule.end_temperature / schedule.start_temperature)
+            .powf(1.0 / schedule.steps.max(1) as f64);
+        let mut temperature = schedule.start_temperature;
+        for _ in 0..schedule.steps {
+            self.nodes += 1;
+            if puzzle.only_spares(&unplaced) && puzzle.covered(&current) == puzzle.target.len() {
+                break;
+            }
+            let mut next = Placement::new();
+            let mut removed = Vec::new();
+            let movable = current.placed.len() - fixed;
+            let take = if movable == 0 {
+                0
+            } else {
+                1 + rng.below(2.min(movable))
+            };
+            let picks: Vec<usize> = (0..take).map(|_| fixed + rng.below(movable)).collect();
+            for (index, (id, bits)) in current.placed.iter().enumerate() {
+                if picks.contains(&index) {
+                    removed.push(*id);
+                } else {
+                    next.place(*id, bits.clone());
+                }
+            }
+            let mut pieces: Vec<usize> = unplaced.iter().chain(removed.iter()).copied().collect();
+            rng.shuffle(&mut pieces);
+            let next_unplaced = insert(puzzle, &mut next, &pieces, &mut rng);
+
+            let delta = puzzle.covered(&next) as f64 - puzzle.covered(&current) as f64;
+            if delta >= 0.0 || rng.unit() < (delta / temperature).exp() {
+                current = next;
+                unplaced = next_unplaced;
+                if puzzle.covered(&current) > puzzle.covered(&best) {
+                    best = current.clone();
+                }
+            }
+            temperature *= cooling;
+        }
+
+        *placement = best;
+        let unplaced: Vec<usize> = remaining
+            .iter()
+            .copied()
+            .filter(|id| placement.placed.iter().all(|(placed, _)| placed != id))
+            .collect();
+        let complete =
+            puzzle.only_spares(&unplaced) && puzzle.covered(placement) == puzzle.target.len();
+        if complete {
+            self.emit(puzzle, placement);
+        }
+        complete
+    }
+}
@@ -0,0 +1,358 @@
+use crate::{
+    grid::MAX_SIDE,
+    input::{self, GZIP_MAGIC},
+    provenance::Provenance,
+    Bitset, Placement, MAX_PIECES,
+};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Cursor, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+const MAGIC: &[u8; 8] = b"PCSARCH1";
+const LEVEL: i32 = 19;
+
+const _: () = assert!(MAX_PIECES <= 1 << 6 && (MAX_SIDE as usize).pow(3) <= 256 * 64);
+
+pub type Solution = Vec<(usize, Bitset)>;
+
+/// Appends a piece id and its mask: a byte with the id in its low six bits
+/// and in its top two how many words of the mask follow the first, up to
+/// two, or 3 for a byte with that count to follow, then the words. A mask
+/// within the first 64 cells takes a single word.
+pub(crate) fn encode_pair(out: &mut Vec<u8>, id: usize, bits: &Bitset) {
+    let words = bits.words();
+    let more = words.len().saturating_sub(1);
+    if more < 3 {
+        out.push(id as u8 | (more as u8) << 6);
+    } else {
+        out.extend_from_slice(&[id as u8 | 3 << 6, more as u8]);
+    }
+    for word in words.iter() {
+        out.extend_from_slice(&word.to_le_bytes());
+    }
+    if words.is_empty() {
+        out.extend_from_slice(&0u64.to_le_bytes());
+    }
+}
+
+/// The piece id and mask at the start of `raw` as `encode_pair` writes
+/// them, and the bytes they take, or `None` if `raw` stops short.
+pub(crate) fn decode_pair(raw: &[u8]) -> Option<((usize, Bitset), usize)> {
+    let (&first, mut rest) = raw.split_first()?;
+    let mut header = 1;
+    let mut more = (first >> 6) as usize;
+    if more == 3 {
+        let (&count, after) = rest.split_first()?;
+        (more, rest, header) = (count as usize, after, 2);
+    }
+    let count = more + 1;
+    let words: Vec<u64> = rest
+        .get(..count * 8)?
+        .chunks_exact(8)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        .collect();
+    let id = (first & 0x3f) as usize;
+    Some(((id, Bitset::from_words(&words)), header + count * 8))
+}
+
+/// Solutions stored as independently compressed zstd blocks, followed by an
+/// index of block offsets so any solution can be read without decompressing
+/// the ones before it.
+///
+/// Layout: magic, an optional provenance block in text, blocks, index
+/// (`u64` offset and `u32` count per block),
+/// `u64` offset of the index, magic. Inside a block each solution is a piece
+/// count followed by the pieces' ids and masks as `encode_pair` writes
+/// them, all little endian. Counts and ids fit in a byte as puzzles have at
+/// most `MAX_PIECES` pieces.
+pub struct ArchiveWriter {
+    out: BufWriter<File>,
+    block_size: usize,
+    block: Vec<u8>,
+    in_block: usize,
+    offset: u64,
+    index: Vec<(u64, u32)>,
+    len: usize,
+}
+
+impl ArchiveWriter {
+    pub fn create(path: &Path, block_size: usize) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(MAGIC)?;
+        Ok(Self {
+            out,
+            block_size,
+            block: Vec::new(),
+            in_block: 0,
+            offset: MAGIC.len() as u64,
+            index: Vec::new(),
+            len: 0,
+        })
+    }
+
+    /// Like `create`, recording how the solutions were made. Blocks are
+    /// found through the index, so readers that don't know about the
+    /// provenance skip it.
+    pub fn create_with(
+        path: &Path,
+        block_size: usize,
+        provenance: &Provenance,
+    ) -> io::Result<Self> {
+        let mut archive = Self::create(path, block_size)?;
+        let text = provenance.to_string();
+        archive.out.write_all(text.as_bytes())?;
+        archive.offset += text.len() as u64;
+        Ok(archive)
+    }
+
+    pub fn push(&mut self, placement: &Placement) -> io::Result<()> {
+        self.block.push(placement.placed.len() as u8);
+        for (id, bits) in placement.placed.iter() {
+            encode_pair(&mut self.block, *id, bits);
+        }
+        self.in_block += 1;
+        self.len += 1;
+        if self.in_block == self.block_size {
+            self.flush_block()?;
+        }
+        Ok(())
+    }
+
+    fn flush_block(&mut self) -> io::Result<()> {
+        if self.in_block == 0 {
+            return Ok(());
+        }
+        let compressed = zstd::encode_all(&self.block[..], LEVEL)?;
+        self.out.write_all(&compressed)?;
+        self.index.push((self.offset, self.in_block as u32));
+        self.offset += compressed.len() as u64;
+        self.block.clear();
+        self.in_block = 0;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.flush_block()?;
+        for (offset, count) in self.index.iter() {
+            self.out.write_all(&offset.to_le_bytes())?;
+            self.out.write_all(&count.to_le_bytes())?;
+        }
+        self.out.write_all(&self.offset.to_le_bytes())?;
+        self.out.write_all(MAGIC)?;
+        self.out.flush()
+    }
+}
+
+trait Source: Read + Seek {}
+
+impl<T: Read + Seek> Source for T {}
+
+pub struct ArchiveReader {
+    file: Box<dyn Source>,
+    index: Vec<(u64, u32)>,
+    index_offset: u64,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+impl ArchiveReader {
+    /// Opens an archive, reading it into memory first if it is gzip
+    /// compressed since the blocks can't be found without seeking.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut file: Box<dyn Source> = Box::new(File::open(path)?);
+        let mut magic = [0; 8];
+        file.read_exact(&mut magic)?;
+        if magic.starts_with(&GZIP_MAGIC) {
+            let mut raw = Vec::new();
+            input::open(path)?.read_to_end(&mut raw)?;
+            file = Box::new(Cursor::new(raw));
+            file.read_exact(&mut magic)?;
+        }
+        if &magic != MAGIC {
+            return Err(invalid("not a solution archive"));
+        }
+
+        let mut footer = [0; 16];
+        file.seek(SeekFrom::End(-16))?;
+        file.read_exact(&mut footer)?;
+        if &footer[8..] != MAGIC {
+            return Err(invalid("truncated solution archive"));
+        }
+        let index_offset = u64::from_le_bytes(footer[..8].try_into().unwrap());
+        let end = file.seek(SeekFrom::End(-16))?;
+
+        let mut raw = vec![0; (end - index_offset) as usize];
+        file.seek(SeekFrom::Start(index_offset))?;
+        file.read_exact(&mut raw)?;
+        let index = raw
+            .chunks_exact(12)
+            .map(|entry| {
+                (
+                    u64::from_le_bytes(entry[..8].try_into().unwrap()),
+                    u32::from_le_bytes(entry[8..].try_into().unwrap()),
+                )
+            })
+            .collect();
+
+        Ok(Self {
+            file,
+            index,
+            index_offset,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.iter().map(|(_, count)| *count as usize).sum()
+    }
+
+    /// How the solutions were made, if the archive recorded it.
+    pub fn provenance(&mut self) -> io::Result<Option<Provenance>> {
+        let start = MAGIC.len() as u64;
+        let end = self
+            .index
+            .first()
+            .map_or(self.index_offset, |(first, _)| *first);
+        let mut text = vec![0; end.saturating_sub(start) as usize];
+        self.file.seek(SeekFrom::Start(start))?;
+        self.file.read_exact(&mut text)?;
+        Ok(Provenance::find(&String::from_utf8_lossy(&text)))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    fn block(&mut self, block: usize) -> io::Result<Vec<Solution>> {
+        let (offset, count) = self.index[block];
+        let end = self
+            .index
+            .get(block + 1)
+            .map_or(self.index_offset, |(next, _)| *next);
+        let mut compressed = vec![0; (end - offset) as usize];
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(&mut compressed)?;
+        let raw = zstd::decode_all(&compressed[..])?;
+
+        let mut solutions = Vec::with_capacity(count as usize);
+        let mut pos = 0;
+        for _ in 0..count {
+            let pieces = raw[pos] as usize;
+            pos += 1;
+            let mut solution = Vec::with_capacity(pieces);
+            for _ in 0..pieces {
+                let (pair, len) =
+                    decode_pair(&raw[pos..]).ok_or_else(|| invalid("corrupt solution archive"))?;
+                solution.push(pair);
+                pos += len;
+            }
+            solutions.push(solution);
+        }
+        Ok(solutions)
+    }
+
+    /// Reads solution `n`, decompressing only the block that holds it.
+    pub fn get(&mut self, mut n: usize) -> io::Result<Option<Solution>> {
+        for block in 0..self.index.len() {
+            let count = self.index[block].1 as usize;
+            if n < count {
+                return Ok(self.block(block)?.into_iter().nth(n));
+            }
+            n -= count;
+        }
+        Ok(None)
+    }
+
+    pub fn for_each(&mut self, mut f: impl FnMut(Solution)) -> io::Result<()> {
+        for block in 0..self.index.len() {
+            self.block(block)?.into_iter().for_each(&mut f);
+        }
+        Ok(())
+    }
+}
+
+impl Placement {
+    pub fn from_solution(solution: &Solution) -> Self {
+        let mut placement = Placement::new();
+        for (id, bits) in solution.iter() {
+            placement.place(*id, bits.clone());
+        }
+        placement
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArchiveReader, ArchiveWriter};
+    use crate::{Bitset, Placement};
+    use std::{fs, path::PathBuf};
+
+    /// A file of its own for each test.
+    fn path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "puzzle-cubes-archive-{}-{}",
+            std::process::id(),
+            name
+        ))
+    }
+
+    fn solution(n: u64) -> Placement {
+        let mut placement = Placement::new();
+        placement.place(0, Bitset::from(n));
+        placement.place(1, Bitset::from(n << 32));
+        placement.place(2, Bitset::cell(64 + n as usize * 8));
+        placement.place(3, Bitset::cell(900 + n as usize));
+        placement
+    }
+
+    #[test]
+    fn solutions_read_back_across_blocks() {
+        let path = path("blocks");
+        let mut writer = ArchiveWriter::create(&path, 3).unwrap();
+        for n in 1..=7 {
+            writer.push(&solution(n)).unwrap();
+        }
+        assert_eq!(writer.len(), 7);
+        writer.finish().unwrap();
+
+        let mut reader = ArchiveReader::open(&path).unwrap();
+        assert_eq!(reader.len(), 7);
+        assert_eq!(reader.get(4).unwrap(), Some(solution(5).placed));
+        assert_eq!(reader.get(7).unwrap(), None);
+        let mut all = Vec::new();
+        reader.for_each(|solution| all.push(solution)).unwrap();
+        let expected: Vec<_> = (1..=7).map(|n| solution(n).placed).collect();
+        assert_eq!(all, expected);
+        assert!(reader.provenance().unwrap().is_none());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn empty_archive() {
+        let path = path("empty");
+        ArchiveWriter::create(&path, 3).unwrap().finish().unwrap();
+        let mut reader = ArchiveReader::open(&path).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(reader.get(0).unwrap(), None);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let path = path("other");
+        fs::write(&path, "not an archive at all").unwrap();
+        assert!(ArchiveReader::open(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
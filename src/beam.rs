@@ -0,0 +1,156 @@
+use crate::{grid::NEIGHBOURS, Bitset, Coord, Placement, Puzzle, Solver};
+use std::cmp::Reverse;
+
+/// A partial packing kept in the beam. Cells in `skipped` were given up on
+/// and stay empty.
+struct State {
+    placement: Placement,
+    remaining: Vec<usize>,
+    skipped: Bitset,
+    score: (usize, u32, usize),
+}
+
+impl Puzzle {
+    /// Faces of `bits` that touch a filled cell or the edge of the region,
+    /// higher means a tighter fit.
+    fn contacts(&self, filled: &Bitset, bits: &Bitset) -> u32 {
+        let region = self.target.or(&self.optional);
+        let mut contacts = 0;
+        for cell in self.grid.cells().filter(|c| bits.get(self.grid.index(c))) {
+            for (dx, dy, dz) in NEIGHBOURS {
+                let next = Coord::new(cell.x + dx, cell.y + dy, cell.z + dz);
+                if !self.grid.contains(&next) {
+                    contacts += 1;
+                    continue;
+                }
+                let index = self.grid.index(&next);
+                if !region.get(index) || (filled.get(index) && !bits.get(index)) {
+                    contacts += 1;
+                }
+            }
+        }
+        contacts
+    }
+
+    /// Fewest placements left to any of `remaining`, how boxed in the worst
+    /// off piece is.
+    fn flexibility(&self, blocked: &Bitset, remaining: &[usize]) -> usize {
+        remaining
+            .iter()
+            .map(|id| {
+                self.pieces[*id]
+                    .placements
+                    .iter()
+                    .filter(|bits| blocked.is_disjoint(bits))
+                    .count()
+            })
+            .min()
+            .unwrap_or(usize::MAX)
+    }
+}
+
+impl Solver {
+    /// Heuristic packing that is not exhaustive: each step keeps only the
+    /// `width` best partial placements, ranked by filled volume, then by how
+    /// tightly the pieces touch, then by the room left to the most constrained
+    /// remaining piece. A cell nothing fits in is left empty rather than
+    /// ending the branch. The best packing seen ends up in `placement`, and
+    /// is output as a solution if it is complete.
+    pub fn beam_solve(
+        &mut self,
+        puzzle: &Puzzle,
+        placement: &mut Placement,
+        remaining: &[usize],
+        width: usize,
+    ) -> bool {
+        let start = State {
+            placement: placement.clone(),
+            remaining: remaining.to_vec(),
+            skipped: Bitset::empty(),
+            score: (placement.occupied.len(), 0, 0),
+        };
+        let mut best = start.placement.clone();
+        let mut beam = vec![start];
+        while !beam.is_empty() {
+            let mut next = Vec::new();
+            for state in beam.iter() {
+                self.expand(puzzle, state, &mut next);
+            }
+            next.sort_by_key(|state| Reverse(state.score));
+            next.truncate(width);
+            if let Some(state) = next.first() {
+                if state.placement.occupied.len() > best.occupied.len() {
+                    best = state.placement.clone();
+                }
+            }
+            if let Some(done) = next.iter().find(|state| state.is_complete(puzzle)) {
+                *placement = done.placement.clone();
+                self.emit(puzzle, placement);
+                return true;
+            }
+            beam = next;
+        }
+        *placement = best;
+        false
+    }
+
+    fn expand(&mut self, puzzle: &Puzzle, state: &State, next: &mut Vec<State>) {
+        let blocked = state.placement.occupied.or(&state.skipped);
+        let cell = puzzle.target.without(&blocked).first().map(Bitset::cell);
+        let pieces = match (&cell, state.remaining.is_empty()) {
+            (_, true) => return,
+            (None, false) => &state.remaining[..1],
+            (Some(_), false) => &state.remaining[..],
+        };
+
+        for piece_id in pieces.iter() {
+            if !puzzle.usable(*piece_id, &state.remaining) {
+                continue;
+            }
+            let piece = &puzzle.pieces[*piece_id];
+            let mut remaining = state.remaining.clone();
+            remaining.retain(|id| id != piece_id);
+            for bits in piece.placements.iter() {
+                if cell.as_ref().is_some_and(|cell| bits.is_disjoint(cell))
+                    || !blocked.is_disjoint(bits)
+                {
+                    continue;
+                }
+                let mut placement = state.placement.clone();
+                placement.place(*piece_id, bits.clone());
+                self.nodes += 1;
+                if let Some(histogram) = self.histogram.as_mut() {
+                    histogram.accept(placement.placed.len());
+                }
+                let blocked = blocked.or(bits);
+                let score = (
+                    placement.occupied.len(),
+                    state.score.1 + puzzle.contacts(&state.placement.occupied, bits),
+                    puzzle.flexibility(&blocked, &remaining),
+                );
+                next.push(State {
+                    placement,
+                    remaining: remaining.clone(),
+                    skipped: state.skipped.clone(),
+                    score,
+                });
+            }
+        }
+
+        if let Some(cell) = cell {
+            next.push(State {
+                placement: state.placement.clone(),
+                remaining: state.remaining.clone(),
+                skipped: state.skipped.or(&cell),
+                score: state.score,
+            });
+        }
+    }
+}
+
+impl State {
+    fn is_complete(&self, puzzle: &Puzzle) -> bool {
+        puzzle.only_spares(&self.remaining)
+            && self.placement.occupied.and(&puzzle.target) == puzzle.target
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{grid::Grid, view::View, Bitset, Color, Coord, Orintaion, Piece, Puzzle, MAX_PIECES};
+
+/// Builds a `Puzzle` in code instead of reading it from a file.
+///
+/// ```
+/// use puzzle_cubes::{builder::PuzzleBuilder, Color};
+///
+/// let puzzle = PuzzleBuilder::new("dominoes")
+///     .dims(2, 1, 1)
+///     .piece("A", Color::Red, &[(0, 0, 0)])
+///     .piece("B", Color::Blue, &[(0, 0, 0)])
+///     .build()
+///     .unwrap();
+/// assert_eq!(puzzle.pieces.len(), 2);
+/// ```
+pub struct PuzzleBuilder {
+    name: String,
+    grid: Grid,
+    target: Option<Vec<Coord>>,
+    optional: Vec<Coord>,
+    pieces: Vec<PieceDef>,
+}
+
+struct PieceDef {
+    name: String,
+    color: Color,
+    blocks: Vec<Coord>,
+    /// Least and most copies a solution may use.
+    uses: (usize, usize),
+}
+
+fn coords(cells: &[(i32, i32, i32)]) -> Vec<Coord> {
+    cells.iter().map(|&(x, y, z)| Coord::new(x, y, z)).collect()
+}
+
+impl PuzzleBuilder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            grid: Grid::default(),
+            target: None,
+            optional: Vec::new(),
+            pieces: Vec::new(),
+        }
+    }
+
+    pub fn dims(mut self, x: i32, y: i32, z: i32) -> Self {
+        self.grid = Grid::new(x, y, z);
+        self
+    }
+
+    /// Cells to fill, defaults to the whole box given by `dims`.
+    pub fn target(mut self, cells: &[(i32, i32, i32)]) -> Self {
+        self.target = Some(coords(cells));
+        self
+    }
+
+    /// Marks cells as don't-care: a solution may leave them empty or fill
+    /// them. They are taken out of the target if it includes them.
+    pub fn optional(mut self, cells: &[(i32, i32, i32)]) -> Self {
+        self.optional.extend(coords(cells));
+        self
+    }
+
+    pub fn piece(mut self, name: &str, color: Color, cells: &[(i32, i32, i32)]) -> Self {
+        self.pieces.push(PieceDef {
+            name: name.to_string(),
+            color,
+            blocks: coords(cells),
+            uses: (1, 1),
+        });
+        self
+    }
+
+    /// How many times the last added piece may be used, by default exactly
+    /// once. Copies beyond `min` may be left out of a solution.
+    pub fn uses(mut self, min: usize, max: usize) -> Result<Self, String> {
+        let Some(def) = self.pieces.last_mut() else {
+            return Err("no piece to set uses for".to_string());
+        };
+        if min > max {
+            return Err(format!(
+                "piece {}: minimum uses {} above maximum {}",
+                def.name, min, max
+            ));
+        }
+        def.uses = (min, max);
+        Ok(self)
+    }
+
+    /// Adds a piece drawn as layers, see `Piece::from_layers`.
+    pub fn piece_layers(
+        mut self,
+        name: &str,
+        color: Color,
+        layers: &[&str],
+    ) -> Result<Self, String> {
+        let ori =
+            Orintaion::from_layers(layers).map_err(|err| format!("piece {}: {}", name, err))?;
+        self.pieces.push(PieceDef {
+            name: name.to_string(),
+            color,
+            blocks: ori.blocks,
+            uses: (1, 1),
+        });
+        Ok(self)
+    }
+
+    pub fn build(self) -> Result<Puzzle, String> {
+        let grid = self.grid;
+        grid.check()?;
+
+        let cells = match &self.target {
+            Some(cells) => cells.clone(),
+            None => grid.cells().collect(),
+        };
+        let mut target = Bitset::empty();
+        for cell in cells.iter() {
+            if !grid.contains(cell) {
+                return Err(format!(
+                    "target cell {},{},{} is outside the puzzle",
+                    cell.x, cell.y, cell.z
+                ));
+            }
+            target.set(grid.index(cell));
+        }
+        let mut optional = Bitset::empty();
+        for cell in self.optional.iter() {
+            if !grid.contains(cell) {
+                return Err(format!(
+                    "optional cell {},{},{} is outside the puzzle",
+                    cell.x, cell.y, cell.z
+                ));
+            }
+            optional.set(grid.index(cell));
+        }
+        let count: usize = self.pieces.iter().map(|def| def.uses.1).sum();
+        if count > MAX_PIECES {
+            return Err(format!(
+                "{} pieces counting copies, a puzzle has at most {}",
+                count, MAX_PIECES
+            ));
+        }
+        let target = target.and(&optional.xor(&grid.full()));
+        let allowed = target.or(&optional);
+
+        let mut pieces = Vec::new();
+        for PieceDef {
+            name,
+            color,
+            blocks,
+            uses: (min, max),
+        } in self.pieces
+        {
+            let cells: Vec<_> = blocks.iter().map(|b| (b.x, b.y, b.z)).collect();
+            let ori =
+                Orintaion::from_coords(&cells).map_err(|err| format!("piece {}: {}", name, err))?;
+            if !ori.blocks.iter().all(|b| grid.contains(b)) {
+                return Err(format!(
+                    "piece {} does not fit in {}x{}x{}",
+                    name, grid.x, grid.y, grid.z
+                ));
+            }
+            let piece = Piece::in_region(0, name, color, ori, &grid, &allowed);
+            for copy in 0..max {
+                let piece_id = pieces.len();
+                pieces.push(Piece {
+                    piece_id,
+                    spare: copy >= min,
+                    copy_of: (copy > 0).then(|| piece_id - 1),
+                    ..piece.clone()
+                });
+            }
+        }
+
+        let mut puzzle = Puzzle {
+            name: self.name,
+            grid,
+            target,
+            optional,
+            pieces,
+            view: View::default(),
+        };
+        puzzle.confine_to_regions();
+        Ok(puzzle)
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{
+    error::PuzzleError, grid::Grid, input, parse_checked_blocks, profile, Bitset, Color, Orintaion,
+    Placement, Puzzle, Solver,
+};
+use rayon::prelude::*;
+use std::{io::BufRead, path::Path};
+
+/// A target shape to be built from the catalog's piece set.
+pub struct Figure {
+    pub name: String,
+    pub target: Bitset,
+}
+
+/// One piece set and the figures it can be asked to build. The first line
+/// names the catalog, the puzzle file holding the pieces (relative to the
+/// catalog) and optionally the board size, as in `Soma,soma.txt,3x3x3`.
+/// Every other line is `figure-name,xyz-xyz-...`. Without a size the board
+/// is the smallest one holding every figure.
+pub struct Catalog {
+    pub name: String,
+    pub pieces: Puzzle,
+    pub figures: Vec<Figure>,
+}
+
+pub struct FigureResult {
+    pub name: String,
+    pub volume: usize,
+    pub solutions: usize,
+}
+
+impl Catalog {
+    /// Reads the catalog at `filepath` and the puzzle file with its pieces.
+    /// A line that doesn't make sense gives a `PuzzleError` with its line
+    /// and column, as for puzzle files.
+    pub fn read(filepath: &Path, colors: &[Color]) -> Result<Self, PuzzleError> {
+        let mut lines = input::open(filepath)?.lines();
+        let header = match lines.next() {
+            Some(header) => header?,
+            None => String::new(),
+        };
+        let bad_header =
+            |field: usize, message: String| PuzzleError::at(1, &header, field, message);
+        let top: Vec<&str> = header.split(',').collect();
+        let name = top[0].to_string();
+        let path = match top.get(1).map(|path| path.trim()) {
+            Some(path) if !path.is_empty() => path,
+            _ => {
+                return Err(bad_header(
+                    1,
+                    "expected the name and the puzzle file with the pieces, like Soma,soma.txt"
+                        .to_string(),
+                ))
+            }
+        };
+        let size = match top.get(2).map(|size| size.trim()) {
+            Some(size) if !size.is_empty() => {
+                Some(size.parse::<Grid>().map_err(|err| bad_header(2, err))?)
+            }
+            _ => None,
+        };
+        let dir = filepath.parent().unwrap_or(Path::new(""));
+        let pieces = Puzzle::read(&dir.join(path), colors)
+            .map_err(|err| bad_header(1, format!("pieces {}: {}", path, err)))?;
+
+        let mut shapes = Vec::new();
+        for (index, text) in lines.enumerate() {
+            let text = text?;
+            if text.trim().is_empty() {
+                continue;
+            }
+            let bad =
+                |field: usize, message: String| PuzzleError::at(index + 2, &text, field, message);
+            let line: Vec<&str> = text.split(',').collect();
+            let Some(blocks) = line.get(1) else {
+                return Err(bad(
+                    1,
+                    format!(
+                        "figure {} has no cells, expected e.g. name,000-100-110",
+                        line[0]
+                    ),
+                ));
+            };
+            let blocks = parse_checked_blocks(blocks)
+                .map_err(|err| bad(1, format!("figure {}: {}", line[0], err)))?;
+            let shape = Orintaion::new(blocks).normalise();
+            if let Some(grid) = size {
+                if !shape.blocks.iter().all(|block| grid.contains(block)) {
+                    return Err(bad(
+                        1,
+                        format!(
+                            "figure {} does not fit in {}x{}x{}",
+                            line[0], grid.x, grid.y, grid.z
+                        ),
+                    ));
+                }
+            }
+            shapes.push((line[0].to_string(), shape));
+        }
+
+        let grid = match size {
+            Some(grid) => grid,
+            None => {
+                let cells: Vec<_> = shapes
+                    .iter()
+                    .flat_map(|(_, shape)| shape.blocks.iter().copied())
+                    .collect();
+                let grid = Grid::around(&cells);
+                grid.check()
+                    .map_err(|err| bad_header(2, format!("the figures need {}", err)))?;
+                grid
+            }
+        };
+        let pieces = pieces.with_grid(grid);
+        let figures = shapes
+            .into_iter()
+            .map(|(name, shape)| {
+                let mut target = Bitset::empty();
+                for block in shape.blocks.iter() {
+                    target.set(grid.index(block));
+                }
+                Figure { name, target }
+            })
+            .collect();
+
+        Ok(Catalog {
+            name,
+            pieces,
+            figures,
+        })
+    }
+
+    pub fn solve(&self) -> Vec<FigureResult> {
+        let volume: usize = self.pieces.pieces.iter().map(|p| p.size).sum();
+        self.figures
+            .par_iter()
+            .map(|figure| {
+                let puzzle = self.pieces.with_target(figure.target.clone());
+                let figure_volume = figure.target.len();
+                let mut solver = Solver::new();
+                if figure_volume == volume {
+                    let _span = profile::span("search");
+                    let remaining: Vec<usize> = (0..puzzle.pieces.len()).collect();
+                    solver.fill(&puzzle, &mut Placement::new(), &remaining);
+                }
+                FigureResult {
+                    name: figure.name.clone(),
+                    volume: figure_volume,
+                    solutions: solver.num_solutions,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Every way of choosing `k` of `0..n`, each in increasing order.
+pub(crate) fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    let mut all = Vec::new();
+    let mut chosen: Vec<usize> = (0..k).collect();
+    if k > n {
+        return all;
+    }
+    loop {
+        all.push(chosen.clone());
+        let Some(i) = (0..k).rev().find(|&i| chosen[i] < n - k + i) else {
+            return all;
+        };
+        chosen[i] += 1;
+        for j in i + 1..k {
+            chosen[j] = chosen[j - 1] + 1;
+        }
+    }
+}
+
+impl Catalog {
+    /// Every set of `size` pieces that can build all the figures, as piece
+    /// ids. A set only needs one solution per figure, so each figure is
+    /// searched until its first. Copies of a piece are chosen in order.
+    pub fn sets_building_all(&self, size: usize) -> Result<Vec<Vec<usize>>, String> {
+        let volume = match self.figures.first() {
+            Some(figure) => figure.target.len(),
+            None => return Err("the catalog has no figures".to_string()),
+        };
+        if let Some(figure) = self
+            .figures
+            .iter()
+            .find(|figure| figure.target.len() != volume)
+        {
+            return Err(format!(
+                "figure {} has a different volume from {}",
+                figure.name, self.figures[0].name
+            ));
+        }
+        let puzzles: Vec<Puzzle> = self
+            .figures
+            .iter()
+            .map(|figure| self.pieces.with_target(figure.target.clone()))
+            .collect();
+        let pieces = &self.pieces.pieces;
+        let _span = profile::span("search");
+        Ok(combinations(pieces.len(), size)
+            .into_par_iter()
+            .filter(|set| {
+                set.iter().all(|id| {
+                    pieces[*id]
+                        .copy_of
+                        .is_none_or(|copy_of| set.contains(&copy_of))
+                })
+            })
+            .filter(|set| set.iter().map(|id| pieces[*id].size).sum::<usize>() == volume)
+            .filter(|set| {
+                puzzles.iter().all(|puzzle| {
+                    let mut solver = Solver::new();
+                    solver.solution_limit = Some(1);
+                    solver.fill(puzzle, &mut Placement::new(), set);
+                    solver.num_solutions > 0
+                })
+            })
+            .collect())
+    }
+}
+
+impl Puzzle {
+    /// Copy of the puzzle whose pieces only keep placements inside `target`,
+    /// each within one of its regions.
+    pub fn with_target(&self, target: Bitset) -> Puzzle {
+        let mut pieces = self.pieces.clone();
+        for piece in pieces.iter_mut() {
+            piece.placements.retain(|bits| bits.and(&target) == *bits);
+        }
+        let mut puzzle = Puzzle {
+            name: self.name.clone(),
+            grid: self.grid,
+            target,
+            optional: Bitset::empty(),
+            pieces,
+            view: self.view,
+        };
+        puzzle.confine_to_regions();
+        puzzle
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{Placement, Puzzle};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use std::io::{self, Write};
+
+/// How `write_cells` lays out a solution.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum CellLayout {
+    /// One row per board cell with its coordinates and piece
+    #[default]
+    Rows,
+    /// One block per layer laid out like the board, a piece name per cell
+    Layers,
+}
+
+/// The header of the `rows` layout, written once before any solution.
+pub fn write_cells_header(out: &mut dyn Write, layout: CellLayout) -> io::Result<()> {
+    match layout {
+        CellLayout::Rows => writeln!(out, "solution,x,y,z,piece_id,piece"),
+        CellLayout::Layers => Ok(()),
+    }
+}
+
+impl Puzzle {
+    /// Solution `n` as CSV, each cell of the board with the piece covering
+    /// it, left empty where no piece does. Layers list rows from the top
+    /// down, as `show` prints them.
+    pub fn write_cells(
+        &self,
+        out: &mut dyn Write,
+        n: usize,
+        solution: &Placement,
+        layout: CellLayout,
+    ) -> io::Result<()> {
+        let board = self.target.or(&self.optional);
+        match layout {
+            CellLayout::Rows => {
+                for cell in self.grid.cells() {
+                    if !board.get(self.grid.index(&cell)) {
+                        continue;
+                    }
+                    write!(out, "{},{},{},{},", n, cell.x, cell.y, cell.z)?;
+                    match self.piece_in(solution, cell.x, cell.y, cell.z) {
+                        Some(id) => writeln!(out, "{},{}", id, self.pieces[id].name)?,
+                        None => writeln!(out, ",")?,
+                    }
+                }
+            }
+            CellLayout::Layers => {
+                // Layers without board cells, such as those above a short
+                // box, are left out.
+                let layers = (0..self.grid.z).filter(|z| {
+                    self.grid
+                        .cells()
+                        .any(|c| c.z == *z && board.get(self.grid.index(&c)))
+                });
+                for z in layers {
+                    writeln!(out, "solution {} layer {}", n, z)?;
+                    for y in (0..self.grid.y).rev() {
+                        let row: Vec<&str> = (0..self.grid.x)
+                            .map(|x| match self.piece_in(solution, x, y, z) {
+                                Some(id) => self.pieces[id].name.as_str(),
+                                None => "",
+                            })
+                            .collect();
+                        writeln!(out, "{}", row.join(","))?;
+                    }
+                    writeln!(out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
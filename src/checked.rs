@@ -0,0 +1,102 @@
+use crate::{Bitset, Placement, Puzzle};
+use std::fmt;
+
+/// Why a placement was refused, or why a `Placement` is inconsistent.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlacementError {
+    /// No piece of the puzzle has this id.
+    UnknownPiece(usize),
+    /// The piece is placed already.
+    AlreadyPlaced(String),
+    /// The cells aren't one of the ways the piece fits on the board.
+    NotAPlacement { piece: String, bits: Bitset },
+    /// The piece would cover these cells that are already covered.
+    Overlap { piece: String, cells: Bitset },
+    /// The occupied cells aren't exactly the cells of the placed pieces.
+    Occupied { expected: Bitset, found: Bitset },
+}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlacementError::UnknownPiece(id) => write!(f, "there is no piece {}", id),
+            PlacementError::AlreadyPlaced(piece) => write!(f, "piece {} is placed twice", piece),
+            PlacementError::NotAPlacement { piece, bits } => {
+                write!(f, "piece {} can't cover {:#018x}", piece, bits)
+            }
+            PlacementError::Overlap { piece, cells } => write!(
+                f,
+                "piece {} overlaps another piece at {:#018x}",
+                piece, cells
+            ),
+            PlacementError::Occupied { expected, found } => write!(
+                f,
+                "occupied cells {:#018x} should be {:#018x}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PlacementError {}
+
+/// A piece taken back off by `Placement::pop`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placed {
+    pub piece_id: usize,
+    pub bits: Bitset,
+}
+
+impl Placement {
+    /// Places `piece_id` at `bits` after checking that it is a piece of
+    /// `puzzle` not placed yet, that `bits` is one of its placements and
+    /// that it covers no placed piece. Nothing changes when it fails.
+    ///
+    /// `place` skips these checks for the solver, which only ever makes
+    /// placements it has checked itself.
+    pub fn try_place(
+        &mut self,
+        puzzle: &Puzzle,
+        piece_id: usize,
+        bits: Bitset,
+    ) -> Result<(), PlacementError> {
+        let piece = puzzle
+            .pieces
+            .get(piece_id)
+            .ok_or(PlacementError::UnknownPiece(piece_id))?;
+        if self.placed.iter().any(|(id, _)| *id == piece_id) {
+            return Err(PlacementError::AlreadyPlaced(piece.name.clone()));
+        }
+        if !piece.placements.contains(&bits) {
+            return Err(PlacementError::NotAPlacement {
+                piece: piece.name.clone(),
+                bits,
+            });
+        }
+        if !self.is_valid(&bits) {
+            return Err(PlacementError::Overlap {
+                piece: piece.name.clone(),
+                cells: bits.and(&self.occupied),
+            });
+        }
+        self.place(piece_id, bits);
+        Ok(())
+    }
+
+    /// Checks everything `try_place` would have for each placed piece, in
+    /// order, and that the occupied cells match them, for placements built
+    /// some other way.
+    pub fn check(&self, puzzle: &Puzzle) -> Result<(), PlacementError> {
+        let mut rebuilt = Placement::new();
+        for (id, bits) in self.placed.iter() {
+            rebuilt.try_place(puzzle, *id, bits.clone())?;
+        }
+        if rebuilt.occupied != self.occupied {
+            return Err(PlacementError::Occupied {
+                expected: rebuilt.occupied,
+                found: self.occupied.clone(),
+            });
+        }
+        Ok(())
+    }
+}
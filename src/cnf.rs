@@ -0,0 +1,223 @@
+use crate::{grid::Grid, Bitset, Coord, Piece, Placement, Puzzle};
+use std::io::{self, Write};
+
+/// Clauses saying exactly one, or with `at_least` false at most one, of
+/// `vars` is true. At most one uses the sequential counter encoding, which
+/// needs `vars.len() - 1` extra variables taken from `next_var`.
+fn choose_one(vars: &[usize], at_least: bool, next_var: &mut usize) -> Vec<Vec<i64>> {
+    let mut clauses = Vec::new();
+    if at_least {
+        clauses.push(vars.iter().map(|v| *v as i64).collect());
+    }
+    if vars.len() < 2 {
+        return clauses;
+    }
+    let counters: Vec<i64> = (0..vars.len() - 1)
+        .map(|_| {
+            *next_var += 1;
+            *next_var as i64
+        })
+        .collect();
+    let x = |i: usize| vars[i] as i64;
+    clauses.push(vec![-x(0), counters[0]]);
+    for i in 1..vars.len() - 1 {
+        clauses.push(vec![-x(i), counters[i]]);
+        clauses.push(vec![-counters[i - 1], counters[i]]);
+        clauses.push(vec![-x(i), -counters[i - 1]]);
+    }
+    clauses.push(vec![-x(vars.len() - 1), -counters[vars.len() - 2]]);
+    clauses
+}
+
+impl Piece {
+    /// Orientation index and offset that produce `bits`.
+    fn locate(&self, bits: &Bitset, grid: &Grid) -> Option<(usize, Coord)> {
+        let cells: Vec<Coord> = grid.cells().filter(|c| bits.get(grid.index(c))).collect();
+        let offset = Coord::new(
+            cells.iter().map(|c| c.x).min()?,
+            cells.iter().map(|c| c.y).min()?,
+            cells.iter().map(|c| c.z).min()?,
+        );
+        let index = self.orintations.iter().position(|ori| {
+            ori.blocks.iter().all(|b| {
+                let cell = Coord::new(b.x + offset.x, b.y + offset.y, b.z + offset.z);
+                grid.contains(&cell) && bits.get(grid.index(&cell))
+            })
+        })?;
+        Some((index, offset))
+    }
+}
+
+impl Puzzle {
+    /// Variable `n` (from 1) is the `n`-th placement in this list.
+    fn cnf_variables(&self) -> Vec<(usize, Bitset)> {
+        self.pieces
+            .iter()
+            .flat_map(|piece| {
+                piece
+                    .placements
+                    .iter()
+                    .map(|bits| (piece.piece_id, bits.clone()))
+            })
+            .collect()
+    }
+
+    /// SAT encoding in DIMACS format: one variable per placement, every piece
+    /// placed exactly once (spare pieces at most once), every target cell
+    /// covered exactly once and every don't-care cell at most once. The
+    /// comments map variables back to pieces.
+    pub fn write_cnf(&self, out: &mut dyn Write) -> io::Result<()> {
+        let vars = self.cnf_variables();
+        let mut next_var = vars.len();
+        let mut clauses = Vec::new();
+        for piece in self.pieces.iter() {
+            let own: Vec<usize> = (0..vars.len())
+                .filter(|v| vars[*v].0 == piece.piece_id)
+                .map(|v| v + 1)
+                .collect();
+            clauses.extend(choose_one(&own, !piece.spare, &mut next_var));
+        }
+        for cell in 0..self.grid.len() {
+            let required = self.target.get(cell);
+            if !required && !self.optional.get(cell) {
+                continue;
+            }
+            let covering: Vec<usize> = (0..vars.len())
+                .filter(|v| vars[*v].1.get(cell))
+                .map(|v| v + 1)
+                .collect();
+            clauses.extend(choose_one(&covering, required, &mut next_var));
+        }
+
+        writeln!(out, "c {}", self.name)?;
+        for (n, (piece_id, bits)) in vars.iter().enumerate() {
+            let piece = &self.pieces[*piece_id];
+            match piece.locate(bits, &self.grid) {
+                Some((ori, offset)) => writeln!(
+                    out,
+                    "c var {} piece {} orientation {} offset {}{}{}",
+                    n + 1,
+                    piece.name,
+                    ori,
+                    offset.x,
+                    offset.y,
+                    offset.z
+                )?,
+                None => writeln!(out, "c var {} piece {}", n + 1, piece.name)?,
+            }
+        }
+        writeln!(out, "p cnf {} {}", next_var, clauses.len())?;
+        for clause in clauses.iter() {
+            for literal in clause.iter() {
+                write!(out, "{} ", literal)?;
+            }
+            writeln!(out, "0")?;
+        }
+        Ok(())
+    }
+
+    /// Reads a SAT solver's model (`v` lines of literals, other lines are
+    /// ignored) for the encoding of `write_cnf` and checks that it is a
+    /// solution.
+    pub fn decode_cnf(&self, model: &str) -> Result<Placement, String> {
+        if model.lines().any(|line| line.trim() == "s UNSATISFIABLE") {
+            return Err("the solver found the puzzle unsatisfiable".to_string());
+        }
+        let vars = self.cnf_variables();
+        let mut placement = Placement::new();
+        let literals = model
+            .lines()
+            .filter_map(|line| line.strip_prefix('v'))
+            .flat_map(|line| line.split_whitespace());
+        for literal in literals {
+            let literal: i64 = literal
+                .parse()
+                .map_err(|_| format!("invalid literal {}", literal))?;
+            if literal <= 0 || literal as usize > vars.len() {
+                continue;
+            }
+            let (piece_id, bits) = &vars[literal as usize - 1];
+            placement
+                .try_place(self, *piece_id, bits.clone())
+                .map_err(|err| err.to_string())?;
+        }
+        if let Some(piece) = self
+            .pieces
+            .iter()
+            .filter(|p| !p.spare)
+            .find(|p| placement.placed.iter().all(|(id, _)| *id != p.piece_id))
+        {
+            return Err(format!("piece {} is not placed", piece.name));
+        }
+        if placement.occupied.and(&self.target) != self.target {
+            return Err("the target is not filled".to_string());
+        }
+        Ok(placement)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Placement, Puzzle, Solver, DEFAULT_PALETTE};
+
+    const DOMINOES: &str = "dominoes,2x2x1\nA,red,000-100\nB,blue,000-100\n";
+
+    /// The `v` line setting the variables of `solution`'s placements.
+    fn model(puzzle: &Puzzle, solution: &Placement) -> String {
+        let vars: Vec<String> = puzzle
+            .cnf_variables()
+            .iter()
+            .enumerate()
+            .map(|(n, placed)| match solution.placed.contains(placed) {
+                true => (n + 1).to_string(),
+                false => format!("-{}", n + 1),
+            })
+            .collect();
+        format!("s SATISFIABLE\nv {} 0\n", vars.join(" "))
+    }
+
+    #[test]
+    fn header_counts_clauses() {
+        let puzzle = Puzzle::parse(DOMINOES, DEFAULT_PALETTE).unwrap();
+        let mut out = Vec::new();
+        puzzle.write_cnf(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let header: Vec<&str> = text
+            .lines()
+            .find(|line| line.starts_with("p cnf"))
+            .unwrap()
+            .split_whitespace()
+            .collect();
+        let clauses = text
+            .lines()
+            .filter(|line| !line.starts_with('c') && !line.starts_with('p'))
+            .count();
+        assert_eq!(header[3].parse::<usize>().unwrap(), clauses);
+        // Each domino lies flat four ways on the 2x2 board.
+        let vars = text
+            .lines()
+            .filter(|line| line.starts_with("c var"))
+            .count();
+        assert_eq!(vars, 8);
+    }
+
+    #[test]
+    fn solutions_decode_from_models() {
+        let puzzle = Puzzle::parse(DOMINOES, DEFAULT_PALETTE).unwrap();
+        let mut solver = Solver::new();
+        solver.collected = Some(Vec::new());
+        solver.solve(&puzzle, &mut Placement::new(), &[0, 1]);
+        for solution in solver.collected.unwrap() {
+            let decoded = puzzle.decode_cnf(&model(&puzzle, &solution)).unwrap();
+            assert_eq!(decoded.occupied, solution.occupied);
+            assert_eq!(decoded.placed.len(), 2);
+        }
+    }
+
+    #[test]
+    fn incomplete_models_are_rejected() {
+        let puzzle = Puzzle::parse(DOMINOES, DEFAULT_PALETTE).unwrap();
+        assert!(puzzle.decode_cnf("v 1 0\n").is_err());
+        assert!(puzzle.decode_cnf("s UNSATISFIABLE\n").is_err());
+    }
+}
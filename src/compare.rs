@@ -0,0 +1,114 @@
+use crate::{anneal::Anneal, restart::Restarts, timeline::Timeline, Placement, Puzzle, Solver};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+/// A search configuration that `compare` can run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum Strategy {
+    /// Cover the lowest empty cell first
+    Fill,
+    /// Cover the lowest empty cell first, placing forced pieces before branching
+    FillPropagate,
+    /// Cover the cell with the fewest ways left first, skipping placements
+    /// that leave a cell nothing can cover
+    Solve,
+    /// Cover the cell with the fewest ways left first, skipping placements
+    /// that leave a cell nothing can cover and placing forced pieces before
+    /// branching
+    SolvePropagate,
+    /// Exact cover with dancing links
+    Dlx,
+    /// Randomized restarts looking for one solution
+    Restart,
+    /// Beam search keeping 64 partial placements
+    Beam,
+    /// Simulated annealing, one move per node
+    Anneal,
+}
+
+impl Strategy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Strategy::Fill => "fill",
+            Strategy::FillPropagate => "fill-propagate",
+            Strategy::Solve => "solve",
+            Strategy::SolvePropagate => "solve-propagate",
+            Strategy::Dlx => "dlx",
+            Strategy::Restart => "restart",
+            Strategy::Beam => "beam",
+            Strategy::Anneal => "anneal",
+        }
+    }
+}
+
+/// Outcome of one strategy.
+pub struct Comparison {
+    pub strategy: Strategy,
+    pub solutions: usize,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    /// Time until the first solution, if there was one.
+    pub first: Option<Duration>,
+}
+
+impl Puzzle {
+    /// Runs each strategy on the whole puzzle with the same node limit.
+    /// Solutions are counted but not output.
+    pub fn compare(&self, strategies: &[Strategy], node_limit: u64, seed: u64) -> Vec<Comparison> {
+        let remaining: Vec<usize> = (0..self.pieces.len()).collect();
+        strategies
+            .iter()
+            .map(|strategy| {
+                let mut solver = Solver::new();
+                solver.output = Some(Box::new(io::sink()));
+                solver.timeline = Some(Timeline::new());
+                solver.node_limit = Some(node_limit);
+                solver.propagate =
+                    matches!(strategy, Strategy::FillPropagate | Strategy::SolvePropagate);
+                let mut placement = Placement::new();
+                let start = Instant::now();
+                match strategy {
+                    Strategy::Fill | Strategy::FillPropagate => {
+                        solver.fill(self, &mut placement, &remaining)
+                    }
+                    Strategy::Solve | Strategy::SolvePropagate => {
+                        solver.solve(self, &mut placement, &remaining)
+                    }
+                    Strategy::Dlx => solver.solve_dlx(self, &mut placement, &remaining),
+                    Strategy::Restart => {
+                        let restarts = Restarts {
+                            seed,
+                            budget: 10_000,
+                            luby: true,
+                            max_runs: usize::MAX,
+                        };
+                        solver.restart_solve(self, &mut placement, &remaining, &restarts);
+                    }
+                    Strategy::Beam => {
+                        solver.beam_solve(self, &mut placement, &remaining, 64);
+                    }
+                    Strategy::Anneal => {
+                        solver.anneal_solve(
+                            self,
+                            &mut placement,
+                            &remaining,
+                            &Anneal::new(node_limit, seed),
+                        );
+                    }
+                }
+                Comparison {
+                    strategy: *strategy,
+                    solutions: solver.num_solutions,
+                    nodes: solver.nodes,
+                    elapsed: start.elapsed(),
+                    first: solver.timeline.as_ref().and_then(|t| t.first()),
+                }
+            })
+            .collect()
+    }
+}
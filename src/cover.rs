@@ -0,0 +1,122 @@
+use crate::{grid::NEIGHBOURS, Bitset, Coord, Placement, Puzzle, Solver};
+
+/// The placements of every piece that cover each cell, in the order of the
+/// pieces and their placements, and each cell's face neighbours. See
+/// `Solver::indexed`.
+pub struct CellIndex {
+    cells: Vec<Vec<(usize, Bitset)>>,
+    neighbours: Vec<Bitset>,
+}
+
+impl CellIndex {
+    pub fn new(puzzle: &Puzzle) -> Self {
+        let mut cells = vec![Vec::new(); puzzle.grid.len()];
+        for piece in puzzle.pieces.iter() {
+            for bits in piece.placements.iter() {
+                for cell in bits.iter() {
+                    cells[cell].push((piece.piece_id, bits.clone()));
+                }
+            }
+        }
+        let neighbours = puzzle
+            .grid
+            .cells()
+            .map(|cell| {
+                let mut bits = Bitset::empty();
+                for (dx, dy, dz) in NEIGHBOURS {
+                    let next = Coord::new(cell.x + dx, cell.y + dy, cell.z + dz);
+                    if puzzle.grid.contains(&next) {
+                        bits.set(puzzle.grid.index(&next));
+                    }
+                }
+                bits
+            })
+            .collect();
+        Self { cells, neighbours }
+    }
+
+    /// The placements covering `cell`, by index.
+    pub fn covering(&self, cell: usize) -> &[(usize, Bitset)] {
+        &self.cells[cell]
+    }
+
+    /// The cells sharing a face with `cell`, by index.
+    pub fn neighbours(&self, cell: usize) -> &Bitset {
+        &self.neighbours[cell]
+    }
+}
+
+impl Puzzle {
+    /// The first empty target cell around `occupied` that no placement of
+    /// a piece in `remaining` can cover any more.
+    pub fn uncoverable_cell(
+        &self,
+        index: &CellIndex,
+        occupied: &Bitset,
+        remaining: &[usize],
+    ) -> Option<usize> {
+        for cell in self.target.without(occupied).iter() {
+            let coverable = index
+                .covering(cell)
+                .iter()
+                .any(|(piece_id, bits)| occupied.is_disjoint(bits) && remaining.contains(piece_id));
+            if !coverable {
+                return Some(cell);
+            }
+        }
+        None
+    }
+}
+
+/// How a search picks the empty target cell to cover next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CellChoice {
+    /// The lowest empty cell, see `Solver::fill`.
+    Lowest,
+    /// The cell the fewest placements still cover, see `Solver::solve`.
+    MostConstrained,
+}
+
+impl Solver {
+    /// The empty target cell to cover next by `choice`, with the placements
+    /// of `remaining` that still cover it, or `None` once the target is
+    /// full. A cell nothing can cover any more comes back with no
+    /// placements.
+    pub(crate) fn next_cell(
+        &self,
+        puzzle: &Puzzle,
+        placement: &Placement,
+        remaining: &[usize],
+        choice: CellChoice,
+    ) -> Option<(usize, Vec<(usize, Bitset)>)> {
+        let index = self.cell_index();
+        let mut usable = vec![false; puzzle.pieces.len()];
+        for piece_id in remaining.iter() {
+            usable[*piece_id] = puzzle.usable(*piece_id, remaining);
+        }
+        let fits =
+            |(piece_id, bits): &&(usize, Bitset)| usable[*piece_id] && placement.is_valid(bits);
+        let empty = puzzle.target.without(&placement.occupied);
+        let cell = match choice {
+            CellChoice::Lowest => empty.first()?,
+            CellChoice::MostConstrained => {
+                let mut best: Option<(usize, usize)> = None;
+                for cell in empty.iter() {
+                    // Counting stops once the cell is no better than the best
+                    // so far.
+                    let limit = best.map_or(usize::MAX, |(_, count)| count);
+                    let count = index.covering(cell).iter().filter(fits).take(limit).count();
+                    if count < limit {
+                        best = Some((cell, count));
+                        if count <= 1 {
+                            break;
+                        }
+                    }
+                }
+                best?.0
+            }
+        };
+        let candidates = index.covering(cell).iter().filter(fits).cloned().collect();
+        Some((cell, candidates))
+    }
+}
@@ -0,0 +1,310 @@
+use crate::{Bitset, Placement, Puzzle, Solver};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+
+/// How the search looks for solutions.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum Algorithm {
+    /// Corners first, then the cell with the fewest ways left to cover it
+    #[default]
+    Backtrack,
+    /// Exact cover with dancing links, the most constrained cell or piece
+    /// first
+    Dlx,
+}
+
+/// The exact cover matrix as Knuth's dancing links: node 0 is the root,
+/// then one header per column, then one node per one in the matrix.
+/// Primary columns are linked from the root and must be covered exactly
+/// once, secondary ones at most once.
+struct Links {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    /// Header of each node's column.
+    column: Vec<usize>,
+    /// Row of each node, unused for the root and headers.
+    row: Vec<usize>,
+    /// Nodes in each column, by header.
+    size: Vec<usize>,
+}
+
+impl Links {
+    fn new(primary: usize, secondary: usize) -> Self {
+        let headers = primary + secondary + 1;
+        let mut links = Self {
+            left: (0..headers).collect(),
+            right: (0..headers).collect(),
+            up: (0..headers).collect(),
+            down: (0..headers).collect(),
+            column: (0..headers).collect(),
+            row: vec![usize::MAX; headers],
+            size: vec![0; headers],
+        };
+        for header in 0..=primary {
+            links.left[header] = if header == 0 { primary } else { header - 1 };
+            links.right[header] = if header == primary { 0 } else { header + 1 };
+        }
+        links
+    }
+
+    fn add_row(&mut self, row: usize, columns: &[usize]) {
+        let first = self.left.len();
+        for (i, column) in columns.iter().enumerate() {
+            let node = first + i;
+            self.left.push(if i == 0 {
+                first + columns.len() - 1
+            } else {
+                node - 1
+            });
+            self.right.push(if i == columns.len() - 1 {
+                first
+            } else {
+                node + 1
+            });
+            self.up.push(self.up[*column]);
+            self.down.push(*column);
+            self.column.push(*column);
+            self.row.push(row);
+            let last = self.up[*column];
+            self.down[last] = node;
+            self.up[*column] = node;
+            self.size[*column] += 1;
+        }
+    }
+
+    fn cover(&mut self, column: usize) {
+        self.right[self.left[column]] = self.right[column];
+        self.left[self.right[column]] = self.left[column];
+        let mut i = self.down[column];
+        while i != column {
+            let mut j = self.right[i];
+            while j != i {
+                self.down[self.up[j]] = self.down[j];
+                self.up[self.down[j]] = self.up[j];
+                self.size[self.column[j]] -= 1;
+                j = self.right[j];
+            }
+            i = self.down[i];
+        }
+    }
+
+    fn uncover(&mut self, column: usize) {
+        let mut i = self.up[column];
+        while i != column {
+            let mut j = self.left[i];
+            while j != i {
+                self.size[self.column[j]] += 1;
+                self.down[self.up[j]] = j;
+                self.up[self.down[j]] = j;
+                j = self.left[j];
+            }
+            i = self.up[i];
+        }
+        self.right[self.left[column]] = column;
+        self.left[self.right[column]] = column;
+    }
+
+    /// The primary column with the fewest rows left, `None` once all are
+    /// covered.
+    fn smallest(&self) -> Option<usize> {
+        let mut best = None;
+        let mut column = self.right[0];
+        while column != 0 {
+            if best.is_none_or(|best| self.size[column] < self.size[best]) {
+                best = Some(column);
+            }
+            column = self.right[column];
+        }
+        best
+    }
+}
+
+impl Solver {
+    /// Finds the same solutions as `fill` by solving the exact cover
+    /// problem with dancing links. Every empty target cell and every
+    /// required piece in `remaining` is a column to cover exactly once,
+    /// empty don't-care cells and spare pieces at most once, and every
+    /// placement of a remaining piece is a row. Copies of a piece are kept
+    /// in the order of their lowest cells so swapping them doesn't give a
+    /// new solution.
+    ///
+    /// Propagation, nogoods and warm starts aren't used by this search.
+    pub fn solve_dlx(&mut self, puzzle: &Puzzle, placement: &mut Placement, remaining: &[usize]) {
+        let free = |bits: &Bitset| bits.without(&placement.occupied);
+        let cells: Vec<usize> = free(&puzzle.target)
+            .iter()
+            .chain(free(&puzzle.optional).iter())
+            .collect();
+        let required = cells.len() - free(&puzzle.optional).len();
+        let (needed, spares): (Vec<usize>, Vec<usize>) =
+            remaining.iter().partition(|id| !puzzle.pieces[**id].spare);
+        let primary = required + needed.len();
+        let mut links = Links::new(primary, cells.len() - required + spares.len());
+        // Headers: target cells, required pieces, don't-care cells, spares.
+        let cell_column = |cell: usize| {
+            let index = cells.iter().position(|c| *c == cell).unwrap();
+            match index < required {
+                true => index + 1,
+                false => index + needed.len() + 1,
+            }
+        };
+        let piece_column = |piece_id: usize| match needed.iter().position(|id| *id == piece_id) {
+            Some(index) => required + index + 1,
+            None => {
+                let index = spares.iter().position(|id| *id == piece_id).unwrap();
+                cells.len() + needed.len() + index + 1
+            }
+        };
+        let region = puzzle.target.or(&puzzle.optional);
+        let mut rows = Vec::new();
+        for piece_id in remaining.iter() {
+            for bits in puzzle.pieces[*piece_id].placements.iter() {
+                if !placement.is_valid(bits) || bits.and(&region) != *bits {
+                    continue;
+                }
+                let mut columns = vec![piece_column(*piece_id)];
+                columns.extend(bits.iter().map(cell_column));
+                links.add_row(rows.len(), &columns);
+                rows.push((*piece_id, bits.clone()));
+            }
+        }
+        let mut lowest = vec![None; puzzle.pieces.len()];
+        for (piece_id, bits) in placement.placed.iter() {
+            lowest[*piece_id] = bits.first();
+        }
+        self.dlx_search(puzzle, placement, &mut links, &rows, &mut lowest);
+    }
+
+    fn dlx_search(
+        &mut self,
+        puzzle: &Puzzle,
+        placement: &mut Placement,
+        links: &mut Links,
+        rows: &[(usize, Bitset)],
+        lowest: &mut [Option<usize>],
+    ) {
+        let Some(column) = links.smallest() else {
+            if copies_in_order(puzzle, lowest) && self.accepts(puzzle, placement) {
+                self.emit(puzzle, placement);
+            }
+            return;
+        };
+        links.cover(column);
+        let mut node = links.down[column];
+        while node != column {
+            if self.should_stop() {
+                break;
+            }
+            let (piece_id, bits) = &rows[links.row[node]];
+            let piece_id = *piece_id;
+            let low = bits.first().unwrap_or_default();
+            if keeps_copy_order(puzzle, lowest, piece_id, low) {
+                let piece = &puzzle.pieces[piece_id];
+                self.place(placement, piece, bits);
+                lowest[piece_id] = Some(low);
+                let mut j = links.right[node];
+                while j != node {
+                    links.cover(links.column[j]);
+                    j = links.right[j];
+                }
+                self.dlx_search(puzzle, placement, links, rows, lowest);
+                let mut j = links.left[node];
+                while j != node {
+                    links.uncover(links.column[j]);
+                    j = links.left[j];
+                }
+                lowest[piece_id] = None;
+                self.backtrack(placement, piece);
+            }
+            node = links.down[node];
+        }
+        links.uncover(column);
+    }
+}
+
+/// Whether `piece_id` with its lowest cell at `low` sits after the copy
+/// before it and before the copy after it, where those are placed.
+fn keeps_copy_order(
+    puzzle: &Puzzle,
+    lowest: &[Option<usize>],
+    piece_id: usize,
+    low: usize,
+) -> bool {
+    let before = puzzle.pieces[piece_id]
+        .copy_of
+        .and_then(|previous| lowest[previous]);
+    let after = puzzle
+        .pieces
+        .get(piece_id + 1)
+        .filter(|next| next.copy_of == Some(piece_id))
+        .and_then(|next| lowest[next.piece_id]);
+    before.is_none_or(|before| before < low) && after.is_none_or(|after| low < after)
+}
+
+/// Whether every placed copy has the copy before it placed too, so spare
+/// copies are used in order.
+fn copies_in_order(puzzle: &Puzzle, lowest: &[Option<usize>]) -> bool {
+    puzzle.pieces.iter().all(|piece| {
+        lowest[piece.piece_id].is_none()
+            || piece
+                .copy_of
+                .is_none_or(|previous| lowest[previous].is_some())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Placement, Puzzle, Solver, DEFAULT_PALETTE};
+    use std::path::Path;
+
+    /// Solutions found by dancing links and by backtracking.
+    fn counts(puzzle: &Puzzle) -> (usize, usize) {
+        let remaining: Vec<usize> = (0..puzzle.pieces.len()).collect();
+        let mut dlx = Solver::new();
+        dlx.count_only = true;
+        dlx.solve_dlx(puzzle, &mut Placement::new(), &remaining);
+        let mut backtrack = Solver::new();
+        backtrack.count_only = true;
+        backtrack.solve(puzzle, &mut Placement::new(), &remaining);
+        (dlx.num_solutions, backtrack.num_solutions)
+    }
+
+    #[test]
+    fn soma_solutions() {
+        let puzzle = Puzzle::read(Path::new("puzzles/soma.txt"), DEFAULT_PALETTE).unwrap();
+        assert_eq!(counts(&puzzle), (11520, 11520));
+    }
+
+    #[test]
+    fn copies_spares_and_dont_care_cells() {
+        for text in [
+            "copies,4x1x1\nI,red,000-100,2\n",
+            "spare,4x1x1\nI,red,000-100\nA,blue,000,1-3\n",
+            "optional,3x1x1,200\nI,red,000-100\nA,blue,000,0-1\n",
+        ] {
+            let puzzle = Puzzle::parse(text, DEFAULT_PALETTE).unwrap();
+            let (dlx, backtrack) = counts(&puzzle);
+            assert_eq!(dlx, backtrack, "{}", text);
+        }
+    }
+
+    #[test]
+    fn starts_from_a_partial_placement() {
+        let puzzle = Puzzle::parse(
+            "dominoes,2x2x1\nA,red,000-100\nB,blue,000-100\n",
+            DEFAULT_PALETTE,
+        )
+        .unwrap();
+        let mut placement = Placement::new();
+        placement.place(0, puzzle.pieces[0].placements[0].clone());
+        let mut solver = Solver::new();
+        solver.collected = Some(Vec::new());
+        solver.solve_dlx(&puzzle, &mut placement, &[1]);
+        let collected = solver.collected.unwrap();
+        assert_eq!(collected.len(), 1);
+        assert_eq!(collected[0].occupied, puzzle.target);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{grid::Grid, Bitset, Piece};
+use std::io::{self, Write};
+
+struct Node {
+    parent: usize,
+    label: String,
+    solutions: usize,
+    /// Some children were left out by the depth or node limit.
+    cut: bool,
+}
+
+/// The top of the search tree, kept to be drawn with Graphviz. Each node is
+/// a placement, counted with the solutions found below it; placements
+/// deeper than `max_depth` or beyond the first `max_nodes` are left out but
+/// their solutions still count towards their ancestors.
+pub struct SearchTree {
+    grid: Grid,
+    max_depth: usize,
+    max_nodes: usize,
+    nodes: Vec<Node>,
+    /// The node of each placement on the current path, if it was kept, and
+    /// the solutions found below it so far.
+    path: Vec<(Option<usize>, usize)>,
+}
+
+impl SearchTree {
+    pub fn new(grid: Grid, max_depth: usize, max_nodes: usize) -> Self {
+        let root = Node {
+            parent: 0,
+            label: "start".to_string(),
+            solutions: 0,
+            cut: false,
+        };
+        Self {
+            grid,
+            max_depth,
+            max_nodes,
+            nodes: vec![root],
+            path: vec![(Some(0), 0)],
+        }
+    }
+
+    pub fn place(&mut self, piece: &Piece, bits: &Bitset) {
+        let parent = self.path.last().and_then(|(node, _)| *node);
+        let node = match parent {
+            Some(parent)
+                if self.path.len() <= self.max_depth && self.nodes.len() < self.max_nodes =>
+            {
+                let cells: Vec<String> = (0..self.grid.len())
+                    .filter(|index| bits.get(*index))
+                    .map(|index| self.grid.coord(index))
+                    .map(|cell| format!("{}{}{}", cell.x, cell.y, cell.z))
+                    .collect();
+                self.nodes.push(Node {
+                    parent,
+                    label: format!("{}\\n{}", piece.name.replace('"', "\\\""), cells.join("-")),
+                    solutions: 0,
+                    cut: false,
+                });
+                Some(self.nodes.len() - 1)
+            }
+            Some(parent) => {
+                self.nodes[parent].cut = true;
+                None
+            }
+            None => None,
+        };
+        self.path.push((node, 0));
+    }
+
+    pub fn backtrack(&mut self) {
+        let Some((node, solutions)) = self.path.pop() else {
+            return;
+        };
+        if let Some(node) = node {
+            self.nodes[node].solutions = solutions;
+        }
+        if let Some((_, parent)) = self.path.last_mut() {
+            *parent += solutions;
+        }
+    }
+
+    pub fn solution(&mut self) {
+        if let Some((_, solutions)) = self.path.last_mut() {
+            *solutions += 1;
+        }
+    }
+
+    /// Writes the tree as a DOT graph. Nodes with solutions below them are
+    /// green and nodes with children left out have a dashed border.
+    pub fn write(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        while self.path.len() > 1 {
+            self.backtrack();
+        }
+        self.nodes[0].solutions = self.path[0].1;
+        writeln!(out, "digraph search {{")?;
+        writeln!(
+            out,
+            "  node [shape=box, style=filled, fillcolor=lightgray];"
+        )?;
+        for (index, node) in self.nodes.iter().enumerate() {
+            let mut attrs = format!("label=\"{}\\n{} solutions\"", node.label, node.solutions);
+            if node.solutions > 0 {
+                attrs.push_str(", fillcolor=palegreen");
+            }
+            if node.cut {
+                attrs.push_str(", style=\"filled,dashed\"");
+            }
+            writeln!(out, "  n{} [{}];", index, attrs)?;
+        }
+        for (index, node) in self.nodes.iter().enumerate().skip(1) {
+            writeln!(out, "  n{} -> n{};", node.parent, index)?;
+        }
+        writeln!(out, "}}")
+    }
+}
@@ -0,0 +1,120 @@
+use std::{fmt, io, path::Path};
+
+/// Why a puzzle file couldn't be read.
+#[derive(Debug)]
+pub enum PuzzleError {
+    /// The file couldn't be opened or read.
+    Io(io::Error),
+    /// The file has no header line.
+    Empty,
+    /// A line doesn't make sense. `line` and `column` count from 1 and
+    /// `text` is the whole line, to show where the problem is.
+    Syntax {
+        line: usize,
+        column: usize,
+        text: String,
+        message: String,
+    },
+}
+
+impl PuzzleError {
+    /// The error on `line`, pointing at its field number `field`, or past
+    /// the end if the line has fewer.
+    pub(crate) fn at(line: usize, text: &str, field: usize, message: String) -> Self {
+        let column = 1 + text
+            .split(',')
+            .take(field)
+            .map(|field| field.len() + 1)
+            .sum::<usize>();
+        PuzzleError::Syntax {
+            line,
+            column: column.min(text.len() + 1),
+            text: text.to_string(),
+            message,
+        }
+    }
+
+    /// The error as a compiler would show it: where in `path` it is, then
+    /// the line with the column marked.
+    ///
+    /// ```
+    /// use puzzle_cubes::error::PuzzleError;
+    /// use std::path::Path;
+    ///
+    /// let err = PuzzleError::Syntax {
+    ///     line: 2,
+    ///     column: 3,
+    ///     text: "L,purple,000-100".to_string(),
+    ///     message: "unknown color purple".to_string(),
+    /// };
+    /// assert_eq!(
+    ///     err.diagnostic(Path::new("cube.txt")),
+    ///     "cube.txt:2:3: unknown color purple\n  |\n2 | L,purple,000-100\n  |   ^"
+    /// );
+    /// ```
+    pub fn diagnostic(&self, path: &Path) -> String {
+        match self {
+            PuzzleError::Syntax {
+                line,
+                column,
+                text,
+                message,
+            } => {
+                let gutter = " ".repeat(line.to_string().len());
+                format!(
+                    "{}:{}:{}: {}\n{} |\n{} | {}\n{} | {}^",
+                    path.display(),
+                    line,
+                    column,
+                    message,
+                    gutter,
+                    line,
+                    text,
+                    gutter,
+                    " ".repeat(column - 1)
+                )
+            }
+            _ => format!("{}: {}", path.display(), self),
+        }
+    }
+}
+
+impl fmt::Display for PuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PuzzleError::Io(err) => write!(f, "{}", err),
+            PuzzleError::Empty => write!(f, "empty puzzle file, expected a header like cube,4x4x4"),
+            PuzzleError::Syntax {
+                line,
+                column,
+                message,
+                ..
+            } => write!(f, "line {}, column {}: {}", line, column, message),
+        }
+    }
+}
+
+impl std::error::Error for PuzzleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PuzzleError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PuzzleError {
+    fn from(err: io::Error) -> Self {
+        PuzzleError::Io(err)
+    }
+}
+
+/// For readers of other files that include a puzzle, such as catalogs.
+impl From<PuzzleError> for io::Error {
+    fn from(err: PuzzleError) -> Self {
+        match err {
+            PuzzleError::Io(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err.to_string()),
+        }
+    }
+}
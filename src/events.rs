@@ -0,0 +1,49 @@
+use crate::{Bitset, Placement};
+
+/// Placements between `Event::Progress` reports.
+pub(crate) const PROGRESS_INTERVAL: u64 = 1 << 16;
+
+/// Told of an event with the board as it is then, while the search waits;
+/// returns whether the search should go on. See `Solver::pause`.
+pub type Pause = Box<dyn FnMut(&Placement, &Event) -> bool + Send>;
+
+/// What the solver is doing, sent as it happens so a front end can follow
+/// the search live.
+///
+/// ```
+/// use puzzle_cubes::{builder::PuzzleBuilder, events::Event, Color, Placement, Solver};
+/// use std::sync::mpsc;
+///
+/// let puzzle = PuzzleBuilder::new("bar")
+///     .dims(2, 1, 1)
+///     .piece("I", Color::Red, &[(0, 0, 0), (1, 0, 0)])
+///     .build()
+///     .unwrap();
+/// let (sender, receiver) = mpsc::sync_channel(16);
+/// let mut solver = Solver::new();
+/// solver.events = Some(sender);
+/// solver.fill(&puzzle, &mut Placement::new(), &[0]);
+/// drop(solver);
+/// let events: Vec<Event> = receiver.iter().collect();
+/// assert!(matches!(events[1], Event::SolutionFound { index: 0 }));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    PiecePlaced {
+        piece_id: usize,
+        bits: Bitset,
+        /// Pieces placed before this one.
+        depth: usize,
+    },
+    Backtracked {
+        piece_id: usize,
+        depth: usize,
+    },
+    SolutionFound {
+        index: usize,
+    },
+    Progress {
+        nodes: u64,
+        solutions: usize,
+    },
+}
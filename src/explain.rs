@@ -0,0 +1,190 @@
+use crate::{Bitset, Placement, Puzzle};
+use std::io::{self, Write};
+
+const RULES: &str = "\
+The search follows three rules:
+  1. Cover the lowest empty cell next. Every solution covers it, so each
+     solution is found exactly once.
+  2. Skip placements that overlap pieces already placed.
+  3. Prune a placement that leaves some remaining piece no room at all.
+When every option for a cell has failed, the last piece is taken back off.
+";
+
+struct Narrator<'a> {
+    puzzle: &'a Puzzle,
+    out: &'a mut dyn Write,
+    max_steps: usize,
+    steps: usize,
+    solutions: usize,
+    /// Set when a step beyond `max_steps` was due.
+    cut: bool,
+}
+
+impl<'a> Narrator<'a> {
+    fn say(&mut self, depth: usize, text: String) -> io::Result<()> {
+        writeln!(self.out, "{:indent$}{}", "", text, indent = depth * 2)
+    }
+
+    fn name(&self, piece_id: usize) -> &'a str {
+        &self.puzzle.pieces[piece_id].name
+    }
+
+    fn search(&mut self, placement: &mut Placement, remaining: &[usize]) -> io::Result<()> {
+        let puzzle = self.puzzle;
+        let depth = placement.placed.len();
+        let Some(index) = puzzle.target.without(&placement.occupied).first() else {
+            if puzzle.only_spares(remaining) {
+                self.solutions += 1;
+                self.say(
+                    depth,
+                    format!("Every cell is covered: solution {}.", self.solutions),
+                )?;
+                return puzzle.show(self.out, placement);
+            }
+            let left: Vec<&str> = remaining.iter().map(|id| self.name(*id)).collect();
+            return self.say(
+                depth,
+                format!(
+                    "Every cell is covered but {} must still be used, a dead end.",
+                    left.join(", ")
+                ),
+            );
+        };
+
+        let cell = Bitset::cell(index);
+        let cell_name = puzzle.format_cells(&cell);
+        let candidates: Vec<(usize, Bitset)> = remaining
+            .iter()
+            .filter(|id| puzzle.usable(**id, remaining))
+            .flat_map(|id| {
+                puzzle.pieces[*id]
+                    .placements
+                    .iter()
+                    .filter(|bits| !bits.is_disjoint(&cell))
+                    .map(|bits| (*id, bits.clone()))
+            })
+            .collect();
+        let fits: Vec<(usize, Bitset)> = candidates
+            .iter()
+            .filter(|(_, bits)| placement.is_valid(bits))
+            .cloned()
+            .collect();
+        let reason = match candidates.len() {
+            0 => "no remaining piece can cover it".to_string(),
+            n => format!(
+                "{} placements cover it, {} of them overlap placed pieces (rule 2)",
+                n,
+                n - fits.len()
+            ),
+        };
+        self.say(
+            depth,
+            format!("Cell {} is the lowest empty cell: {}.", cell_name, reason),
+        )?;
+
+        for (piece_id, bits) in fits {
+            let new_remaining: Vec<usize> = remaining
+                .iter()
+                .copied()
+                .filter(|id| *id != piece_id)
+                .collect();
+            let occupied = placement.occupied.or(&bits);
+            let stranded = new_remaining
+                .iter()
+                .map(|id| &puzzle.pieces[*id])
+                .filter(|piece| !piece.spare)
+                .find(|piece| piece.placements.iter().all(|b| !occupied.is_disjoint(b)));
+            if let Some(stranded) = stranded {
+                self.say(
+                    depth,
+                    format!(
+                        "{} on {} would leave no room for {}, pruned (rule 3).",
+                        self.name(piece_id),
+                        puzzle.format_cells(&bits),
+                        stranded.name
+                    ),
+                )?;
+                continue;
+            }
+            if self.steps == self.max_steps {
+                self.cut = true;
+                return Ok(());
+            }
+            self.steps += 1;
+            self.say(
+                depth,
+                format!(
+                    "Step {}: place {} on {}.",
+                    self.steps,
+                    self.name(piece_id),
+                    puzzle.format_cells(&bits)
+                ),
+            )?;
+            placement.place(piece_id, bits);
+            self.search(placement, &new_remaining)?;
+            placement.pop();
+            if self.cut {
+                return Ok(());
+            }
+            self.say(depth, format!("Take {} back off.", self.name(piece_id)))?;
+        }
+        match depth {
+            0 => self.say(
+                depth,
+                format!("Every option for cell {} has been tried.", cell_name),
+            ),
+            _ => self.say(
+                depth,
+                format!(
+                    "Every option for cell {} has been tried, so back up.",
+                    cell_name
+                ),
+            ),
+        }
+    }
+}
+
+impl Puzzle {
+    /// Solves the puzzle from `placement` while narrating every decision, for
+    /// teaching how backtracking works. Stops after `max_steps` placements.
+    /// Returns the number of solutions found.
+    pub fn explain(
+        &self,
+        out: &mut dyn Write,
+        placement: &Placement,
+        max_steps: usize,
+    ) -> io::Result<usize> {
+        write!(out, "{}", RULES)?;
+        writeln!(out)?;
+        let remaining: Vec<usize> = self
+            .pieces
+            .iter()
+            .map(|piece| piece.piece_id)
+            .filter(|id| placement.placed.iter().all(|(placed, _)| placed != id))
+            .collect();
+        let mut narrator = Narrator {
+            puzzle: self,
+            out,
+            max_steps,
+            steps: 0,
+            solutions: 0,
+            cut: false,
+        };
+        narrator.search(&mut placement.clone(), &remaining)?;
+        let (steps, solutions, cut) = (narrator.steps, narrator.solutions, narrator.cut);
+        writeln!(out)?;
+        match cut {
+            true => writeln!(
+                out,
+                "Stopped after {} steps with {} solutions found so far.",
+                steps, solutions
+            )?,
+            false => writeln!(
+                out,
+                "The search is complete: {} steps, {} solutions.",
+                steps, solutions
+            )?,
+        }
+        Ok(solutions)
+    }
+}
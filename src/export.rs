@@ -0,0 +1,115 @@
+use crate::{grid::NEIGHBOURS, Coord, Placement, Puzzle};
+use std::io::{self, Write};
+
+/// The corners of the face of a unit cube on the side `normal` points to,
+/// counterclockwise seen from outside so the face points out.
+fn face(normal: (i32, i32, i32)) -> [[i32; 3]; 4] {
+    let n = [normal.0, normal.1, normal.2];
+    let a = n.iter().position(|d| *d != 0).unwrap();
+    let (b, c) = ((a + 1) % 3, (a + 2) % 3);
+    let mut corners = [(0, 0), (1, 0), (1, 1), (0, 1)];
+    if n[a] < 0 {
+        corners.reverse();
+    }
+    corners.map(|(u, v)| {
+        let mut corner = [0; 3];
+        corner[a] = (n[a] > 0) as i32;
+        corner[b] = u;
+        corner[c] = v;
+        corner
+    })
+}
+
+impl Puzzle {
+    /// The cells of each placed piece, in the order they were placed.
+    fn placed_cells(&self, solution: &Placement) -> Vec<(usize, Vec<Coord>)> {
+        solution
+            .placed
+            .iter()
+            .map(|(id, bits)| {
+                let cells = self
+                    .grid
+                    .cells()
+                    .filter(|cell| bits.get(self.grid.index(cell)))
+                    .collect();
+                (*id, cells)
+            })
+            .collect()
+    }
+
+    /// `solution` as an OpenSCAD model, each piece a union of `size`
+    /// millimetre cubes in its color, `z` up.
+    pub fn write_scad(
+        &self,
+        out: &mut dyn Write,
+        solution: &Placement,
+        size: f64,
+    ) -> io::Result<()> {
+        writeln!(out, "// {}, {} mm cubes", self.name, size)?;
+        for (id, cells) in self.placed_cells(solution) {
+            let piece = &self.pieces[id];
+            let (r, g, b) = self.rgb(piece.color);
+            writeln!(
+                out,
+                "color([{:.3}, {:.3}, {:.3}]) union() {{ // {}",
+                r as f64 / 255.0,
+                g as f64 / 255.0,
+                b as f64 / 255.0,
+                piece.name
+            )?;
+            for cell in cells.iter() {
+                writeln!(
+                    out,
+                    "    translate([{}, {}, {}]) cube({});",
+                    cell.x as f64 * size,
+                    cell.y as f64 * size,
+                    cell.z as f64 * size,
+                    size
+                )?;
+            }
+            writeln!(out, "}}")?;
+        }
+        Ok(())
+    }
+
+    /// `solution` as an ASCII STL mesh of `size` millimetre cubes, `z` up.
+    /// Each piece is its own solid, named after it, with only the faces on
+    /// its outside. STL has no colors or comments, so neither are written.
+    pub fn write_stl(
+        &self,
+        out: &mut dyn Write,
+        solution: &Placement,
+        size: f64,
+    ) -> io::Result<()> {
+        for (id, cells) in self.placed_cells(solution) {
+            let name = self.pieces[id].name.replace(char::is_whitespace, "_");
+            writeln!(out, "solid {}", name)?;
+            for cell in cells.iter() {
+                for (dx, dy, dz) in NEIGHBOURS {
+                    if cells.contains(&Coord::new(cell.x + dx, cell.y + dy, cell.z + dz)) {
+                        continue;
+                    }
+                    let corners = face((dx, dy, dz)).map(|[x, y, z]| {
+                        [
+                            (cell.x + x) as f64 * size,
+                            (cell.y + y) as f64 * size,
+                            (cell.z + z) as f64 * size,
+                        ]
+                    });
+                    for triangle in [[0, 1, 2], [0, 2, 3]] {
+                        writeln!(out, "  facet normal {} {} {}", dx, dy, dz)?;
+                        writeln!(out, "    outer loop")?;
+                        for corner in triangle {
+                            let [x, y, z] = corners[corner];
+                            writeln!(out, "      vertex {} {} {}", x, y, z)?;
+                        }
+                        writeln!(out, "    endloop")?;
+                        writeln!(out, "  endfacet")?;
+                    }
+                }
+            }
+            writeln!(out, "endsolid {}", name)?;
+        }
+        Ok(())
+    }
+}
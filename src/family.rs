@@ -0,0 +1,56 @@
+use crate::{catalog::combinations, Bitset, Placement};
+use std::{cmp::Reverse, collections::HashMap};
+
+/// Solutions linked by sharing placements, with the placements all of them
+/// have in common.
+pub struct Family {
+    /// Indices of the solutions, the first being the representative.
+    pub members: Vec<usize>,
+    pub core: Vec<(usize, Bitset)>,
+}
+
+fn root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/// Groups `solutions` into families, two solutions being in one family when
+/// they place at least `shared` pieces identically, or are both linked that
+/// way to a third. Families are ordered largest first.
+pub fn families(solutions: &[Placement], shared: usize) -> Vec<Family> {
+    let mut parents: Vec<usize> = (0..solutions.len()).collect();
+    let mut first: HashMap<Vec<(usize, Bitset)>, usize> = HashMap::new();
+    for (index, solution) in solutions.iter().enumerate() {
+        let mut placed = solution.placed.clone();
+        placed.sort_by_key(|(id, _)| *id);
+        for chosen in combinations(placed.len(), shared) {
+            let key = chosen.iter().map(|i| placed[*i].clone()).collect();
+            let other = *first.entry(key).or_insert(index);
+            let (a, b) = (root(&mut parents, index), root(&mut parents, other));
+            parents[a.max(b)] = a.min(b);
+        }
+    }
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for index in 0..solutions.len() {
+        members
+            .entry(root(&mut parents, index))
+            .or_default()
+            .push(index);
+    }
+    let mut families: Vec<Family> = members
+        .into_values()
+        .map(|members| {
+            let mut core = solutions[members[0]].placed.clone();
+            for member in members.iter() {
+                core.retain(|placement| solutions[*member].placed.contains(placement));
+            }
+            core.sort_by_key(|(id, _)| *id);
+            Family { members, core }
+        })
+        .collect();
+    families.sort_by_key(|family| (Reverse(family.members.len()), family.members[0]));
+    families
+}
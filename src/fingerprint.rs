@@ -0,0 +1,128 @@
+use crate::{Orintaion, Piece, Puzzle};
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = FNV_OFFSET;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+impl Orintaion {
+    /// Blocks sorted and written as `xyz-xyz-...`, used to compare shapes
+    /// independently of the order the blocks were listed in.
+    pub fn sorted_key(&self) -> String {
+        let mut blocks: Vec<(i32, i32, i32)> =
+            self.blocks.iter().map(|b| (b.x, b.y, b.z)).collect();
+        blocks.sort();
+        blocks
+            .iter()
+            .map(|(x, y, z)| format!("{}{}{}", x, y, z))
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+}
+
+impl Piece {
+    /// Smallest key over all orientations, so any rotation of the same shape
+    /// gives the same form.
+    pub fn canonical_form(&self) -> String {
+        self.orintations
+            .iter()
+            .map(|ori| ori.sorted_key())
+            .min()
+            .unwrap_or_default()
+    }
+
+    /// `canonical_form` marked if the piece can be turned over or left out,
+    /// so only pieces that can stand in for each other share a form.
+    pub fn marked_form(&self) -> String {
+        let mut form = self.canonical_form();
+        if self.flips {
+            form.push_str(" flip");
+        }
+        if self.spare {
+            form.push_str(" spare");
+        }
+        form
+    }
+}
+
+impl Puzzle {
+    /// Canonical description of the puzzle. Names, colors and piece order are
+    /// ignored; the dimensions, the target and don't-care cells, and so the
+    /// blocked ones, and the multiset of piece shapes remain. Each shape is
+    /// marked if the piece can be turned over or left out, and a piece used
+    /// more than once is there once per use.
+    pub fn canonical_form(&self) -> String {
+        let mut shapes: Vec<String> = self.pieces.iter().map(Piece::marked_form).collect();
+        shapes.sort();
+        format!(
+            "{}x{}x{};{:x};{:x};{}",
+            self.grid.x,
+            self.grid.y,
+            self.grid.z,
+            self.target,
+            self.optional,
+            shapes.join(";")
+        )
+    }
+
+    pub fn fingerprint(&self) -> u64 {
+        fnv1a(self.canonical_form().as_bytes())
+    }
+
+    pub fn same_as(&self, other: &Puzzle) -> bool {
+        self.canonical_form() == other.canonical_form()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Puzzle, DEFAULT_PALETTE};
+
+    fn fingerprint(text: &str) -> u64 {
+        Puzzle::parse(text, DEFAULT_PALETTE).unwrap().fingerprint()
+    }
+
+    #[test]
+    fn ignores_names_colors_order_and_rotation() {
+        assert_eq!(
+            fingerprint("a,3x2x1\nL,red,000-100-010\nI,blue,000-100-200\n"),
+            fingerprint("b,3x2x1\nJ,green,000-100-200\nM,white,000-010-110\n")
+        );
+    }
+
+    #[test]
+    fn tells_puzzles_apart() {
+        let base = "p,3x2x1\nL,red,000-100-010\nI,blue,000-100-200\n";
+        let fingerprints = [
+            fingerprint(base),
+            fingerprint(&base.replace("3x2x1", "3x2x1,210")),
+            fingerprint(&base.replace("3x2x1", "3x2x1,,,210")),
+            fingerprint(&base.replace("200\n", "200,0-1\n")),
+            fingerprint(&base.replace("010\n", "010,1,flip\n")),
+        ];
+        for (i, a) in fingerprints.iter().enumerate() {
+            for b in fingerprints[i + 1..].iter() {
+                assert_ne!(a, b);
+            }
+        }
+    }
+    #[test]
+    fn target_as_cells_is_the_blocked_board() {
+        let base = "p,3x2x1\nL,red,000-100-010\nI,blue,000-100-200\n";
+        assert_eq!(
+            fingerprint(&base.replace("3x2x1", "000-100-200-010-110")),
+            fingerprint(&base.replace("3x2x1", "3x2x1,,,210"))
+        );
+        assert_eq!(
+            fingerprint(&base.replace("3x2x1", "000-100-200-010-110-210")),
+            fingerprint(base)
+        );
+    }
+}
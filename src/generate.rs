@@ -0,0 +1,163 @@
+use crate::{
+    builder::PuzzleBuilder,
+    grid::{Grid, NEIGHBOURS},
+    view::View,
+    Bitset, Coord, Orintaion, Piece, Puzzle, DEFAULT_PALETTE,
+};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use std::collections::HashSet;
+
+/// A complete set of polycubes that can be generated instead of typed in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum StandardSet {
+    /// The 8 pieces of four cubes
+    Tetracubes,
+    /// The 29 pieces of five cubes, mirror images counted separately
+    Pentacubes,
+    /// The 12 flat pieces of five squares
+    Pentominoes,
+}
+
+/// Flat shapes by their usual letter, used to name the flat pieces of a set.
+const LETTERS: &[(&str, &str)] = &[
+    ("I", "####"),
+    ("O", "##/##"),
+    ("T", "###/.#."),
+    ("L", "###/#.."),
+    ("S", "##./.##"),
+    ("F", ".##/##./.#."),
+    ("I", "#####"),
+    ("L", "####/#..."),
+    ("N", "##../.###"),
+    ("P", "##/##/#."),
+    ("T", "###/.#./.#."),
+    ("U", "#.#/###"),
+    ("V", "#../#../###"),
+    ("W", "#../##./.##"),
+    ("X", ".#./###/.#."),
+    ("Y", "####/.#.."),
+    ("Z", "##./.#./.##"),
+];
+
+/// Smallest block key over all rotations, equal for rotated copies.
+fn canonical_key(ori: &Orintaion) -> String {
+    ori.all_orintations()
+        .iter()
+        .map(|ori| ori.sorted_key())
+        .min()
+        .unwrap_or_default()
+}
+
+/// Every polycube of `size` cubes, one orientation of each. Mirror images
+/// are different pieces unless a rotation turns one into the other.
+pub fn polycubes(size: usize) -> Vec<Orintaion> {
+    let mut shapes = vec![Orintaion::new(vec![Coord::new(0, 0, 0)])];
+    for _ in 1..size {
+        let mut seen = HashSet::new();
+        let mut grown = Vec::new();
+        for shape in shapes.iter() {
+            for block in shape.blocks.iter() {
+                for (dx, dy, dz) in NEIGHBOURS {
+                    let next = Coord::new(block.x + dx, block.y + dy, block.z + dz);
+                    if shape.blocks.contains(&next) {
+                        continue;
+                    }
+                    let mut blocks = shape.blocks.clone();
+                    blocks.push(next);
+                    let ori = Orintaion::new(blocks).normalise();
+                    if seen.insert(canonical_key(&ori)) {
+                        grown.push(ori);
+                    }
+                }
+            }
+        }
+        shapes = grown;
+    }
+    for shape in shapes.iter_mut() {
+        shape.blocks.sort_by_key(|b| (b.z, b.y, b.x));
+    }
+    shapes.sort_by_key(canonical_key);
+    shapes
+}
+
+impl StandardSet {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StandardSet::Tetracubes => "tetracubes",
+            StandardSet::Pentacubes => "pentacubes",
+            StandardSet::Pentominoes => "pentominoes",
+        }
+    }
+
+    /// Named shapes of the set. Flat pieces take their usual letter and the
+    /// others are numbered `Q1`, `Q2` and so on.
+    pub fn shapes(&self) -> Vec<(String, Orintaion)> {
+        let (size, flat_only) = match self {
+            StandardSet::Tetracubes => (4, false),
+            StandardSet::Pentacubes => (5, false),
+            StandardSet::Pentominoes => (5, true),
+        };
+        let letters: Vec<(&str, String)> = LETTERS
+            .iter()
+            .filter_map(|(letter, layer)| {
+                let ori = Orintaion::from_layers(&[layer]).ok()?;
+                (ori.blocks.len() == size).then(|| (*letter, canonical_key(&ori)))
+            })
+            .collect();
+        let mut numbered = 0;
+        polycubes(size)
+            .into_iter()
+            .filter_map(|ori| {
+                let key = canonical_key(&ori);
+                match letters.iter().find(|(_, letter_key)| *letter_key == key) {
+                    Some((letter, _)) => Some((letter.to_string(), ori)),
+                    None if flat_only => None,
+                    None => {
+                        numbered += 1;
+                        Some((format!("Q{}", numbered), ori))
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// The set as a puzzle on the default cube, to write out or solve
+    /// against a target of its own.
+    pub fn puzzle(&self) -> Puzzle {
+        let grid = Grid::default();
+        let pieces = self
+            .shapes()
+            .into_iter()
+            .enumerate()
+            .map(|(piece_id, (name, ori))| {
+                let color = DEFAULT_PALETTE[piece_id % DEFAULT_PALETTE.len()];
+                Piece::new(piece_id, name, color, ori)
+            })
+            .collect();
+        Puzzle {
+            name: self.name().to_string(),
+            grid,
+            target: grid.full(),
+            optional: Bitset::empty(),
+            pieces,
+            view: View::default(),
+        }
+    }
+}
+
+impl PuzzleBuilder {
+    /// Adds every piece of `set`, colored in palette order.
+    pub fn piece_set(self, set: StandardSet) -> Self {
+        set.shapes()
+            .into_iter()
+            .enumerate()
+            .fold(self, |builder, (index, (name, ori))| {
+                let cells: Vec<(i32, i32, i32)> =
+                    ori.blocks.iter().map(|b| (b.x, b.y, b.z)).collect();
+                let color = DEFAULT_PALETTE[index % DEFAULT_PALETTE.len()];
+                builder.piece(&name, color, &cells)
+            })
+    }
+}
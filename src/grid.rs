@@ -0,0 +1,133 @@
+use crate::{Bitset, Coord, SIZE};
+use std::str::FromStr;
+
+/// Dimensions of the board and the one mapping between cells and bit
+/// indices, `x` varying fastest, then `y`, then `z`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Grid {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+/// Offsets to the six face neighbours of a cell.
+pub const NEIGHBOURS: [(i32, i32, i32); 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self::new(SIZE as i32, SIZE as i32, SIZE as i32)
+    }
+}
+
+/// Longest side a board can have, puzzle files writing each coordinate as
+/// one digit.
+pub const MAX_SIDE: i32 = 10;
+
+/// Dimensions as `XxYxZ`, such as `3x3x3` or `2x3x5`.
+///
+/// ```
+/// use puzzle_cubes::grid::Grid;
+///
+/// assert_eq!("2x3x5".parse(), Ok(Grid::new(2, 3, 5)));
+/// assert_eq!("5x5x5".parse(), Ok(Grid::new(5, 5, 5)));
+/// assert_eq!("10x10x10".parse(), Ok(Grid::new(10, 10, 10)));
+/// assert!("11x1x1".parse::<Grid>().is_err());
+/// ```
+impl FromStr for Grid {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let dims: Vec<i32> = s
+            .trim()
+            .split('x')
+            .map(|d| d.parse().map_err(|_| format!("invalid size {}", s)))
+            .collect::<Result<_, _>>()?;
+        let [x, y, z] = dims[..] else {
+            return Err(format!("size {} is not XxYxZ", s));
+        };
+        let grid = Grid::new(x, y, z);
+        grid.check()?;
+        Ok(grid)
+    }
+}
+
+impl Grid {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Checks that a board this size can be solved and written.
+    pub fn check(&self) -> Result<(), String> {
+        if [self.x, self.y, self.z]
+            .iter()
+            .any(|d| !(1..=MAX_SIDE).contains(d))
+        {
+            return Err(format!(
+                "dimensions {}x{}x{} must be between 1 and {}",
+                self.x, self.y, self.z, MAX_SIDE
+            ));
+        }
+        Ok(())
+    }
+
+    /// The smallest board with a corner at the origin holding `cells`.
+    ///
+    /// ```
+    /// use puzzle_cubes::{grid::Grid, Coord};
+    ///
+    /// let cells = [Coord::new(0, 0, 0), Coord::new(2, 1, 0)];
+    /// assert_eq!(Grid::around(&cells), Grid::new(3, 2, 1));
+    /// ```
+    pub fn around(cells: &[Coord]) -> Self {
+        let side = |axis: fn(&Coord) -> i32| cells.iter().map(axis).max().map_or(1, |max| max + 1);
+        Self::new(side(|c| c.x), side(|c| c.y), side(|c| c.z))
+    }
+
+    pub fn len(&self) -> usize {
+        (self.x * self.y * self.z) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, coord: &Coord) -> bool {
+        (0..self.x).contains(&coord.x)
+            && (0..self.y).contains(&coord.y)
+            && (0..self.z).contains(&coord.z)
+    }
+
+    pub fn index(&self, coord: &Coord) -> usize {
+        (coord.x + coord.y * self.x + coord.z * self.x * self.y) as usize
+    }
+
+    pub fn coord(&self, index: usize) -> Coord {
+        let index = index as i32;
+        Coord::new(
+            index % self.x,
+            index / self.x % self.y,
+            index / (self.x * self.y),
+        )
+    }
+
+    /// Every cell in index order.
+    pub fn cells(&self) -> impl Iterator<Item = Coord> + '_ {
+        (0..self.len()).map(|index| self.coord(index))
+    }
+
+    /// Bitset with every cell of the grid set.
+    pub fn full(&self) -> Bitset {
+        let mut bits = Bitset::empty();
+        for index in 0..self.len() {
+            bits.set(index);
+        }
+        bits
+    }
+}
@@ -0,0 +1,70 @@
+use crate::view::TableFormat;
+use std::io::{self, Write};
+
+/// Placement attempts and successes counted by search depth.
+#[derive(Default)]
+pub struct Histogram {
+    attempts: Vec<u64>,
+    accepted: Vec<u64>,
+}
+
+fn bump(counts: &mut Vec<u64>, depth: usize) {
+    if counts.len() <= depth {
+        counts.resize(depth + 1, 0);
+    }
+    counts[depth] += 1;
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn attempt(&mut self, depth: usize) {
+        bump(&mut self.attempts, depth);
+    }
+
+    pub fn accept(&mut self, depth: usize) {
+        bump(&mut self.accepted, depth);
+    }
+
+    fn rows(&self) -> impl Iterator<Item = (usize, u64, u64)> + '_ {
+        (0..self.attempts.len().max(self.accepted.len())).map(|depth| {
+            (
+                depth,
+                self.attempts.get(depth).copied().unwrap_or(0),
+                self.accepted.get(depth).copied().unwrap_or(0),
+            )
+        })
+    }
+
+    pub fn write(&self, out: &mut dyn Write, format: TableFormat) -> io::Result<()> {
+        match format {
+            TableFormat::Table => {
+                writeln!(
+                    out,
+                    "{:>5} {:>14} {:>14} {:>7}",
+                    "depth", "attempts", "placed", "rate"
+                )?;
+                for (depth, attempts, accepted) in self.rows() {
+                    let rate = match attempts {
+                        0 => 0.0,
+                        _ => 100.0 * accepted as f64 / attempts as f64,
+                    };
+                    writeln!(
+                        out,
+                        "{:>5} {:>14} {:>14} {:>6.2}%",
+                        depth, attempts, accepted, rate
+                    )?;
+                }
+            }
+            TableFormat::Csv => {
+                writeln!(out, "depth,attempts,placed")?;
+                for (depth, attempts, accepted) in self.rows() {
+                    writeln!(out, "{},{},{}", depth, attempts, accepted)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{metrics::json_string, Placement, Puzzle};
+use std::io::{self, Write};
+
+/// The viewer page, with `__TITLE__` and `__DATA__` to fill in.
+const PAGE: &str = include_str!("page.html");
+
+pub(crate) fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+impl Puzzle {
+    /// The grid, piece names and colors and each solution's cells as JSON.
+    pub(crate) fn html_data(&self, solutions: &[Placement]) -> String {
+        let pieces: Vec<String> = self
+            .pieces
+            .iter()
+            .map(|piece| {
+                let (r, g, b) = self.rgb(piece.color);
+                format!(
+                    "{{\"name\": {}, \"color\": \"#{:02x}{:02x}{:02x}\"}}",
+                    json_string(&piece.name),
+                    r,
+                    g,
+                    b
+                )
+            })
+            .collect();
+        let solutions: Vec<String> = solutions
+            .iter()
+            .map(|solution| {
+                let placed: Vec<String> = solution
+                    .placed
+                    .iter()
+                    .map(|(id, bits)| {
+                        let cells: Vec<String> = self
+                            .grid
+                            .cells()
+                            .filter(|cell| bits.get(self.grid.index(cell)))
+                            .map(|cell| format!("[{}, {}, {}]", cell.x, cell.y, cell.z))
+                            .collect();
+                        format!("[{}, [{}]]", id, cells.join(", "))
+                    })
+                    .collect();
+                format!("[{}]", placed.join(", "))
+            })
+            .collect();
+        format!(
+            "{{\"grid\": [{}, {}, {}], \"pieces\": [{}], \"solutions\": [\n{}\n]}}",
+            self.grid.x,
+            self.grid.y,
+            self.grid.z,
+            pieces.join(", "),
+            solutions.join(",\n")
+        )
+    }
+
+    /// A single HTML page showing `solutions` in 3D, with the solution data
+    /// embedded and three.js loaded from a CDN. Pieces can be hidden and
+    /// pulled apart with the explode slider.
+    pub fn write_html(&self, out: &mut dyn Write, solutions: &[Placement]) -> io::Result<()> {
+        // `</` would end the script element early.
+        let data = self.html_data(solutions).replace("</", "<\\/");
+        let page = PAGE
+            .replace("__TITLE__", &html_escape(&self.name))
+            .replace("__DATA__", &data);
+        out.write_all(page.as_bytes())
+    }
+}
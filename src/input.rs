@@ -0,0 +1,26 @@
+use flate2::bufread::MultiGzDecoder;
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+/// The first bytes of every gzip file.
+pub(crate) const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Opens a text file for reading, `-` for stdin. Gzip files, recognised by
+/// a `.gz` extension or their magic bytes, are decompressed as they are read.
+pub fn open(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    let mut reader: Box<dyn BufRead> = if path == Path::new("-") {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(path)?))
+    };
+    let gzip = path.extension().is_some_and(|ext| ext == "gz")
+        || reader.fill_buf()?.starts_with(&GZIP_MAGIC);
+    if gzip {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else {
+        Ok(reader)
+    }
+}
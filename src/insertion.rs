@@ -0,0 +1,93 @@
+use crate::{archive::Solution, grid::NEIGHBOURS, Bitset, Coord, Placement, Puzzle, Solver};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+
+/// The side of a box left open for putting pieces in, named as in
+/// `metrics::FACES`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum Face {
+    /// The +x side
+    Right,
+    /// The -x side
+    Left,
+    /// The +y side
+    Top,
+    /// The -y side
+    Bottom,
+    /// The +z side
+    Back,
+    /// The -z side
+    Front,
+}
+
+impl Face {
+    /// The step from a cell towards the open side.
+    fn outwards(&self) -> Coord {
+        let (x, y, z) = NEIGHBOURS[*self as usize];
+        Coord::new(x, y, z)
+    }
+}
+
+impl Puzzle {
+    /// Whether `bits` can slide straight out through `face` past the cells
+    /// in `occupied`. Cells off the board it passes are the air above the
+    /// box, unless there is board further out, which makes them a wall.
+    fn slides_out(&self, bits: &Bitset, occupied: &Bitset, face: Face) -> bool {
+        let board = self.target.or(&self.optional);
+        let step = face.outwards();
+        self.grid
+            .cells()
+            .filter(|c| bits.get(self.grid.index(c)))
+            .all(|c| {
+                let mut outside = false;
+                let mut c = Coord::new(c.x + step.x, c.y + step.y, c.z + step.z);
+                while self.grid.contains(&c) {
+                    let index = self.grid.index(&c);
+                    if !board.get(index) {
+                        outside = true;
+                    } else if outside || (occupied.get(index) && !bits.get(index)) {
+                        return false;
+                    }
+                    c = Coord::new(c.x + step.x, c.y + step.y, c.z + step.z);
+                }
+                true
+            })
+    }
+
+    /// An order to put the pieces of `solution` in, each pushed straight in
+    /// through `face` without hitting the ones already there, as indices
+    /// into `solution`, or `None` if there is no such order.
+    ///
+    /// Taking a piece out never blocks another, so the pieces are taken out
+    /// of the finished assembly whenever they can be and put back in the
+    /// reverse order.
+    pub fn insertion_order(&self, solution: &Solution, face: Face) -> Option<Vec<usize>> {
+        let mut occupied = solution
+            .iter()
+            .fold(Bitset::empty(), |occupied, (_, bits)| occupied.or(bits));
+        let mut left: Vec<usize> = (0..solution.len()).collect();
+        let mut order = Vec::with_capacity(solution.len());
+        while !left.is_empty() {
+            let next = left
+                .iter()
+                .position(|i| self.slides_out(&solution[*i].1, &occupied, face))?;
+            let i = left.remove(next);
+            occupied = occupied.xor(&solution[i].1);
+            order.push(i);
+        }
+        order.reverse();
+        Some(order)
+    }
+}
+
+impl Solver {
+    /// Whether a complete `placement` counts as a solution: every group got
+    /// its count and, with an open face, the pieces can be put in through it.
+    pub(crate) fn accepts(&self, puzzle: &Puzzle, placement: &Placement) -> bool {
+        self.selected(placement, &[])
+            && self
+                .open_face
+                .is_none_or(|face| puzzle.insertion_order(&placement.placed, face).is_some())
+    }
+}
@@ -0,0 +1,2171 @@
+//! Solver for puzzles that pack polycube pieces into a box, such as the
+//! Bedlam and Soma cubes. The `puzzle-cubes` binary is a command line front
+//! end to this library; without the `cli` feature only the library is built.
+//!
+//! A puzzle is read from a file with `Puzzle::read` or built in code with
+//! `builder::PuzzleBuilder`, then searched with a `Solver`:
+//!
+//! ```
+//! use puzzle_cubes::{builder::PuzzleBuilder, Color, Placement, Solver};
+//!
+//! let puzzle = PuzzleBuilder::new("dominoes")
+//!     .dims(2, 2, 1)
+//!     .piece("A", Color::Red, &[(0, 0, 0), (1, 0, 0)])
+//!     .piece("B", Color::Blue, &[(0, 0, 0), (1, 0, 0)])
+//!     .build()
+//!     .unwrap();
+//! let mut solver = Solver::new();
+//! solver.collected = Some(Vec::new());
+//! solver.fill(&puzzle, &mut Placement::new(), &[0, 1]);
+//! assert_eq!(solver.num_solutions, 4);
+//! assert_eq!(solver.collected.unwrap()[0].placed.len(), 2);
+//! ```
+
+#[cfg(feature = "cli")]
+use colored::Colorize;
+use rayon::prelude::*;
+use std::{
+    io::{self, BufRead, Write},
+    ops::Range,
+    path::Path,
+    str::FromStr,
+    sync::{mpsc::SyncSender, Arc},
+};
+
+pub mod adjacency;
+pub mod analyze;
+pub mod anneal;
+pub mod archive;
+pub mod beam;
+pub mod builder;
+pub mod catalog;
+pub mod cells;
+pub mod checked;
+pub mod cnf;
+pub mod compare;
+pub mod cover;
+pub mod dlx;
+pub mod dot;
+pub mod error;
+pub mod events;
+pub mod explain;
+pub mod export;
+pub mod family;
+pub mod fingerprint;
+pub mod generate;
+pub mod grid;
+pub mod histogram;
+pub mod html;
+pub mod input;
+pub mod insertion;
+pub mod memory;
+pub mod metrics;
+pub mod nearmiss;
+pub mod nogood;
+pub mod outdir;
+pub mod palette;
+pub mod parallel;
+pub mod pieceset;
+pub mod play;
+#[cfg(feature = "playground")]
+pub mod playground;
+pub mod preprocess;
+pub mod profile;
+pub mod propagate;
+pub mod provenance;
+pub mod recolor;
+pub mod record;
+pub mod region;
+pub mod report;
+pub mod restart;
+pub mod rng;
+pub mod shape;
+pub mod solutions;
+pub mod spin;
+pub mod split;
+pub mod subset;
+pub mod template;
+pub mod tikz;
+pub mod timeline;
+pub mod trace;
+pub mod transform;
+pub mod unique;
+pub mod validate;
+pub mod view;
+#[cfg(feature = "viewer")]
+pub mod viewer;
+pub mod warmstart;
+
+use archive::ArchiveWriter;
+use cover::CellChoice;
+use error::PuzzleError;
+use events::Event;
+use grid::Grid;
+use histogram::Histogram;
+#[cfg(feature = "cli")]
+use palette::Palette;
+use propagate::Propagated;
+use timeline::Timeline;
+use trace::{Prune, Trace};
+use view::View;
+use warmstart::WarmStart;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Color {
+    Red,
+    Yellow,
+    Blue,
+    White,
+    Green,
+    Magenta,
+    Cyan,
+    BrightRed,
+    BrightYellow,
+    BrightBlue,
+    BrightGreen,
+    BrightMagenta,
+    BrightCyan,
+}
+
+/// Colors handed out to pieces that don't name one, in order of piece index.
+pub const DEFAULT_PALETTE: &[Color] = &[
+    Color::Red,
+    Color::Yellow,
+    Color::Blue,
+    Color::Green,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+    Color::BrightRed,
+    Color::BrightYellow,
+    Color::BrightBlue,
+    Color::BrightGreen,
+    Color::BrightMagenta,
+    Color::BrightCyan,
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Piece {
+    pub piece_id: usize,
+    pub name: String,
+    pub color: Color,
+    pub size: usize,
+    pub orintations: Vec<Orintaion>,
+    pub placements: Vec<Bitset>,
+    /// May be left out of a solution.
+    pub spare: bool,
+    /// The previous copy, for pieces that may be used more than once.
+    pub copy_of: Option<usize>,
+    /// The pieces glued together to make this one and where each sits,
+    /// empty for ordinary pieces.
+    pub glued: Vec<(String, Coord)>,
+    /// May be turned over into its mirror image as well as rotated.
+    pub flips: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Coord {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Coord {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+}
+
+/// `Orintaion` under its usual spelling.
+pub type Orientation = Orintaion;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Orintaion {
+    pub blocks: Vec<Coord>,
+}
+
+impl Color {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Color::Red => "red",
+            Color::Yellow => "yellow",
+            Color::Blue => "blue",
+            Color::White => "white",
+            Color::Green => "green",
+            Color::Magenta => "magenta",
+            Color::Cyan => "cyan",
+            Color::BrightRed => "bright-red",
+            Color::BrightYellow => "bright-yellow",
+            Color::BrightBlue => "bright-blue",
+            Color::BrightGreen => "bright-green",
+            Color::BrightMagenta => "bright-magenta",
+            Color::BrightCyan => "bright-cyan",
+        }
+    }
+
+    /// `str` in the terminal color of `self`, plain without the `cli` feature.
+    #[cfg(feature = "cli")]
+    pub fn color(&self, str: &str) -> String {
+        match self {
+            Color::Red => str.red(),
+            Color::Yellow => str.yellow(),
+            Color::Blue => str.blue(),
+            Color::White => str.white(),
+            Color::Green => str.green(),
+            Color::Magenta => str.magenta(),
+            Color::Cyan => str.cyan(),
+            Color::BrightRed => str.bright_red(),
+            Color::BrightYellow => str.bright_yellow(),
+            Color::BrightBlue => str.bright_blue(),
+            Color::BrightGreen => str.bright_green(),
+            Color::BrightMagenta => str.bright_magenta(),
+            Color::BrightCyan => str.bright_cyan(),
+        }
+        .to_string()
+    }
+
+    #[cfg(not(feature = "cli"))]
+    pub fn color(&self, str: &str) -> String {
+        str.to_string()
+    }
+}
+
+/// Colors are read ignoring case, with `_` or a space allowed in place of
+/// the `-` in names like `bright-red`.
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase().replace(['_', ' '], "-");
+        DEFAULT_PALETTE
+            .iter()
+            .find(|color| color.name() == name)
+            .copied()
+            .ok_or_else(|| format!("unknown color {}", s))
+    }
+}
+
+impl Piece {
+    pub fn new(piece_id: usize, name: String, color: Color, orintaion: Orintaion) -> Self {
+        Self::on_grid(piece_id, name, color, orintaion, &Grid::default())
+    }
+
+    /// Like `new` but with placements on `grid` instead of the default cube.
+    pub fn on_grid(
+        piece_id: usize,
+        name: String,
+        color: Color,
+        orintaion: Orintaion,
+        grid: &Grid,
+    ) -> Self {
+        Self::in_region(piece_id, name, color, orintaion, grid, &grid.full())
+    }
+
+    /// Like `on_grid` but only with placements inside `region`, such as the
+    /// cells of a target that isn't a box.
+    pub fn in_region(
+        piece_id: usize,
+        name: String,
+        color: Color,
+        orintaion: Orintaion,
+        grid: &Grid,
+        region: &Bitset,
+    ) -> Self {
+        let oris = {
+            let _span = profile::span("orientations");
+            orintaion.all_orintations()
+        };
+        let placements = {
+            let _span = profile::span("placements");
+            oris.iter()
+                .flat_map(|ori| ori.placements_in(grid, region))
+                .collect()
+        };
+        Self {
+            piece_id,
+            name,
+            color,
+            size: orintaion.blocks.len(),
+            orintations: oris,
+            placements,
+            spare: false,
+            copy_of: None,
+            glued: Vec::new(),
+            flips: false,
+        }
+    }
+
+    /// The piece allowed to be turned over: the orientations of its mirror
+    /// image that aren't rotations of it are added, with their placements
+    /// inside `region`.
+    pub fn flipped(mut self, grid: &Grid, region: &Bitset) -> Self {
+        let oris = self.orintations[0].all_orintations_and_mirrors();
+        for ori in oris[self.orintations.len()..].iter() {
+            self.placements.extend(ori.placements_in(grid, region));
+        }
+        self.orintations = oris;
+        self.flips = true;
+        self
+    }
+
+    /// How a compound piece was made, like `V+L@100`, written the way the
+    /// puzzle file defines it.
+    pub fn derivation(&self) -> Option<String> {
+        if self.glued.is_empty() {
+            return None;
+        }
+        let parts: Vec<String> = self
+            .glued
+            .iter()
+            .map(|(name, at)| match (at.x, at.y, at.z) {
+                (0, 0, 0) => name.clone(),
+                _ => format!("{}@{}{}{}", name, at.x, at.y, at.z),
+            })
+            .collect();
+        Some(parts.join("+"))
+    }
+
+    /// Piece from a list of `(x, y, z)` cells, translated to the origin.
+    pub fn from_coords(
+        piece_id: usize,
+        name: &str,
+        color: Color,
+        cells: &[(i32, i32, i32)],
+    ) -> Result<Self, String> {
+        Ok(Self::new(
+            piece_id,
+            name.to_string(),
+            color,
+            Orintaion::from_coords(cells)?,
+        ))
+    }
+
+    /// Piece drawn as layers, one string per `z`. Rows are separated by `/`
+    /// and listed top (highest `y`) first, the same way `show` prints them;
+    /// `#` or `X` marks a block and any other character an empty cell.
+    ///
+    /// ```
+    /// use puzzle_cubes::{Color, Piece};
+    ///
+    /// let l = Piece::from_layers(0, "L", Color::Red, &["#./#./##"]).unwrap();
+    /// assert_eq!(l.size, 4);
+    /// ```
+    pub fn from_layers(
+        piece_id: usize,
+        name: &str,
+        color: Color,
+        layers: &[&str],
+    ) -> Result<Self, String> {
+        Ok(Self::new(
+            piece_id,
+            name.to_string(),
+            color,
+            Orintaion::from_layers(layers)?,
+        ))
+    }
+
+    /// The piece's id as one character, `0`-`9` then `A`-`Z`. Puzzles have
+    /// at most `MAX_PIECES` pieces so every id has one.
+    pub fn char_id(&self) -> char {
+        match self.piece_id {
+            0..=9 => (self.piece_id as u8 + b'0') as char,
+            10..=35 => (self.piece_id as u8 + b'A' - 10) as char,
+            _ => panic!("Invalid piece id"),
+        }
+    }
+}
+
+enum Direction {
+    Next,
+    Clk,
+    CClk,
+}
+
+impl Orintaion {
+    pub fn new(blocks: Vec<Coord>) -> Self {
+        Self { blocks }
+    }
+
+    /// Normalised orientation from `(x, y, z)` cells, rejecting empty or
+    /// repeated cells.
+    pub fn from_coords(cells: &[(i32, i32, i32)]) -> Result<Self, String> {
+        if cells.is_empty() {
+            return Err("piece has no blocks".to_string());
+        }
+        for (i, cell) in cells.iter().enumerate() {
+            if cells[..i].contains(cell) {
+                return Err(format!("block {},{},{} repeated", cell.0, cell.1, cell.2));
+            }
+        }
+        let blocks = cells.iter().map(|&(x, y, z)| Coord::new(x, y, z)).collect();
+        Ok(Orintaion::new(blocks).normalise())
+    }
+
+    /// See `Piece::from_layers`.
+    pub fn from_layers(layers: &[&str]) -> Result<Self, String> {
+        let mut cells = Vec::new();
+        for (z, layer) in layers.iter().enumerate() {
+            let rows: Vec<&str> = layer.split('/').collect();
+            for (row, line) in rows.iter().enumerate() {
+                let y = rows.len() - 1 - row;
+                for (x, c) in line.chars().enumerate() {
+                    if c == '#' || c == 'X' {
+                        cells.push((x as i32, y as i32, z as i32));
+                    }
+                }
+            }
+        }
+        Self::from_coords(&cells)
+    }
+
+    pub fn placements(&self, grid: &Grid) -> Vec<Bitset> {
+        self.placements_in(grid, &grid.full())
+    }
+
+    /// The placements on `grid` that only cover cells of `region`.
+    pub fn placements_in(&self, grid: &Grid, region: &Bitset) -> Vec<Bitset> {
+        let mut placements = Vec::new();
+        for x in 0..grid.x {
+            for y in 0..grid.y {
+                for z in 0..grid.z {
+                    let mut valid = true;
+                    let mut bits = Bitset::empty();
+                    for block in self.blocks.iter() {
+                        let coord = Coord {
+                            x: block.x + x,
+                            y: block.y + y,
+                            z: block.z + z,
+                        };
+                        if grid.contains(&coord) && region.get(grid.index(&coord)) {
+                            bits.set(grid.index(&coord));
+                        } else {
+                            valid = false;
+                            break;
+                        }
+                    }
+                    if valid {
+                        placements.push(bits);
+                    }
+                }
+            }
+        }
+        placements
+    }
+
+    pub fn normalise(&self) -> Self {
+        // Find minimum coordinates
+        let min_x = self.blocks.iter().map(|block| block.x).min().unwrap();
+        let min_y = self.blocks.iter().map(|block| block.y).min().unwrap();
+        let min_z = self.blocks.iter().map(|block| block.z).min().unwrap();
+
+        Orintaion::new(
+            self.blocks
+                .iter()
+                .map(|block| Coord {
+                    x: block.x - min_x,
+                    y: block.y - min_y,
+                    z: block.z - min_z,
+                })
+                .collect(),
+        )
+    }
+
+    fn rotate(&self, dir: Direction) -> Self {
+        let mut ori = self.clone();
+        for block in ori.blocks.iter_mut() {
+            match dir {
+                Direction::Next => {
+                    let tmp = block.y;
+                    block.y = block.z;
+                    block.z = -tmp;
+                }
+                Direction::Clk => {
+                    let tmp = block.x;
+                    block.x = block.z;
+                    block.z = -tmp;
+                }
+                Direction::CClk => {
+                    let tmp = block.z;
+                    block.z = block.x;
+                    block.x = -tmp;
+                }
+            }
+        }
+        ori
+    }
+
+    fn similar(&self, other: &Self) -> bool {
+        let mut count = 0;
+        for block in self.blocks.iter() {
+            for other_block in other.blocks.iter() {
+                if block == other_block {
+                    count += 1;
+                    break;
+                }
+            }
+        }
+        count == self.blocks.len()
+    }
+
+    /// The shape reflected in the `x` axis, normalised.
+    pub fn mirror(&self) -> Self {
+        let blocks = self
+            .blocks
+            .iter()
+            .map(|b| Coord::new(-b.x, b.y, b.z))
+            .collect();
+        Orintaion::new(blocks).normalise()
+    }
+
+    /// The 24 rotations of the shape and the 24 of its mirror image, each
+    /// distinct one once and the rotations first, in the order
+    /// `all_orintations` gives them.
+    ///
+    /// ```
+    /// use puzzle_cubes::Orintaion;
+    ///
+    /// // The chiral tetracube has a mirror image it can't be turned into.
+    /// let screw = Orintaion::from_coords(&[(0, 0, 0), (1, 0, 0), (1, 1, 0), (1, 1, 1)]).unwrap();
+    /// assert_eq!(screw.all_orintations().len(), 12);
+    /// assert_eq!(screw.all_orintations_and_mirrors().len(), 24);
+    /// // An L tetromino turns over into its mirror image by rotation.
+    /// let l = Orintaion::from_coords(&[(0, 0, 0), (1, 0, 0), (2, 0, 0), (2, 1, 0)]).unwrap();
+    /// assert_eq!(l.all_orintations_and_mirrors().len(), 24);
+    /// ```
+    pub fn all_orintations_and_mirrors(&self) -> Vec<Orintaion> {
+        let mut orintations = self.all_orintations();
+        for ori in self.mirror().all_orintations() {
+            if orintations.iter().all(|o| !o.similar(&ori)) {
+                orintations.push(ori);
+            }
+        }
+        orintations
+    }
+
+    pub fn all_orintations(&self) -> Vec<Orintaion> {
+        let mut orintations = Vec::new();
+        let mut ori = self.normalise();
+        orintations.push(ori.clone());
+        let mut clk = true;
+        for _dir in 0..6 {
+            for _rot in 0..3 {
+                ori = if clk {
+                    ori.rotate(Direction::Clk)
+                } else {
+                    ori.rotate(Direction::CClk)
+                }
+                .normalise();
+                if orintations.iter().all(|o| !o.similar(&ori)) {
+                    orintations.push(ori.clone());
+                }
+            }
+            ori = ori.rotate(Direction::Next).normalise();
+            if orintations.iter().all(|o| !o.similar(&ori)) {
+                orintations.push(ori.clone());
+            }
+            clk = !clk;
+        }
+        orintations
+    }
+}
+
+/// Words of 64 cells each that a `Bitset` holds without allocating, so sets
+/// on boards of up to 128 cells stay cheap to copy.
+const INLINE: usize = 2;
+
+/// A set of cells by their index, see `grid::Grid::index`. Any number of
+/// cells fit, those past the first `INLINE` words going on the heap.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Bitset {
+    /// The first words, the lowest 64 cells first.
+    low: [u64; INLINE],
+    /// The words after `low`, only when one of them has a cell and then
+    /// without zero words at the end, so equal sets are stored alike.
+    high: Option<Box<[u64]>>,
+}
+
+impl Bitset {
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// The set of just the cell at `index`.
+    pub fn cell(index: usize) -> Self {
+        let mut bits = Self::empty();
+        bits.set(index);
+        bits
+    }
+
+    /// The set with `words`, the lowest 64 cells first.
+    pub fn from_words(words: &[u64]) -> Self {
+        let mut low = [0; INLINE];
+        let split = words.len().min(INLINE);
+        low[..split].copy_from_slice(&words[..split]);
+        Self::from_parts(low, &words[split..])
+    }
+
+    /// The set with `low` and then the words of `high`.
+    fn from_parts(low: [u64; INLINE], high: &[u64]) -> Self {
+        let used = high
+            .iter()
+            .rposition(|word| *word != 0)
+            .map_or(0, |top| top + 1);
+        Self {
+            low,
+            high: (used > 0).then(|| high[..used].into()),
+        }
+    }
+
+    /// The words up to the last with a cell in it, the lowest 64 cells
+    /// first, so the empty set has none.
+    pub fn words(&self) -> Vec<u64> {
+        let mut words: Vec<u64> = self.low.iter().chain(self.high()).copied().collect();
+        let used = words
+            .iter()
+            .rposition(|word| *word != 0)
+            .map_or(0, |top| top + 1);
+        words.truncate(used);
+        words
+    }
+
+    fn high(&self) -> &[u64] {
+        self.high.as_deref().unwrap_or_default()
+    }
+
+    fn zip(&self, other: &Bitset, op: impl Fn(u64, u64) -> u64) -> Bitset {
+        let mut low = self.low;
+        for (word, other) in low.iter_mut().zip(other.low) {
+            *word = op(*word, other);
+        }
+        if self.high.is_none() && other.high.is_none() {
+            return Bitset { low, high: None };
+        }
+        let (a, b) = (self.high(), other.high());
+        let high: Vec<u64> = (0..a.len().max(b.len()))
+            .map(|i| {
+                op(
+                    a.get(i).copied().unwrap_or(0),
+                    b.get(i).copied().unwrap_or(0),
+                )
+            })
+            .collect();
+        Bitset::from_parts(low, &high)
+    }
+
+    pub fn and(&self, other: &Bitset) -> Bitset {
+        self.zip(other, |a, b| a & b)
+    }
+
+    pub fn or(&self, other: &Bitset) -> Bitset {
+        self.zip(other, |a, b| a | b)
+    }
+
+    pub fn xor(&self, other: &Bitset) -> Bitset {
+        self.zip(other, |a, b| a ^ b)
+    }
+
+    /// The cells of this set that aren't in `other`.
+    pub fn without(&self, other: &Bitset) -> Bitset {
+        self.zip(other, |a, b| a & !b)
+    }
+
+    /// Whether the sets have no cell in common, without making the set of
+    /// those they do.
+    pub fn is_disjoint(&self, other: &Bitset) -> bool {
+        self.low.iter().zip(other.low).all(|(a, b)| a & b == 0)
+            && self
+                .high()
+                .iter()
+                .zip(other.high())
+                .all(|(a, b)| a & b == 0)
+    }
+
+    pub fn set(&mut self, index: usize) {
+        let (word, bit) = (index / 64, 1 << (index % 64));
+        if word < INLINE {
+            self.low[word] |= bit;
+        } else {
+            let mut high = self.high().to_vec();
+            high.resize(high.len().max(word - INLINE + 1), 0);
+            high[word - INLINE] |= bit;
+            self.high = Some(high.into());
+        }
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        let word = match index / 64 {
+            word if word < INLINE => self.low[word],
+            word => self.high().get(word - INLINE).copied().unwrap_or(0),
+        };
+        word & (1 << (index % 64)) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.low == [0; INLINE] && self.high.is_none()
+    }
+
+    /// Cells in the set.
+    pub fn len(&self) -> usize {
+        self.low
+            .iter()
+            .chain(self.high())
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// The lowest cell in the set.
+    pub fn first(&self) -> Option<usize> {
+        self.low
+            .iter()
+            .chain(self.high())
+            .enumerate()
+            .find(|(_, word)| **word != 0)
+            .map(|(i, word)| i * 64 + word.trailing_zeros() as usize)
+    }
+
+    /// The cells in the set, lowest first.
+    pub fn iter(&self) -> Cells<'_> {
+        Cells {
+            low: self.low,
+            high: self.high(),
+            word: 0,
+            bits: 0,
+        }
+    }
+
+    /// Reads a set written in hex as `LowerHex` does, without `0x`.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        if hex.is_empty() {
+            return None;
+        }
+        let mut words = Vec::new();
+        let mut end = hex.len();
+        while end > 0 {
+            let start = end.saturating_sub(16);
+            words.push(u64::from_str_radix(hex.get(start..end)?, 16).ok()?);
+            end = start;
+        }
+        Some(Self::from_words(&words))
+    }
+}
+
+/// Sets in order of their value as one number, as `LowerHex` writes them.
+impl Ord for Bitset {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let (a, b) = (self.high(), other.high());
+        a.len()
+            .cmp(&b.len())
+            .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+            .then_with(|| self.low.iter().rev().cmp(other.low.iter().rev()))
+    }
+}
+
+impl PartialOrd for Bitset {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The cells of a `Bitset`, see `Bitset::iter`.
+pub struct Cells<'a> {
+    low: [u64; INLINE],
+    high: &'a [u64],
+    word: usize,
+    /// Cells of the word before `word` not yet given.
+    bits: u64,
+}
+
+impl Iterator for Cells<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.bits == 0 {
+            self.bits = match self.word {
+                word if word < INLINE => self.low[word],
+                word => *self.high.get(word - INLINE)?,
+            };
+            self.word += 1;
+        }
+        let bit = self.bits.trailing_zeros() as usize;
+        self.bits &= self.bits - 1;
+        Some((self.word - 1) * 64 + bit)
+    }
+}
+
+impl From<u64> for Bitset {
+    fn from(bits: u64) -> Self {
+        Self::from_words(&[bits])
+    }
+}
+
+/// The set as one number in hex, so sets within the first 64 cells read as
+/// the `u64` they once were.
+impl std::fmt::LowerHex for Bitset {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let words = self.words();
+        let mut digits = format!("{:x}", words.last().copied().unwrap_or(0));
+        for word in words.iter().rev().skip(1) {
+            digits.push_str(&format!("{:016x}", word));
+        }
+        f.pad_integral(true, "0x", &digits)
+    }
+}
+
+/// Side of the default board, for puzzles that don't give their size.
+pub const SIZE: usize = 4;
+
+/// Most pieces a puzzle may have, counting each copy of a piece, one for
+/// each character `Piece::char_id` can give.
+pub const MAX_PIECES: usize = 36;
+
+#[derive(Clone, Default)]
+pub struct Placement {
+    pub occupied: Bitset,
+    pub placed: Vec<(usize, Bitset)>,
+}
+
+impl Placement {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes the last placed piece back off.
+    pub fn pop(&mut self) -> Option<checked::Placed> {
+        let (piece_id, bits) = self.placed.pop()?;
+        self.occupied = self.occupied.xor(&bits);
+        Some(checked::Placed { piece_id, bits })
+    }
+
+    pub fn is_valid(&self, bits: &Bitset) -> bool {
+        bits.is_disjoint(&self.occupied)
+    }
+
+    /// Places a piece without checking it, see `try_place`.
+    pub fn place(&mut self, id: usize, bits: Bitset) {
+        self.occupied = self.occupied.or(&bits);
+        self.placed.push((id, bits));
+    }
+}
+
+/// Uses of a piece as `n` or `min-max`, either may be zero.
+fn parse_uses(uses: &str) -> Result<(usize, usize), String> {
+    let parse = |n: &str| {
+        n.trim()
+            .parse::<usize>()
+            .map_err(|_| format!("invalid use count {}", uses))
+    };
+    let (min, max) = match uses.split_once('-') {
+        Some((min, max)) => (parse(min)?, parse(max)?),
+        None => (parse(uses)?, parse(uses)?),
+    };
+    if min > max {
+        return Err(format!("minimum uses {} above maximum {}", min, max));
+    }
+    Ok((min, max))
+}
+
+/// A piece line of a puzzle file, before ids are given out.
+struct Def {
+    /// Line of the file the piece is on, counting from 1, and its text.
+    line: usize,
+    text: String,
+    name: String,
+    color: Color,
+    ori: Orintaion,
+    spare: bool,
+    /// A later copy of the piece before it.
+    copy: bool,
+    glued: Vec<(String, Coord)>,
+    flip: bool,
+}
+
+/// The parts of a compound piece, `NAME[@XYZ]` joined by `+` like
+/// `V+L@100`, each piece's blocks shifted by its offset.
+fn parse_glue(parts: &str) -> Result<Vec<(String, Coord)>, String> {
+    parts
+        .split('+')
+        .map(|part| match part.trim().split_once('@') {
+            Some((name, at)) => {
+                let digits: Vec<i32> = at
+                    .trim()
+                    .chars()
+                    .map(|c| c.to_digit(10).map(|d| d as i32))
+                    .collect::<Option<_>>()
+                    .unwrap_or_default();
+                match digits[..] {
+                    [x, y, z] => Ok((name.trim().to_string(), Coord::new(x, y, z))),
+                    _ => Err(format!(
+                        "invalid offset {}, expected e.g. V@100",
+                        part.trim()
+                    )),
+                }
+            }
+            None if part.trim().is_empty() => {
+                Err("glue needs pieces, e.g. glue V+L@100".to_string())
+            }
+            None => Ok((part.trim().to_string(), Coord::new(0, 0, 0))),
+        })
+        .collect()
+}
+
+/// Joins the `glued` pieces, defined earlier in the file, into one rigid
+/// shape. Each part uses up the last copy of its piece.
+fn glue(defs: &mut Vec<Def>, glued: &[(String, Coord)]) -> Result<Orintaion, String> {
+    let mut blocks: Vec<Coord> = Vec::new();
+    for (name, at) in glued.iter() {
+        let index = defs
+            .iter()
+            .rposition(|def| def.name == *name)
+            .ok_or_else(|| format!("glued piece {} is not defined above", name))?;
+        let part = defs.remove(index);
+        for b in part.ori.blocks.iter() {
+            let b = Coord::new(b.x + at.x, b.y + at.y, b.z + at.z);
+            if blocks.contains(&b) {
+                return Err(format!(
+                    "glued piece {} overlaps the others at {}{}{}",
+                    name, b.x, b.y, b.z
+                ));
+            }
+            blocks.push(b);
+        }
+    }
+    Ok(Orintaion::new(blocks))
+}
+
+/// A piece's blocks, listed like `000-100-110` or given as a solid box like
+/// `box 2x2x1`. A box can be moved off the origin with `@XYZ`, such as
+/// `box 2x2x2@111` for the middle of a 4x4x4 cube.
+fn parse_piece_blocks(blocks: &str) -> Result<Vec<Coord>, String> {
+    let Some(size) = blocks.trim().strip_prefix("box") else {
+        return parse_checked_blocks(blocks);
+    };
+    let invalid = || {
+        format!(
+            "invalid box {}, expected e.g. box 2x2x1 or box 2x2x2@111",
+            size.trim()
+        )
+    };
+    let (size, at) = match size.split_once('@') {
+        Some((size, at)) => match parse_checked_blocks(at).map_err(|_| invalid())?[..] {
+            [at] => (size, at),
+            _ => return Err(invalid()),
+        },
+        None => (size, Coord::new(0, 0, 0)),
+    };
+    let dims: Vec<i32> = size
+        .trim()
+        .split('x')
+        .map(|d| d.parse().ok().filter(|d| *d > 0))
+        .collect::<Option<_>>()
+        .ok_or_else(invalid)?;
+    let [x, y, z] = dims[..] else {
+        return Err(invalid());
+    };
+    Ok((0..z)
+        .flat_map(|cz| (0..y).flat_map(move |cy| (0..x).map(move |cx| Coord::new(cx, cy, cz))))
+        .map(|c| Coord::new(c.x + at.x, c.y + at.y, c.z + at.z))
+        .collect())
+}
+
+/// Like `parse_blocks` but rejects anything other than three digits per
+/// block, which `parse_blocks` would skip over or panic on.
+pub(crate) fn parse_checked_blocks(blocks: &str) -> Result<Vec<Coord>, String> {
+    let is_block = |block: &str| {
+        let digits: String = block.chars().filter(|c| !c.is_whitespace()).collect();
+        digits.len() == 3 && digits.chars().all(|c| c.is_ascii_digit())
+    };
+    match blocks.split('-').find(|block| !is_block(block)) {
+        Some(block) => Err(format!(
+            "invalid block {:?} in {}, expected three digits like 000-100-110",
+            block.trim(),
+            blocks.trim()
+        )),
+        None => Ok(parse_blocks(blocks)),
+    }
+}
+
+pub fn parse_blocks(blocks: &str) -> Vec<Coord> {
+    blocks
+        .split('-')
+        .map(|block_str| {
+            let coords: Vec<i32> = block_str
+                .chars()
+                .filter_map(|c| c.to_digit(10))
+                .map(|num| num as i32)
+                .collect();
+
+            Coord {
+                x: coords[0],
+                y: coords[1],
+                z: coords[2],
+            }
+        })
+        .collect()
+}
+
+pub struct Puzzle {
+    pub name: String,
+    pub grid: Grid,
+    pub target: Bitset,
+    /// Don't-care cells, pieces may cover them but need not.
+    pub optional: Bitset,
+    pub pieces: Vec<Piece>,
+    pub view: View,
+}
+
+impl Puzzle {
+    /// Pieces are `name,color,blocks` or `name,blocks`; a missing or empty
+    /// color is taken from `colors` by piece index. A fourth field, `n` or
+    /// `min-max`, lets a piece be used that many times, each use becoming a
+    /// copy of the piece. A fifth field of `flip` lets the piece be turned
+    /// over into its mirror image, as in `S,red,000-100-110-210,,flip`. A
+    /// path of `-` reads from stdin. The header is `name,dims` optionally
+    /// followed by the blocks of the don't-care cells, the path of a piece
+    /// library and the blocks of cells no piece may cover, which can split
+    /// the board into separate wells, see `regions`, or leave holes in it:
+    /// `hollow,4x4x4,,,box 2x2x2@111` is a cube with its middle left empty.
+    /// Cells are given the way piece blocks are. A last header field gives
+    /// the axes the file was written with, see `transform::Axes`; the puzzle
+    /// is turned to this tool's axes on reading.
+    ///
+    /// In place of the dims the target can be listed as cells, such as
+    /// `steps,000-100-200-110-210-220` for a staircase. The board is then
+    /// the smallest box holding them and its other cells are blocked, or
+    /// don't-care cells if listed as those.
+    ///
+    /// The library is a puzzle file, relative to this one, whose pieces can
+    /// be used by name: a piece without blocks, such as a line with only a
+    /// name, takes its blocks and default color from the library.
+    ///
+    /// Gzip compressed files are read as well, see `input::open`. A file that
+    /// doesn't make sense gives a `PuzzleError` with the line and column of
+    /// the first problem.
+    pub fn read(filepath: &Path, colors: &[Color]) -> Result<Self, PuzzleError> {
+        Self::read_with(filepath, colors, Piece::in_region)
+    }
+
+    /// Like `read` but builds each piece with `build`, which lets a warm start
+    /// supply cached orientations and placements.
+    pub fn read_with(
+        filepath: &Path,
+        colors: &[Color],
+        build: impl Fn(usize, String, Color, Orintaion, &Grid, &Bitset) -> Piece + Sync,
+    ) -> Result<Self, PuzzleError> {
+        let dir = filepath.parent().unwrap_or(Path::new(""));
+        Self::read_from(input::open(filepath)?, dir, colors, build)
+    }
+
+    /// A puzzle from the text of a puzzle file, with library paths relative
+    /// to the current directory.
+    pub fn parse(text: &str, colors: &[Color]) -> Result<Self, PuzzleError> {
+        Self::read_from(text.as_bytes(), Path::new(""), colors, Piece::in_region)
+    }
+
+    /// Reads a puzzle file from `reader`, with library paths relative to
+    /// `dir`.
+    fn read_from(
+        reader: impl BufRead,
+        dir: &Path,
+        colors: &[Color],
+        build: impl Fn(usize, String, Color, Orintaion, &Grid, &Bitset) -> Piece + Sync,
+    ) -> Result<Self, PuzzleError> {
+        let span = profile::span("parse");
+        let mut defs = Vec::new();
+        let mut lines = reader.lines();
+        let header = lines.next().ok_or(PuzzleError::Empty)??;
+        let bad_header =
+            |field: usize, message: String| PuzzleError::at(1, &header, field, message);
+        let top: Vec<&str> = header.split(",").collect();
+        let name = top[0];
+        // The size, or the target's cells in the smallest board holding them.
+        let (grid, shape) = match top.get(1).filter(|size| !size.trim().is_empty()) {
+            Some(cells) if cells.trim().starts_with("box") || !cells.contains('x') => {
+                let cells = parse_piece_blocks(cells).map_err(|err| bad_header(1, err))?;
+                let grid = Grid::around(&cells);
+                grid.check().map_err(|err| bad_header(1, err))?;
+                let mut shape = Bitset::empty();
+                for cell in cells.iter() {
+                    shape.set(grid.index(cell));
+                }
+                (grid, Some(shape))
+            }
+            Some(size) => (size.parse().map_err(|err| bad_header(1, err))?, None),
+            None => (Grid::default(), None),
+        };
+        let cells = |field: usize, kind: &str| {
+            let blocks = match top.get(field) {
+                Some(blocks) if !blocks.trim().is_empty() => {
+                    parse_piece_blocks(blocks).map_err(|err| bad_header(field, err))?
+                }
+                _ => Vec::new(),
+            };
+            let mut cells = Bitset::empty();
+            for block in blocks.iter() {
+                if !grid.contains(block) {
+                    return Err(bad_header(
+                        field,
+                        format!(
+                            "{} cell {}{}{} is outside the puzzle",
+                            kind, block.x, block.y, block.z
+                        ),
+                    ));
+                }
+                cells.set(grid.index(block));
+            }
+            Ok(cells)
+        };
+        let optional = cells(2, "optional")?;
+        // Cells outside a target given as cells are blocked, unless they
+        // are don't-care cells.
+        let blocked = match shape {
+            Some(shape) => cells(4, "blocked")?.or(&grid.full().xor(&shape.or(&optional))),
+            None => cells(4, "blocked")?,
+        };
+        let region = grid.full().xor(&blocked);
+        let library = match top.get(3) {
+            Some(path) if !path.trim().is_empty() => {
+                let read = Puzzle::read(&dir.join(path.trim()), colors);
+                Some(
+                    read.map_err(|err| bad_header(3, format!("library {}: {}", path.trim(), err)))?,
+                )
+            }
+            _ => None,
+        };
+        for (index, text) in lines.enumerate() {
+            let text = text?;
+            if text.trim().is_empty() {
+                continue;
+            }
+            let bad =
+                |field: usize, message: String| PuzzleError::at(index + 2, &text, field, message);
+            let line: Vec<&str> = text.split(",").collect();
+            let (color, blocks, uses) = match line.len() {
+                1 => ("", "", None),
+                2 => ("", line[1], None),
+                3 => (line[1], line[2], None),
+                _ => (
+                    line[1],
+                    line[2],
+                    Some(line[3]).filter(|uses| !uses.trim().is_empty()),
+                ),
+            };
+            let blocks_field = line.len().min(3) - 1;
+            let shared = match blocks {
+                "" => Some(
+                    library
+                        .as_ref()
+                        .and_then(|library| library.pieces.iter().find(|p| p.name == line[0]))
+                        .ok_or_else(|| {
+                            bad(
+                                blocks_field,
+                                format!("piece {} has no blocks and is not in a library", line[0]),
+                            )
+                        })?,
+                ),
+                _ => None,
+            };
+            let color = match (color, shared) {
+                ("", Some(shared)) => shared.color,
+                ("", None) => colors[index % colors.len()],
+                (color, _) => color
+                    .parse()
+                    .map_err(|err| bad(1, format!("piece {}: {}", line[0], err)))?,
+            };
+            let (min, max) = match uses {
+                Some(uses) => {
+                    parse_uses(uses).map_err(|err| bad(3, format!("piece {}: {}", line[0], err)))?
+                }
+                None => (1, 1),
+            };
+            if max > MAX_PIECES {
+                return Err(bad(
+                    3,
+                    format!(
+                        "piece {}: {} uses, a puzzle has at most {} pieces",
+                        line[0], max, MAX_PIECES
+                    ),
+                ));
+            }
+            let flip = match line.get(4).map(|flip| flip.trim()) {
+                None | Some("") => shared.is_some_and(|shared| shared.flips),
+                Some("flip") => true,
+                Some(other) => {
+                    return Err(bad(
+                        4,
+                        format!("piece {}: unknown option {}, expected flip", line[0], other),
+                    ))
+                }
+            };
+            let invalid = |err: String| bad(blocks_field, format!("piece {}: {}", line[0], err));
+            let (ori, glued) = match (shared, blocks.trim().strip_prefix("glue")) {
+                (Some(shared), _) => (shared.orintations[0].clone(), Vec::new()),
+                (None, Some(parts)) => {
+                    let glued = parse_glue(parts).map_err(invalid)?;
+                    (glue(&mut defs, &glued).map_err(invalid)?, glued)
+                }
+                (None, None) => (
+                    Orintaion::new(parse_piece_blocks(blocks).map_err(invalid)?),
+                    Vec::new(),
+                ),
+            };
+            validate::check_blocks(line[0], &ori.blocks)
+                .map_err(|problem| bad(blocks_field, problem.to_string()))?;
+            for copy in 0..max {
+                defs.push(Def {
+                    line: index + 2,
+                    text: text.clone(),
+                    name: line[0].to_string(),
+                    color,
+                    ori: ori.clone(),
+                    spare: copy >= min,
+                    copy: copy > 0,
+                    glued: glued.clone(),
+                    flip,
+                });
+            }
+        }
+        drop(span);
+        if let Some(def) = defs.get(MAX_PIECES) {
+            return Err(PuzzleError::at(
+                def.line,
+                &def.text,
+                0,
+                format!(
+                    "piece {}: more than {} pieces, counting copies",
+                    def.name, MAX_PIECES
+                ),
+            ));
+        }
+        // Ids are given out last, gluing takes pieces out of the list.
+        let pieces = defs
+            .into_par_iter()
+            .enumerate()
+            .map(|(piece_id, def)| {
+                let piece = Piece {
+                    spare: def.spare,
+                    copy_of: def.copy.then(|| piece_id - 1),
+                    glued: def.glued,
+                    ..build(piece_id, def.name, def.color, def.ori, &grid, &region)
+                };
+                match def.flip {
+                    true => piece.flipped(&grid, &region),
+                    false => piece,
+                }
+            })
+            .collect();
+        let mut puzzle = Puzzle {
+            name: name.to_string(),
+            grid,
+            target: grid.full().xor(&optional.or(&blocked)),
+            optional: optional.and(&blocked.xor(&grid.full())),
+            pieces,
+            view: View::default(),
+        };
+        puzzle.confine_to_regions();
+        match top.get(5).filter(|axes| !axes.trim().is_empty()) {
+            Some(axes) => {
+                let axes: transform::Axes = axes.parse().map_err(|err| bad_header(5, err))?;
+                puzzle
+                    .with_axes(axes)
+                    .map_err(|err| bad_header(5, err.to_string()))
+            }
+            None => Ok(puzzle),
+        }
+    }
+
+    /// The puzzle, written with `axes`, turned to this tool's axes.
+    pub fn with_axes(self, axes: transform::Axes) -> io::Result<Puzzle> {
+        if axes.is_identity() {
+            return Ok(self);
+        }
+        self.transform(&[transform::Transform::Axes(axes)], None)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// The pieces on an empty board of size `grid`, all of it target.
+    pub fn with_grid(mut self, grid: Grid) -> Puzzle {
+        for piece in self.pieces.iter_mut() {
+            piece.placements = piece
+                .orintations
+                .iter()
+                .flat_map(|ori| ori.placements(&grid))
+                .collect();
+        }
+        self.grid = grid;
+        self.target = grid.full();
+        self.optional = Bitset::empty();
+        self
+    }
+
+    pub fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        write!(
+            out,
+            "{},{}x{}x{}",
+            self.name, self.grid.x, self.grid.y, self.grid.z
+        )?;
+        if !self.optional.is_empty() {
+            let cells: Vec<String> = self
+                .grid
+                .cells()
+                .filter(|cell| self.optional.get(self.grid.index(cell)))
+                .map(|cell| format!("{}{}{}", cell.x, cell.y, cell.z))
+                .collect();
+            write!(out, ",{}", cells.join("-"))?;
+        }
+        let blocked = self.grid.full().xor(&self.target.or(&self.optional));
+        if !blocked.is_empty() {
+            if self.optional.is_empty() {
+                write!(out, ",")?;
+            }
+            write!(out, ",,{}", self.format_cells(&blocked))?;
+        }
+        writeln!(out)?;
+        for piece in self.pieces.iter().filter(|piece| piece.copy_of.is_none()) {
+            let blocks: Vec<String> = piece.orintations[0]
+                .blocks
+                .iter()
+                .map(|b| format!("{}{}{}", b.x, b.y, b.z))
+                .collect();
+            write!(
+                out,
+                "{},{},{}",
+                piece.name,
+                piece.color.name(),
+                blocks.join("-")
+            )?;
+            let copies = self.copies(piece.piece_id);
+            let min = copies.iter().filter(|id| !self.pieces[**id].spare).count();
+            let uses = match (min, copies.len()) {
+                (min, max) if min == max => max.to_string(),
+                (min, max) => format!("{}-{}", min, max),
+            };
+            match (uses.as_str(), piece.flips) {
+                ("1", false) => writeln!(out)?,
+                (uses, false) => writeln!(out, ",{}", uses)?,
+                (uses, true) => writeln!(out, ",{},flip", uses)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// `piece_id` followed by its later copies.
+    fn copies(&self, piece_id: usize) -> Vec<usize> {
+        let mut copies = vec![piece_id];
+        while let Some(next) = self
+            .pieces
+            .iter()
+            .find(|piece| piece.copy_of == copies.last().copied())
+        {
+            copies.push(next.piece_id);
+        }
+        copies
+    }
+
+    /// Whether `piece_id` may be placed next. Copies of a piece are placed in
+    /// order, so swapping two copies doesn't give a new solution.
+    pub fn usable(&self, piece_id: usize, remaining: &[usize]) -> bool {
+        match self.pieces[piece_id].copy_of {
+            Some(previous) => !remaining.contains(&previous),
+            None => true,
+        }
+    }
+
+    /// Whether all of `remaining` may be left out.
+    pub fn only_spares(&self, remaining: &[usize]) -> bool {
+        remaining.iter().all(|id| self.pieces[*id].spare)
+    }
+
+    /// Keeps every piece in the orientation it was given in, so pieces can
+    /// be moved but not rotated. Placements stay off blocked cells and
+    /// within one region, as on reading.
+    pub fn translate_only(&mut self) {
+        for piece in self.pieces.iter_mut() {
+            piece.orintations.truncate(1);
+            piece.placements = piece.orintations[0].placements(&self.grid);
+        }
+        self.confine_to_regions();
+    }
+
+    pub fn show(&self, out: &mut dyn Write, placement: &Placement) -> io::Result<()> {
+        #[cfg(feature = "cli")]
+        if self.view.compact && self.view.palette != Palette::Mono {
+            return self.show_compact(out, placement);
+        }
+        let bands = self.layer_bands(2);
+        for (n, band) in bands.iter().enumerate() {
+            self.write_band_header(out, 2, &bands, n)?;
+            self.show_band(out, placement, band.clone())?;
+        }
+        Ok(())
+    }
+
+    fn show_band(
+        &self,
+        out: &mut dyn Write,
+        placement: &Placement,
+        band: Range<i32>,
+    ) -> io::Result<()> {
+        let board = self.target.or(&self.optional);
+        for y in (0..self.grid.y).rev() {
+            if self.view.labels {
+                write!(out, "{:>3} ", y)?;
+            }
+            for z in band.clone() {
+                for x in 0..self.grid.x {
+                    let index = self.grid.index(&Coord::new(x, y, z));
+                    let gap = match self.view.origin && index == 0 {
+                        true => "<",
+                        false => " ",
+                    };
+                    if placement.occupied.get(index) {
+                        for (id, bits) in placement.placed.iter() {
+                            if bits.get(index) {
+                                let id = self.colored_id(*id);
+                                write!(out, "{}{}", id, gap)?;
+                                break;
+                            }
+                        }
+                    } else if board.get(index) {
+                        write!(out, ".{}", gap)?;
+                    } else {
+                        write!(out, " {}", gap)?;
+                    }
+                }
+                write!(out, "  ")?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// Draws the cells of `bits` as `X`, layer by layer like `show`, then
+    /// the mask in hex.
+    pub fn show_bit(&self, out: &mut dyn Write, bits: &Bitset) -> io::Result<()> {
+        for y in (0..self.grid.y).rev() {
+            for z in 0..self.grid.z {
+                for x in 0..self.grid.x {
+                    if bits.get(self.grid.index(&Coord::new(x, y, z))) {
+                        write!(out, "X ")?;
+                    } else {
+                        write!(out, ". ")?;
+                    }
+                }
+                write!(out, "  ")?;
+            }
+            writeln!(out)?;
+        }
+        writeln!(out, "0x{:016x}", bits)?;
+        writeln!(out)
+    }
+}
+
+/// Search state for one run. A `Puzzle` is never modified while solving, so
+/// one loaded puzzle can be shared between threads, each with its own solver.
+///
+/// ```
+/// use puzzle_cubes::{builder::PuzzleBuilder, Color, Placement, Solver};
+/// use std::{sync::Arc, thread};
+///
+/// let puzzle = Arc::new(
+///     PuzzleBuilder::new("pair")
+///         .dims(2, 1, 1)
+///         .piece("A", Color::Red, &[(0, 0, 0)])
+///         .piece("B", Color::Blue, &[(0, 0, 0)])
+///         .build()
+///         .unwrap(),
+/// );
+/// let handles: Vec<_> = (0..2)
+///     .map(|_| {
+///         let puzzle = Arc::clone(&puzzle);
+///         thread::spawn(move || {
+///             let mut solver = Solver::new();
+///             solver.fill(&puzzle, &mut Placement::new(), &[0, 1]);
+///             solver.num_solutions
+///         })
+///     })
+///     .collect();
+/// for handle in handles {
+///     assert_eq!(handle.join().unwrap(), 2);
+/// }
+/// ```
+#[derive(Default)]
+pub struct Solver {
+    pub num_solutions: usize,
+    /// Placements made so far.
+    pub nodes: u64,
+    /// Stop searching once this many placements were made.
+    pub node_limit: Option<u64>,
+    /// Stop searching once this many solutions were found.
+    pub solution_limit: Option<usize>,
+    pub trace: Option<Trace>,
+    pub histogram: Option<Histogram>,
+    pub timeline: Option<Timeline>,
+    pub archive: Option<ArchiveWriter>,
+    /// Each solution is written to its own file instead, when set.
+    pub output_dir: Option<outdir::OutputDir>,
+    /// Where solutions are printed, stdout if not set.
+    pub output: Option<Box<dyn Write + Send>>,
+    /// Solutions are kept here instead of being output, when set.
+    pub collected: Option<Vec<Placement>>,
+    /// Every solution is also added to this file, even when only counting.
+    pub solutions_file: Option<solutions::SolutionsFile>,
+    /// Solutions are only counted, neither output nor collected.
+    pub count_only: bool,
+    /// Only the first of solutions that are symmetric to each other is
+    /// output and counted, when set.
+    pub unique: Option<unique::Unique>,
+    /// Told of each solution and of every `events::PROGRESS_INTERVAL`
+    /// placements as `(placements, solutions)` since it was last told, from
+    /// whichever thread the search runs on.
+    pub progress: Option<Arc<dyn Fn(u64, usize) + Send + Sync>>,
+    pub warm: Option<WarmStart>,
+    /// Place forced pieces before branching, see `Puzzle::forced_placements`.
+    pub propagate: bool,
+    /// Largest partial placement reached, kept to explain failed searches.
+    pub deepest: Placement,
+    /// How many pieces of each group a solution uses, see `Puzzle::select`.
+    pub groups: Vec<subset::Group>,
+    pub nogoods: Option<nogood::Nogoods>,
+    pub recorder: Option<record::Recorder>,
+    pub tree: Option<dot::SearchTree>,
+    /// Where search events are sent. The channel is bounded so a slow
+    /// reader paces the search; a receiver that has gone away is ignored
+    /// rather than stopping it.
+    pub events: Option<SyncSender<Event>>,
+    pub near_misses: Option<nearmiss::NearMisses>,
+    /// Only solutions that can be put together through this side count.
+    pub open_face: Option<insertion::Face>,
+    /// Shown every placement, as `Event::PiecePlaced`, and every solution,
+    /// as `Event::SolutionFound`, with the board as it is then. The search
+    /// waits for it and stops once it returns false.
+    pub pause: Option<events::Pause>,
+    /// Set once `pause` stopped the search.
+    pub stopped: bool,
+    /// Memory the nogoods, warm start, unique solutions and collected
+    /// solutions may grow into. Each stops growing once it is refused.
+    pub budget: memory::Budget,
+    /// Placements by the cells they cover, made from the puzzle when a
+    /// search starts and dropped when it returns, see `indexed`.
+    cell_index: Option<Arc<cover::CellIndex>>,
+}
+
+const _: () = {
+    const fn shared<T: Send + Sync>() {}
+    const fn owned<T: Send>() {}
+    shared::<Puzzle>();
+    owned::<Solver>();
+};
+
+impl Solver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn should_stop(&self) -> bool {
+        self.stopped
+            || self.node_limit.is_some_and(|limit| self.nodes >= limit)
+            || self
+                .solution_limit
+                .is_some_and(|limit| self.num_solutions >= limit)
+    }
+
+    /// Runs `search` with the cell index of `puzzle`. The outermost search
+    /// makes it and drops it once done, so a solver used again after the
+    /// pieces' placements changed doesn't prune with stale ones.
+    fn indexed<T>(&mut self, puzzle: &Puzzle, search: impl FnOnce(&mut Self) -> T) -> T {
+        if self.cell_index.is_some() {
+            return search(self);
+        }
+        self.cell_index = Some(Arc::new(cover::CellIndex::new(puzzle)));
+        let result = search(self);
+        self.cell_index = None;
+        result
+    }
+
+    /// The index made by `indexed` for the search under way.
+    fn cell_index(&self) -> &cover::CellIndex {
+        self.cell_index
+            .as_deref()
+            .expect("Searches run with a cell index")
+    }
+
+    /// Logs the attempt and returns why `bits` can't be placed, if it can't.
+    fn check(
+        &mut self,
+        puzzle: &Puzzle,
+        placement: &Placement,
+        piece: &Piece,
+        bits: &Bitset,
+        remaining: &[usize],
+    ) -> Option<Prune> {
+        let depth = placement.placed.len();
+        if let Some(trace) = self.trace.as_mut() {
+            trace.attempt(depth, piece, bits);
+        }
+        if let Some(histogram) = self.histogram.as_mut() {
+            histogram.attempt(depth);
+        }
+        let reason = if !placement.is_valid(bits) {
+            Some(Prune::Overlap)
+        } else {
+            let occ = bits.or(&placement.occupied);
+            let index = self.cell_index();
+            match puzzle.uncoverable_cell(index, &occ, remaining) {
+                Some(cell) => Some(Prune::Uncoverable(puzzle.grid.coord(cell))),
+                None => puzzle.dead_region(index, &occ, remaining).map(|region| {
+                    let cell = puzzle.grid.coord(region.first().unwrap_or_default());
+                    Prune::DeadRegion(cell, region.len())
+                }),
+            }
+        };
+        if let (Some(trace), Some(reason)) = (self.trace.as_mut(), reason.as_ref()) {
+            trace.prune(depth, reason);
+        }
+        reason
+    }
+
+    fn place(&mut self, placement: &mut Placement, piece: &Piece, bits: &Bitset) {
+        self.nodes += 1;
+        if let Some(trace) = self.trace.as_mut() {
+            trace.accept(placement.placed.len(), piece, bits);
+        }
+        if let Some(histogram) = self.histogram.as_mut() {
+            histogram.accept(placement.placed.len());
+        }
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.place(piece.piece_id, bits);
+        }
+        if let Some(tree) = self.tree.as_mut() {
+            tree.place(piece, bits);
+        }
+        if let Some(warm) = self.warm.as_mut() {
+            warm.enter(self.num_solutions);
+        }
+        if let Some(progress) = &self.progress {
+            if self.nodes.is_multiple_of(events::PROGRESS_INTERVAL) {
+                progress(events::PROGRESS_INTERVAL, 0);
+            }
+        }
+        if let Some(events) = &self.events {
+            let _ = events.send(Event::PiecePlaced {
+                piece_id: piece.piece_id,
+                bits: bits.clone(),
+                depth: placement.placed.len(),
+            });
+            if self.nodes.is_multiple_of(events::PROGRESS_INTERVAL) {
+                let _ = events.send(Event::Progress {
+                    nodes: self.nodes,
+                    solutions: self.num_solutions,
+                });
+            }
+        }
+        placement.place(piece.piece_id, bits.clone());
+        if placement.placed.len() > self.deepest.placed.len() {
+            self.deepest = placement.clone();
+        }
+        self.wait(
+            placement,
+            &Event::PiecePlaced {
+                piece_id: piece.piece_id,
+                bits: bits.clone(),
+                depth: placement.placed.len() - 1,
+            },
+        );
+    }
+
+    /// Lets `pause` see `event`, unless the search has already stopped.
+    fn wait(&mut self, placement: &Placement, event: &Event) {
+        if let (false, Some(pause)) = (self.stopped, self.pause.as_mut()) {
+            self.stopped = !pause(placement, event);
+        }
+    }
+
+    fn backtrack(&mut self, placement: &mut Placement, piece: &Piece) {
+        let popped = placement.pop();
+        let stopped = self.should_stop();
+        if let (Some(warm), Some(popped)) = (self.warm.as_mut(), popped) {
+            let solutions = self.num_solutions;
+            warm.leave(
+                popped.piece_id,
+                popped.bits,
+                solutions,
+                stopped,
+                &mut self.budget,
+            );
+        }
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.backtrack();
+        }
+        if let Some(tree) = self.tree.as_mut() {
+            tree.backtrack();
+        }
+        if let Some(events) = &self.events {
+            let _ = events.send(Event::Backtracked {
+                piece_id: piece.piece_id,
+                depth: placement.placed.len(),
+            });
+        }
+        if let Some(trace) = self.trace.as_mut() {
+            trace.backtrack(placement.placed.len(), piece);
+        }
+    }
+
+    fn found(&mut self, placement: &Placement) {
+        if let Some(trace) = self.trace.as_mut() {
+            trace.solution(placement.placed.len(), self.num_solutions);
+        }
+        if let Some(timeline) = self.timeline.as_mut() {
+            timeline.record(self.nodes);
+        }
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.solution();
+        }
+        if let Some(tree) = self.tree.as_mut() {
+            tree.solution();
+        }
+        if let Some(events) = &self.events {
+            let _ = events.send(Event::SolutionFound {
+                index: self.num_solutions,
+            });
+        }
+        if let Some(progress) = &self.progress {
+            progress(0, 1);
+        }
+        self.wait(
+            placement,
+            &Event::SolutionFound {
+                index: self.num_solutions,
+            },
+        );
+        self.num_solutions += 1;
+    }
+
+    /// Archives, prints or writes out a solution and counts it.
+    fn emit(&mut self, puzzle: &Puzzle, placement: &Placement) {
+        if let Some(unique) = self.unique.as_mut() {
+            if !unique.is_new(puzzle, placement, &mut self.budget) {
+                return;
+            }
+        }
+        if let Some(file) = self.solutions_file.as_mut() {
+            file.push(placement)
+                .expect("Failed to write solutions file");
+        }
+        if self.count_only {
+            return self.found(placement);
+        }
+        // Once there is no room left to collect them, solutions are output
+        // as they come instead.
+        if let Some(collected) = self.collected.as_mut() {
+            if self
+                .budget
+                .reserve("collected solutions", placement.bytes())
+            {
+                collected.push(placement.clone());
+                self.found(placement);
+                return;
+            }
+        }
+        let _span = profile::span("output");
+        match (self.archive.as_mut(), self.output_dir.as_mut()) {
+            (Some(archive), _) => archive.push(placement).expect("Failed to write archive"),
+            (None, Some(dir)) => dir
+                .write(puzzle, placement)
+                .expect("Failed to write solution file"),
+            (None, None) => {
+                let out = self.output.get_or_insert_with(|| Box::new(io::stdout()));
+                puzzle
+                    .show(out, placement)
+                    .and_then(|_| writeln!(out, "{}", self.num_solutions))
+                    .expect("Failed to write solution");
+            }
+        }
+        self.found(placement);
+    }
+
+    /// Counts the ways of filling the target by always covering its lowest
+    /// empty cell next, so each solution is reached exactly once. Pieces left
+    /// over once the target is full go into the don't-care cells in order.
+    /// Solutions are only output when they are being collected.
+    pub fn fill(&mut self, puzzle: &Puzzle, placement: &mut Placement, remaining: &[usize]) {
+        let count_only = self.count_only;
+        self.count_only |= self.collected.is_none();
+        self.indexed(puzzle, |solver| {
+            solver.search(puzzle, placement, remaining, CellChoice::Lowest)
+        });
+        self.count_only = count_only;
+    }
+
+    pub fn solve(&mut self, puzzle: &Puzzle, placement: &mut Placement, remaining: &[usize]) {
+        self.indexed(puzzle, |solver| {
+            solver.search(puzzle, placement, remaining, CellChoice::MostConstrained)
+        })
+    }
+
+    fn search(
+        &mut self,
+        puzzle: &Puzzle,
+        placement: &mut Placement,
+        remaining: &[usize],
+        choice: CellChoice,
+    ) {
+        let state = self
+            .warm
+            .as_ref()
+            .map(|warm| (placement.occupied.clone(), warm.remaining_key(remaining)));
+        if let (Some(warm), Some(state)) = (self.warm.as_ref(), &state) {
+            if warm.is_dead(state) {
+                return;
+            }
+        }
+        let nogood = self.nogood_key(placement, remaining);
+        if let (Some(nogoods), Some(key)) = (self.nogoods.as_mut(), &nogood) {
+            if nogoods.is_known(key) {
+                return;
+            }
+        }
+        let before = self.num_solutions;
+        match self.propagate(puzzle, placement, remaining) {
+            Propagated::Dead => {}
+            Propagated::Forced(forced, remaining) => {
+                self.search(puzzle, placement, &remaining, choice);
+                self.unpropagate(puzzle, placement, &forced);
+            }
+            Propagated::Nothing if !self.selected(placement, remaining) => {}
+            Propagated::Nothing => self.branch(puzzle, placement, remaining, choice),
+        }
+
+        // A subtree cut short, or whose solutions were filtered out, may
+        // still have solutions on another run.
+        let dead = self.num_solutions == before
+            && !self.should_stop()
+            && self.unique.is_none()
+            && self.groups.is_empty();
+        if let (Some(warm), Some(state), true) = (self.warm.as_mut(), state, dead) {
+            warm.mark_dead(state, &mut self.budget);
+        }
+        if let Some(key) = nogood {
+            self.learn(before, key);
+        }
+    }
+
+    /// The conflict a failure here would be learned as. Groups count pieces
+    /// by identity rather than shape, and a subtree whose solutions were all
+    /// symmetric to earlier ones hasn't failed, so nothing is learned with
+    /// either.
+    fn nogood_key(&self, placement: &Placement, remaining: &[usize]) -> Option<(Bitset, u64)> {
+        let nogoods = self.nogoods.as_ref()?;
+        (self.groups.is_empty() && self.unique.is_none())
+            .then(|| nogoods.key(&placement.occupied, remaining))
+    }
+
+    /// Learns `key` if the subtree since `before` solutions failed, unless
+    /// it was cut short by the node limit.
+    fn learn(&mut self, before: usize, key: (Bitset, u64)) {
+        if self.num_solutions == before && !self.should_stop() {
+            if let Some(nogoods) = self.nogoods.as_mut() {
+                nogoods.learn(key, &mut self.budget);
+            }
+        }
+    }
+
+    /// Covers the empty target cell picked by `choice` in each way left to
+    /// cover it. Each solution is reached exactly once, with copies of a
+    /// piece covering cells in order. Required pieces left over once the
+    /// target is full go into the don't-care cells in order.
+    fn branch(
+        &mut self,
+        puzzle: &Puzzle,
+        placement: &mut Placement,
+        remaining: &[usize],
+        choice: CellChoice,
+    ) {
+        if placement.occupied.and(&puzzle.target) == puzzle.target {
+            let required: Vec<usize> = remaining
+                .iter()
+                .copied()
+                .filter(|id| !puzzle.pieces[*id].spare)
+                .collect();
+            match required.split_first() {
+                None => {
+                    if self.accepts(puzzle, placement) {
+                        self.emit(puzzle, placement);
+                    }
+                }
+                Some((piece_id, new_remaining)) => {
+                    let piece = &puzzle.pieces[*piece_id];
+                    for bits in piece.placements.iter() {
+                        if self.should_stop() {
+                            return;
+                        }
+                        if placement.is_valid(bits) {
+                            self.place(placement, piece, bits);
+                            self.search(puzzle, placement, new_remaining, choice);
+                            self.backtrack(placement, piece);
+                        }
+                    }
+                }
+            }
+            return;
+        }
+
+        let Some((_, candidates)) = self.next_cell(puzzle, placement, remaining, choice) else {
+            return;
+        };
+        let mut extended = false;
+        // Candidates come piece by piece, each piece's rest is made once.
+        let mut new_remaining = Vec::new();
+        let mut previous = None;
+        for (piece_id, bits) in candidates.iter() {
+            if self.should_stop() {
+                return;
+            }
+            let piece = &puzzle.pieces[*piece_id];
+            if previous != Some(*piece_id) {
+                previous = Some(*piece_id);
+                new_remaining = remaining.to_vec();
+                new_remaining.retain(|&id| id != *piece_id);
+            }
+            if self
+                .check(puzzle, placement, piece, bits, &new_remaining)
+                .is_none()
+            {
+                extended = true;
+                self.place(placement, piece, bits);
+                self.search(puzzle, placement, &new_remaining, choice);
+                self.backtrack(placement, piece);
+            }
+        }
+        if let (false, Some(near_misses)) = (extended, self.near_misses.as_mut()) {
+            near_misses.dead_end(puzzle, placement);
+        }
+    }
+
+    pub fn corner_solve(
+        &mut self,
+        puzzle: &Puzzle,
+        placement: &mut Placement,
+        corners: &[Bitset],
+        remaining: &[usize],
+    ) {
+        self.indexed(puzzle, |solver| {
+            solver.corner_node(puzzle, placement, corners, remaining)
+        })
+    }
+
+    fn corner_node(
+        &mut self,
+        puzzle: &Puzzle,
+        placement: &mut Placement,
+        corners: &[Bitset],
+        remaining: &[usize],
+    ) {
+        if corners.is_empty() {
+            self.search(puzzle, placement, remaining, CellChoice::MostConstrained);
+            return;
+        }
+
+        let mut new_corners = corners.to_vec();
+        let corner = new_corners.pop().unwrap();
+        // On boards three cells wide one piece can reach two corners.
+        if !corner.is_disjoint(&placement.occupied) {
+            return self.corner_node(puzzle, placement, &new_corners, remaining);
+        }
+        for piece_id in remaining.iter() {
+            if !puzzle.usable(*piece_id, remaining) {
+                continue;
+            }
+            let piece = &puzzle.pieces[*piece_id];
+            let mut new_remaining = remaining.to_vec();
+            new_remaining.retain(|&id| id != *piece_id);
+            for bits in piece.placements.iter() {
+                if self.should_stop() {
+                    return;
+                }
+                if bits.is_disjoint(&corner) {
+                    if let Some(histogram) = self.histogram.as_mut() {
+                        histogram.attempt(placement.placed.len());
+                    }
+                    if let Some(trace) = self.trace.as_mut() {
+                        trace.attempt(placement.placed.len(), piece, bits);
+                        trace.prune(placement.placed.len(), &Prune::MissesCorner);
+                    }
+                    continue;
+                }
+                if self
+                    .check(puzzle, placement, piece, bits, &new_remaining)
+                    .is_none()
+                {
+                    self.place(placement, piece, bits);
+                    self.corner_node(puzzle, placement, &new_corners, &new_remaining);
+                    self.backtrack(placement, piece);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn soma() -> Puzzle {
+        Puzzle::read(Path::new("puzzles/soma.txt"), DEFAULT_PALETTE).unwrap()
+    }
+
+    fn count(puzzle: &Puzzle, solver: &mut Solver) -> usize {
+        solver.count_only = true;
+        let remaining: Vec<usize> = (0..puzzle.pieces.len()).collect();
+        solver.solve(puzzle, &mut Placement::new(), &remaining);
+        solver.num_solutions
+    }
+
+    #[test]
+    fn spare_pieces_may_be_left_out() {
+        // The spare monomino has no room left on three cells and must be
+        // used on four.
+        let text = "spare,3x1x1\nI,red,000-100\nA,blue,000\nB,green,000,0-1\n";
+        let puzzle = Puzzle::parse(text, DEFAULT_PALETTE).unwrap();
+        assert_eq!(count(&puzzle, &mut Solver::new()), 2);
+
+        let puzzle = Puzzle::parse(&text.replace("3x1x1", "4x1x1"), DEFAULT_PALETTE).unwrap();
+        assert_eq!(count(&puzzle, &mut Solver::new()), 6);
+    }
+
+    #[test]
+    fn pieces_are_capped_counting_copies() {
+        let text = format!("m,4x4x4\nA,red,000,{}\n", MAX_PIECES);
+        let puzzle = Puzzle::parse(&text, DEFAULT_PALETTE).unwrap();
+        assert_eq!(puzzle.pieces.last().unwrap().char_id(), 'Z');
+
+        assert!(Puzzle::parse("m,4x4x4\nA,red,000,64\n", DEFAULT_PALETTE).is_err());
+        let text = "m,4x4x4\nA,red,000,20\nB,blue,000,20\n";
+        assert!(Puzzle::parse(text, DEFAULT_PALETTE).is_err());
+    }
+
+    #[test]
+    fn dont_care_cells_need_not_be_covered() {
+        let text = "optional,3x1x1,200\nI,red,000-100\nA,blue,000\n";
+        let puzzle = Puzzle::parse(text, DEFAULT_PALETTE).unwrap();
+        assert_eq!(count(&puzzle, &mut Solver::new()), 2);
+
+        // A spare piece isn't put in don't-care cells once the target is
+        // full, so the only solution with it has it at the other end.
+        let text = "optional,3x1x1,200\nI,red,000-100\nA,blue,000,0-1\n";
+        let puzzle = Puzzle::parse(text, DEFAULT_PALETTE).unwrap();
+        assert_eq!(count(&puzzle, &mut Solver::new()), 2);
+    }
+
+    #[test]
+    fn masks_past_the_first_word() {
+        let mut bits = Bitset::cell(3);
+        bits.set(100);
+        assert_eq!(bits.len(), 2);
+        assert_eq!(bits.first(), Some(3));
+        assert_eq!(bits.iter().collect::<Vec<_>>(), [3, 100]);
+        assert_eq!(Bitset::from_hex(&format!("{:x}", bits)), Some(bits.clone()));
+        // Masks within 64 cells are written as they were as a `u64`.
+        assert_eq!(format!("{:#06x}", Bitset::from(0x13)), "0x0013");
+        assert_eq!(Bitset::from_hex("13"), Some(Bitset::from(0x13)));
+
+        let far = Bitset::cell(700);
+        let both = bits.or(&far);
+        assert_eq!(both.iter().collect::<Vec<_>>(), [3, 100, 700]);
+        assert_eq!(Bitset::from_hex(&format!("{:x}", both)), Some(both.clone()));
+        assert!(both > bits && bits > Bitset::cell(99));
+        // Sets that no longer reach past the inline words are equal to ones
+        // that never did.
+        assert_eq!(both.without(&far), bits);
+        assert!(both.is_disjoint(&Bitset::cell(5)) && !both.is_disjoint(&far));
+    }
+
+    /// A cube of side `side` cut into a slab of two layers and one of the
+    /// rest.
+    fn slabs(side: i32) -> Puzzle {
+        let slab = |layers: Range<i32>| {
+            let grid = Grid::new(side, side, side);
+            let blocks: Vec<String> = grid
+                .cells()
+                .filter(|cell| layers.contains(&cell.z))
+                .map(|cell| format!("{}{}{}", cell.x, cell.y, cell.z))
+                .collect();
+            blocks.join("-")
+        };
+        let text = format!(
+            "c,{side}x{side}x{side}\nA,red,{}\nB,blue,{}\n",
+            slab(0..2),
+            slab(2..side)
+        );
+        Puzzle::parse(&text, DEFAULT_PALETTE).unwrap()
+    }
+
+    #[test]
+    fn boards_past_64_cells_solve() {
+        // The thin slab goes against any of the six faces.
+        assert_eq!(count(&slabs(5), &mut Solver::new()), 6);
+        assert_eq!(count(&slabs(6), &mut Solver::new()), 6);
+    }
+
+    #[test]
+    fn boards_past_64_cells_propagate() {
+        let mut solver = Solver::new();
+        solver.propagate = true;
+        assert_eq!(count(&slabs(5), &mut solver), 6);
+    }
+
+    #[test]
+    fn fill_finds_what_solve_finds() {
+        let texts = [
+            "spare,4x1x1\nI,red,000-100\nA,blue,000\nB,green,000,0-1\n",
+            "optional,3x1x1,200\nI,red,000-100\nA,blue,000,0-1\n",
+            "m,2x2x2\nL,red,000-100-010\nI,blue,000-100,0-3\nA,green,000,0-2\n",
+        ];
+        for text in texts {
+            let puzzle = Puzzle::parse(text, DEFAULT_PALETTE).unwrap();
+            let remaining: Vec<usize> = (0..puzzle.pieces.len()).collect();
+            let mut solver = Solver::new();
+            solver.fill(&puzzle, &mut Placement::new(), &remaining);
+            assert_eq!(
+                solver.num_solutions,
+                count(&puzzle, &mut Solver::new()),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn solver_reused_after_placements_change() {
+        let mut puzzle = soma();
+        let mut solver = Solver::new();
+        count(&puzzle, &mut solver);
+        puzzle.translate_only();
+        solver.num_solutions = 0;
+        assert_eq!(
+            count(&puzzle, &mut solver),
+            count(&puzzle, &mut Solver::new())
+        );
+    }
+
+    #[test]
+    fn parallel_search_reports_each_solution_once() {
+        let puzzle = soma();
+        let corners: Vec<Bitset> = puzzle
+            .corners()
+            .iter()
+            .map(|corner| Bitset::cell(*corner))
+            .collect();
+        let reported = Arc::new(AtomicUsize::new(0));
+        let mut solver = Solver::new();
+        solver.output = Some(Box::new(io::sink()));
+        let counter = Arc::clone(&reported);
+        solver.progress = Some(Arc::new(move |_, found| {
+            counter.fetch_add(found, Ordering::Relaxed);
+        }));
+        let remaining: Vec<usize> = (0..puzzle.pieces.len()).collect();
+        solver.par_corner_solve(&puzzle, &mut Placement::new(), &corners, &remaining);
+        assert_eq!(solver.num_solutions, 11520);
+        assert_eq!(reported.load(Ordering::Relaxed), 11520);
+    }
+}
@@ -1,584 +1,2567 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+#[cfg(feature = "viewer")]
+use puzzle_cubes::viewer::{self, Source};
+use puzzle_cubes::{
+    adjacency::Adjacency,
+    anneal::Anneal,
+    archive::{ArchiveReader, ArchiveWriter},
+    catalog::Catalog,
+    cells::{self, CellLayout},
+    compare::Strategy,
+    dlx::Algorithm,
+    dot::SearchTree,
+    error::PuzzleError,
+    events::Event,
+    family,
+    generate::StandardSet,
+    grid::Grid,
+    histogram::Histogram,
+    input,
+    insertion::Face,
+    memory::{self, Budget},
+    metrics,
+    nearmiss::NearMisses,
+    nogood::Nogoods,
+    outdir::{FileNames, OutputDir, SolutionFormat},
+    palette::Palette,
+    play::Outlook,
+    profile,
+    provenance::Provenance,
+    record::Recorder,
+    report::{SolutionFilter, SolutionReport},
+    restart::{Finish, Restarts},
+    rng::Rng,
+    shape::{Preset, Shape},
+    solutions::SolutionsFile,
+    split::Axis,
+    tikz::TikzStyle,
+    timeline::Timeline,
+    trace::Trace,
+    transform::{Axes, Transform},
+    unique::Unique,
+    view::{TableFormat, View},
+    warmstart::WarmStart,
+    Bitset, Color, Placement, Puzzle, Solver, DEFAULT_PALETTE,
+};
 use std::{
-    fs::File,
-    io::{self, BufRead, BufReader},
+    collections::HashSet,
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
+#[cfg(feature = "viewer")]
+use std::{sync::mpsc, thread};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
+#[command(subcommand_required = true)]
 struct Args {
-    /// Puzzle file
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Write results to this file instead of stdout
+    #[arg(short, long, global = true, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Append to the output file instead of replacing it
+    #[arg(long, global = true, requires = "output")]
+    append: bool,
+
+    /// Worker threads (defaults to the available parallelism)
+    #[arg(
+        long,
+        global = true,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    threads: Option<usize>,
+
+    /// Colors given to pieces without one, cycled by piece index
+    #[arg(long, global = true, value_delimiter = ',')]
+    colors: Vec<Color>,
+
+    /// How piece colors are rendered
+    #[arg(long, global = true, value_enum, default_value_t)]
+    palette: Palette,
+
+    /// Draw two rows per line with half-block characters
+    #[arg(long, global = true)]
+    compact: bool,
+
+    /// Move pieces without rotating them, keeping the orientation they were
+    /// given in
+    #[arg(long, global = true)]
+    translate_only: bool,
+
+    /// Axes puzzle files were written with, such as xzy for files with y
+    /// up, applied after any their header gives
+    #[arg(long, global = true, value_name = "AXES")]
+    axes: Option<Axes>,
+
+    /// Number the layers, columns and rows around printed boards
+    #[arg(long, global = true)]
+    labels: bool,
+
+    /// Mark the cell at x=0, y=0, z=0 with a < after it
+    #[arg(long, global = true)]
+    origin: bool,
+
+    /// Seed for randomized search
+    #[arg(long, global = true, default_value_t = 0)]
+    seed: u64,
+
+    /// Write a chrome://tracing profile of the run to a file
+    #[arg(long, global = true, value_name = "FILE")]
+    profile: Option<PathBuf>,
+}
+
+/// How `solve` and `count` search.
+#[derive(clap::Args, Default)]
+struct SearchArgs {
+    /// Puzzle file, `-` for stdin
     puzzle: PathBuf,
 
-    /// Returns solution to sudoku
+    /// Place a piece before searching, as NAME=BLOCKS or NAME=0xMASK, such as
+    /// a piece fixed in one spot to leave out rotated copies of solutions
+    #[arg(long, value_name = "NAME=CELLS")]
+    place: Vec<String>,
+
+    /// Place pieces that fit in only one way before branching
+    #[arg(long)]
+    propagate: bool,
+
+    /// Only count solutions that can be put together by pushing each piece
+    /// straight in through this side of the box, the others being closed
+    #[arg(long, value_name = "FACE", value_enum, conflicts_with_all = ["cache", "nogoods"])]
+    open_face: Option<Face>,
+
+    /// How the search looks for solutions
+    #[arg(long, value_enum, default_value_t, conflicts_with_all = ["propagate", "nogoods", "cache"])]
+    algorithm: Algorithm,
+
+    /// Reuse piece tables, dead search states and learned placement order
+    /// cached in this directory, and update the cache after the run. The
+    /// learned order isn't used with --limit
+    #[arg(long, value_name = "DIR", conflicts_with = "translate_only")]
+    cache: Option<PathBuf>,
+
+    /// Remember failed states by their empty cells and remaining shapes, and
+    /// skip them when reached again
+    #[arg(long)]
+    nogoods: bool,
+
+    /// Stop after this many solutions. The search then runs on one thread,
+    /// and its order only depends on the puzzle file and options, so they're
+    /// the same ones every run
+    #[arg(long, value_name = "N", visible_alias = "first")]
+    limit: Option<usize>,
+
+    /// Only keep the first of solutions that are rotations of each other
+    #[arg(long)]
+    unique: bool,
+
+    /// Also count mirror images of a solution as the same, pieces turning
+    /// into their mirror images among the others
+    #[arg(long, requires = "unique")]
+    reflections: bool,
+
+    /// Report the memory used by the solver's tables
+    #[arg(long)]
+    memory: bool,
+
+    /// Keep the solver's tables within this size (e.g. 512M, 2G); nogoods,
+    /// the warm start and the unique solutions stop growing at it. The
+    /// search then runs on one thread
+    #[arg(long, value_name = "SIZE", value_parser = memory::parse_size)]
+    memory_limit: Option<usize>,
+}
+
+/// What `solve` reports and the searches for a single solution. `count`
+/// uses the defaults, which leave all of it off.
+#[derive(clap::Args, Default)]
+struct SolveArgs {
+    /// After the search, also print how many placements it tried and how
+    /// long it took
     #[arg(short, long)]
     verbose: bool,
-}
 
-#[derive(Clone, Debug, PartialEq)]
-enum Color {
-    Red,
-    Yellow,
-    Blue,
-    White,
-}
+    /// Log every solver decision, optionally only up to the given depth
+    #[arg(long, value_name = "DEPTH", num_args = 0..=1, default_missing_value = "64")]
+    trace: Option<usize>,
 
-#[derive(Clone, Debug, PartialEq)]
-struct Piece {
-    piece_id: usize,
-    name: String,
-    color: Color,
-    size: usize,
-    orintations: Vec<Orintaion>,
-    placements: Vec<Bitset>,
-}
+    /// How the piece summary is printed
+    #[arg(long, value_name = "FORMAT", value_enum, default_value_t)]
+    piece_format: TableFormat,
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-struct Coord {
-    x: i32,
-    y: i32,
-    z: i32,
-}
+    /// Print placement attempts and successes by depth after the run
+    #[arg(long, value_name = "FORMAT", value_enum, num_args = 0..=1, default_missing_value = "table")]
+    histogram: Option<TableFormat>,
 
-impl Coord {
-    pub fn new(x: i32, y: i32, z: i32) -> Self {
-        Self { x, y, z }
-    }
-}
+    /// Look for one solution, restarting with a new random order whenever a
+    /// run has made this many placements
+    #[arg(long, value_name = "NODES", conflicts_with_all = ["open_face", "algorithm", "unique"])]
+    restart_budget: Option<u64>,
 
-#[derive(Clone, Debug, PartialEq)]
-struct Block {
-    coord: Coord,
-    offset: i32,
-}
+    /// Grow the restart budget along the Luby sequence
+    #[arg(long, requires = "restart_budget")]
+    luby: bool,
 
-impl Block {
-    pub fn new(coord: Coord, size: usize) -> Self {
-        Self {
-            coord,
-            offset: Self::to_offset(coord, size),
-        }
-    }
+    /// Give up after this many restarts
+    #[arg(long, default_value_t = 1000, requires = "restart_budget")]
+    max_restarts: usize,
 
-    pub fn to_offset(coord: Coord, size: usize) -> i32 {
-        coord.x + coord.y * size as i32 + coord.z * size as i32 * size as i32
-    }
-}
+    /// Pack heuristically, keeping only this many partial placements per step
+    #[arg(long, value_name = "WIDTH", conflicts_with_all = ["restart_budget", "open_face", "algorithm", "unique"])]
+    beam: Option<usize>,
 
-#[derive(Clone, Debug, PartialEq)]
-struct Orintaion {
-    blocks: Vec<Coord>,
-}
+    /// Pack by simulated annealing for this many moves
+    #[arg(long, value_name = "STEPS", conflicts_with_all = ["restart_budget", "beam", "open_face", "algorithm", "unique"])]
+    anneal: Option<u64>,
 
-impl Color {
-    pub fn color(&self, str: &str) -> String {
-        match self {
-            Color::Red => str.red(),
-            Color::Yellow => str.yellow(),
-            Color::Blue => str.blue(),
-            Color::White => str.white(),
-        }
-        .to_string()
-    }
-}
+    /// Write when each solution was found to a file, as JSON if it ends in
+    /// .json and CSV otherwise
+    #[arg(long, value_name = "FILE")]
+    timeline: Option<PathBuf>,
 
-impl Piece {
-    pub fn new(piece_id: usize, name: String, color: Color, orintaion: Orintaion) -> Self {
-        let oris = orintaion.all_orintations();
-        Self {
-            piece_id,
-            name,
-            color,
-            size: orintaion.blocks.len(),
-            orintations: orintaion.all_orintations(),
-            placements: oris.iter().flat_map(|ori| ori.placements()).collect(),
-        }
-    }
+    /// Write the trace to a file instead of stderr
+    #[arg(long, requires = "trace")]
+    trace_file: Option<PathBuf>,
 
-    pub fn char_id(&self) -> char {
-        match self.piece_id {
-            0..=9 => (self.piece_id as u8 + b'0') as char,
-            10..=35 => (self.piece_id as u8 + b'A' - 10) as char,
-            _ => panic!("Invalid piece id"),
-        }
-    }
+    /// Store solutions in a compressed archive instead of printing them
+    #[arg(long, value_name = "FILE")]
+    archive: Option<PathBuf>,
 
-    pub fn colored_id(&self) -> String {
-        self.color.color(&self.char_id().to_string())
-    }
+    /// Only count the solutions instead of printing them
+    #[arg(long, conflicts_with_all = ["archive", "beam", "anneal", "restart_budget", "replay"])]
+    count_only: bool,
 
-    pub fn colored_name(&self) -> String {
-        self.color.color(&self.name)
-    }
+    /// Show the placements made and solutions counted so far while counting
+    #[arg(long, requires = "count_only")]
+    progress: bool,
+
+    /// Write each solution to its own file in this directory instead of
+    /// printing them
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["archive", "count_only"])]
+    output_dir: Option<PathBuf>,
+
+    /// Format of the files in the output directory
+    #[arg(long, value_enum, default_value_t, requires = "output_dir")]
+    output_format: SolutionFormat,
+
+    /// How the files in the output directory are named
+    #[arg(long, value_enum, default_value_t, requires = "output_dir")]
+    file_names: FileNames,
+
+    /// Record the search's decisions to this file
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+
+    /// Repeat a recorded search instead of searching
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["restart_budget", "beam", "anneal", "open_face", "algorithm"])]
+    replay: Option<PathBuf>,
+
+    /// Write the top of the search tree to this file as a Graphviz DOT graph
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["beam", "anneal"])]
+    dot: Option<PathBuf>,
+
+    /// Deepest placements drawn in the DOT graph
+    #[arg(long, value_name = "DEPTH", default_value_t = 4, requires = "dot")]
+    dot_depth: usize,
+
+    /// Most nodes drawn in the DOT graph
+    #[arg(long, value_name = "NODES", default_value_t = 1000, requires = "dot")]
+    dot_nodes: usize,
+
+    /// After the run, report the dead ends reached most often that leave
+    /// at most this many target cells empty
+    #[arg(long, value_name = "CELLS", num_args = 0..=1, default_missing_value = "4")]
+    near_misses: Option<usize>,
+
+    /// How many near misses to show
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    near_misses_shown: usize,
+
+    /// Also add each solution to this file as piece ids and placement masks,
+    /// a JSON object per line if it ends in .jsonl and binary records
+    /// otherwise, e.g. solutions.db. The file is added to, not replaced
+    #[arg(long, value_name = "FILE")]
+    solutions_file: Option<PathBuf>,
+
+    /// Leave out solutions the solutions file already has, so a run that
+    /// was stopped can be run again into the same file
+    #[arg(long, requires = "solutions_file")]
+    dedup: bool,
+
+    /// Stop at each solution to show it until a key is pressed: n or space
+    /// for the next, p for the one before, q to quit
+    #[arg(long, conflicts_with_all = ["count_only", "beam", "anneal"])]
+    interactive: bool,
+
+    /// With --interactive, also stop after every placement, to put the
+    /// puzzle together piece by piece
+    #[arg(long, requires = "interactive")]
+    step: bool,
 }
 
-enum Direction {
-    Next,
-    Clk,
-    CClk,
+#[derive(Subcommand)]
+enum Command {
+    /// Search for solutions and print them
+    Solve {
+        #[command(flatten)]
+        search: SearchArgs,
+        #[command(flatten)]
+        solving: Box<SolveArgs>,
+    },
+    /// Count the solutions without printing them
+    Count {
+        #[command(flatten)]
+        search: SearchArgs,
+        /// Show the placements made and solutions counted so far
+        #[arg(long)]
+        progress: bool,
+    },
+    /// Check that a puzzle file reads and that every target cell and every
+    /// piece can be used
+    Validate {
+        /// Puzzle file
+        puzzle: PathBuf,
+    },
+    /// Print a puzzle's pieces and its board
+    Show {
+        /// Puzzle file
+        puzzle: PathBuf,
+        /// How the piece summary is printed
+        #[arg(long, value_name = "FORMAT", value_enum, default_value_t)]
+        piece_format: TableFormat,
+    },
+    /// Print a hash of the puzzle that ignores piece order, rotation and names
+    Fingerprint {
+        /// Puzzle file
+        puzzle: PathBuf,
+    },
+    /// Print how an archive or exported file was made
+    Provenance {
+        /// Archive, or a file written from one
+        file: PathBuf,
+    },
+    /// Check whether two puzzle files describe the same puzzle
+    Same {
+        /// First puzzle file
+        a: PathBuf,
+        /// Second puzzle file
+        b: PathBuf,
+    },
+    /// Solve every figure of a catalog and report which are possible
+    Catalog {
+        /// Catalog file
+        catalog: PathBuf,
+        /// Instead of solving the figures, list every set of this many of
+        /// the pieces that can build all of them
+        #[arg(long, value_name = "PIECES")]
+        choose: Option<usize>,
+    },
+    /// Show solutions stored in an archive
+    Unpack {
+        /// Archive file
+        archive: PathBuf,
+        /// Puzzle file the archive was made from
+        puzzle: PathBuf,
+        /// Only show the solution with this index
+        #[arg(short, long)]
+        index: Option<usize>,
+    },
+    /// Draw archived solutions as TikZ pictures for LaTeX documents
+    Tikz {
+        /// Archive file
+        archive: PathBuf,
+        /// Puzzle file the archive was made from
+        puzzle: PathBuf,
+        /// Only draw the solution with this index
+        #[arg(short, long)]
+        index: Option<usize>,
+        /// How each solution is drawn
+        #[arg(long, value_enum, default_value_t)]
+        style: TikzStyle,
+        /// TikZ scale, the size of a cube in centimetres
+        #[arg(long, default_value_t = 0.5)]
+        scale: f64,
+        /// Leave out the color definitions, for documents that define
+        /// `piece0`, `piece1` and so on themselves
+        #[arg(long)]
+        no_colors: bool,
+    },
+    /// Write an archived solution as an SVG template to print at real scale
+    /// and lay the pieces on while assembling
+    Template {
+        /// Archive file
+        archive: PathBuf,
+        /// Puzzle file the archive was made from
+        puzzle: PathBuf,
+        /// Index of the solution to draw
+        #[arg(short, long, default_value_t = 0)]
+        index: usize,
+        /// Side of a cube in millimetres, the size of the physical pieces
+        #[arg(long, default_value_t = 20.0)]
+        cell: f64,
+        /// Layers side by side on each row of the page
+        #[arg(long, default_value_t = 2)]
+        columns: usize,
+    },
+    /// Write archived solutions as CSV with the piece in each cell, for
+    /// spreadsheets
+    Cells {
+        /// Archive file
+        archive: PathBuf,
+        /// Puzzle file the archive was made from
+        puzzle: PathBuf,
+        /// Only write the solution with this index
+        #[arg(short, long)]
+        index: Option<usize>,
+        /// A row per cell, or a block per layer shaped like the board
+        #[arg(long, value_enum, default_value_t)]
+        layout: CellLayout,
+    },
+    /// Combine archives, such as those of the parts of a search run
+    /// separately, into one without duplicate solutions
+    MergeArchives {
+        /// Puzzle file the archives were made from
+        puzzle: PathBuf,
+        /// Archives to combine, solutions are kept in this order
+        #[arg(required = true)]
+        archives: Vec<PathBuf>,
+        /// Archive to write
+        #[arg(long, value_name = "FILE")]
+        into: PathBuf,
+        /// Also drop solutions that are rotations of one already kept
+        #[arg(long)]
+        symmetry: bool,
+    },
+    /// Write archived solutions as a web page with a 3D viewer
+    Html {
+        /// Archive file
+        archive: PathBuf,
+        /// Puzzle file the archive was made from
+        puzzle: PathBuf,
+        /// Only include the solution with this index
+        #[arg(short, long)]
+        index: Option<usize>,
+        /// Most solutions to include
+        #[arg(long, default_value_t = 100)]
+        limit: usize,
+    },
+    /// Count how many archived solutions have each pair of pieces touching
+    Adjacency {
+        /// Archive file
+        archive: PathBuf,
+        /// Puzzle file the archive was made from
+        puzzle: PathBuf,
+        /// How the counts are printed
+        #[arg(long, value_enum, default_value_t)]
+        format: TableFormat,
+    },
+    /// Choose piece colors so that pieces of one color touch as rarely as
+    /// possible in the archived solutions, and write the puzzle with them
+    Recolor {
+        /// Archive file, of all solutions or a sample
+        archive: PathBuf,
+        /// Puzzle file the archive was made from
+        puzzle: PathBuf,
+        /// How many colors to use, taken from --colors, as many as the
+        /// puzzle uses now by default
+        #[arg(long)]
+        count: Option<usize>,
+        /// Replace the puzzle file instead of printing it
+        #[arg(short, long)]
+        write: bool,
+    },
+    /// Group archived solutions into families that place pieces the same way
+    Families {
+        /// Archive file
+        archive: PathBuf,
+        /// Puzzle file the archive was made from
+        puzzle: PathBuf,
+        /// Pieces two solutions must place identically to be in one family,
+        /// half the pieces by default
+        #[arg(long)]
+        shared: Option<usize>,
+        /// Draw the representatives of this many of the largest families
+        #[arg(long, default_value_t = 5)]
+        show: usize,
+    },
+    /// Print the center of mass and stability of each archived solution, as
+    /// CSV
+    Metrics {
+        /// Archive file
+        archive: PathBuf,
+        /// Puzzle file the archive was made from
+        puzzle: PathBuf,
+        /// Print JSON instead
+        #[arg(long)]
+        json: bool,
+        /// Only solutions where every piece shows on the outside
+        #[arg(long, conflicts_with = "hidden")]
+        all_visible: bool,
+        /// Only solutions with a piece completely hidden inside
+        #[arg(long)]
+        hidden: bool,
+    },
+    /// Run the puzzle under several search strategies with the same limits
+    Compare {
+        /// Puzzle file
+        puzzle: PathBuf,
+        /// Strategies to run, all of them if not given
+        #[arg(short, long, value_enum, value_delimiter = ',')]
+        strategies: Vec<Strategy>,
+        /// Placements each strategy may make
+        #[arg(long, default_value_t = 1_000_000)]
+        nodes: u64,
+    },
+    /// Solve using any subset of the pieces and show which each solution uses
+    Subset {
+        /// Puzzle file
+        puzzle: PathBuf,
+        /// Pieces every solution must use, by name or id
+        #[arg(long, value_delimiter = ',')]
+        require: Vec<String>,
+        /// Exactly this many of these pieces are used, as names=count
+        #[arg(long)]
+        group: Vec<String>,
+        /// Place pieces that fit in only one way before branching
+        #[arg(long)]
+        propagate: bool,
+    },
+    /// Solve a small puzzle while narrating each decision, for teaching
+    /// backtracking
+    Explain {
+        /// Puzzle file
+        puzzle: PathBuf,
+        /// Placements to narrate before stopping
+        #[arg(long, default_value_t = 50)]
+        steps: usize,
+        /// Place a piece first, as NAME=BLOCKS or NAME=0xMASK
+        #[arg(long, value_name = "NAME=CELLS")]
+        place: Vec<String>,
+    },
+    /// Place pieces by hand, checking after each move that the puzzle can
+    /// still be finished
+    Play {
+        /// Puzzle file
+        puzzle: PathBuf,
+        /// Placements each check may make before giving up
+        #[arg(long, default_value_t = 10_000_000)]
+        nodes: u64,
+    },
+    /// Turn a piece through its orientations in the terminal, to see which
+    /// orientation number is which
+    Spin {
+        /// Puzzle file
+        puzzle: PathBuf,
+        /// Piece name or id
+        piece: String,
+        /// Milliseconds each orientation is shown for
+        #[arg(long, default_value_t = 800)]
+        delay: u64,
+        /// Stop after one turn through the orientations instead of repeating
+        /// until interrupted
+        #[arg(long)]
+        once: bool,
+    },
+    /// Open a 3D window on a solution from an archive, or on a search as it
+    /// runs
+    #[cfg(feature = "viewer")]
+    Viewer {
+        /// Puzzle file
+        puzzle: PathBuf,
+        /// Archive to take the solution from, the puzzle is solved live
+        /// without one
+        #[arg(long)]
+        archive: Option<PathBuf>,
+        /// Solution in the archive to show
+        #[arg(long, default_value_t = 0, requires = "archive")]
+        index: usize,
+    },
+    /// Find the ways of dividing the pieces into two groups, one filling a
+    /// region of the target and the other the rest
+    Split {
+        /// Puzzle file
+        puzzle: PathBuf,
+        /// The region is the lower half of the target across this axis
+        #[arg(
+            long,
+            value_enum,
+            required_unless_present = "region",
+            conflicts_with = "region"
+        )]
+        half: Option<Axis>,
+        /// The region's cells, as blocks like in a puzzle file or a hex mask
+        #[arg(long, value_name = "CELLS")]
+        region: Option<String>,
+    },
+    /// Analyses for solving by hand, or with --solutions of an archive
+    /// without solving again
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Analyze {
+        #[command(subcommand)]
+        command: Option<AnalyzeCommand>,
+        /// Archive of solutions to report on: how many are distinct under
+        /// rotation, their metrics, a heatmap and which pieces touch
+        #[arg(long, value_name = "FILE", required = true)]
+        solutions: Option<PathBuf>,
+        /// Puzzle file the archive was made from
+        #[arg(required = true)]
+        puzzle: Option<PathBuf>,
+        /// Show how often this piece covers each cell, instead of how many
+        /// pieces each cell holds
+        #[arg(long)]
+        piece: Option<String>,
+        /// Only solutions making this placement, as NAME=BLOCKS or NAME=0xMASK
+        #[arg(long, value_name = "NAME=CELLS")]
+        place: Vec<String>,
+        /// Only solutions where every piece shows on the outside
+        #[arg(long, conflicts_with = "hidden")]
+        all_visible: bool,
+        /// Only solutions with a piece completely hidden inside
+        #[arg(long)]
+        hidden: bool,
+    },
+    /// Work with target shapes
+    Shape {
+        #[command(subcommand)]
+        command: ShapeCommand,
+    },
+    /// Write a standard piece set as a puzzle file
+    Generate {
+        /// Piece set
+        #[arg(value_enum)]
+        set: StandardSet,
+    },
+    /// Rewrite a puzzle file in the canonical style, pieces from a library
+    /// written out in full
+    Fmt {
+        /// Puzzle file
+        puzzle: PathBuf,
+        /// Order the pieces by name
+        #[arg(long)]
+        sort: bool,
+        /// Replace the file instead of printing it
+        #[arg(short, long)]
+        write: bool,
+    },
+    /// Write the puzzle in another format
+    Export {
+        /// Output format
+        #[arg(value_enum)]
+        format: ExportFormat,
+        /// Puzzle file
+        puzzle: PathBuf,
+    },
+    /// Check a solution, e.g. a SAT solver's model for an exported CNF
+    Verify {
+        /// Puzzle file
+        puzzle: PathBuf,
+        /// Solver output with the model on `v` lines, `-` for stdin
+        model: PathBuf,
+    },
+    /// Combine the pieces of two puzzle files
+    Merge {
+        /// First puzzle file
+        a: PathBuf,
+        /// Second puzzle file
+        b: PathBuf,
+    },
+    /// Remove the pieces of one puzzle file from another, matched by shape
+    Subtract {
+        /// Puzzle file to remove pieces from
+        a: PathBuf,
+        /// Puzzle file with the pieces to remove
+        b: PathBuf,
+    },
+    /// Rotate, mirror or translate a puzzle, to match files written with
+    /// other axes
+    Transform {
+        /// Puzzle file
+        puzzle: PathBuf,
+        /// Steps applied in order: rotate:AXIS[:TURNS] turns counterclockwise
+        /// looking down the axis, mirror:AXIS reflects along it and
+        /// translate:X,Y,Z moves the target within the grid
+        #[arg(required = true)]
+        steps: Vec<Transform>,
+        /// Only turn these pieces, by name or id, leaving the target alone
+        #[arg(long, num_args = 1..)]
+        pieces: Option<Vec<String>>,
+    },
+    /// Remove pieces by name or id
+    Remove {
+        /// Puzzle file
+        puzzle: PathBuf,
+        /// Piece names or ids
+        #[arg(required = true)]
+        pieces: Vec<String>,
+    },
+    /// Keep only the pieces given by name or id
+    Extract {
+        /// Puzzle file
+        puzzle: PathBuf,
+        /// Piece names or ids
+        #[arg(required = true)]
+        pieces: Vec<String>,
+    },
 }
 
-impl Orintaion {
-    pub fn new(blocks: Vec<Coord>) -> Self {
-        Self { blocks: blocks }
-    }
+#[derive(Subcommand)]
+enum AnalyzeCommand {
+    /// Show which pieces can cover each corner, or other cells, and in how
+    /// many ways
+    Corners {
+        /// Puzzle file
+        puzzle: PathBuf,
+        /// Cells to look at instead of the corners, as blocks like in a
+        /// puzzle file or a hex mask
+        #[arg(long, value_name = "CELLS")]
+        cells: Option<String>,
+        /// Place a piece first, as NAME=BLOCKS or NAME=0xMASK
+        #[arg(long, value_name = "NAME=CELLS")]
+        place: Vec<String>,
+    },
+    /// Show how many placements of a piece cover each cell, layer by layer
+    Reach {
+        /// Puzzle file
+        puzzle: PathBuf,
+        /// Piece name or id
+        piece: String,
+        /// Place a piece first, as NAME=BLOCKS or NAME=0xMASK
+        #[arg(long, value_name = "NAME=CELLS")]
+        place: Vec<String>,
+    },
+    /// Count the solutions that use each placement of a piece, to find the
+    /// keystone placement most solutions go through
+    Keystone {
+        /// Puzzle file
+        puzzle: PathBuf,
+        /// Piece name or id
+        piece: String,
+        /// Place a piece first, as NAME=BLOCKS or NAME=0xMASK
+        #[arg(long, value_name = "NAME=CELLS")]
+        place: Vec<String>,
+        /// How the counts are printed
+        #[arg(long, value_enum, default_value_t)]
+        format: TableFormat,
+    },
+}
 
-    fn normalise_first(&self) -> Self {
-        Orintaion::new(
-            self.blocks
-                .iter()
-                .map(|block| Coord {
-                    x: block.x - self.blocks[0].x,
-                    y: block.y - self.blocks[0].y,
-                    z: block.z - self.blocks[0].z,
-                })
-                .collect(),
-        )
+#[derive(Subcommand)]
+enum ShapeCommand {
+    /// Write a preset shape as a catalog figure, or as a puzzle with a piece set
+    Gen {
+        #[arg(value_enum)]
+        preset: Preset,
+        /// Size of the box the shape fills, as XxYxZ
+        #[arg(long, default_value = "4x4x4")]
+        size: Grid,
+        /// Wall thickness of shells
+        #[arg(long, default_value_t = 1)]
+        thickness: i32,
+        /// Write a puzzle filling the shape with this piece set
+        #[arg(long, value_enum)]
+        set: Option<StandardSet>,
+    },
+    /// Write a random connected shape, seeded by --seed
+    Random {
+        /// Size of the box the shape stays in, as XxYxZ
+        #[arg(long, default_value = "4x4x4")]
+        size: Grid,
+        /// Cells in the shape, the volume of the piece set by default
+        #[arg(long, required_unless_present = "set")]
+        volume: Option<usize>,
+        /// Write a puzzle filling the shape with this piece set, retrying
+        /// shapes the set clearly can't fill
+        #[arg(long, value_enum)]
+        set: Option<StandardSet>,
+        /// Shapes to try before giving up on finding a fillable one
+        #[arg(long, default_value_t = 1000)]
+        attempts: usize,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// DIMACS CNF for SAT solvers
+    Cnf,
+}
+
+fn main() {
+    let mut args = Args::parse();
+    if args.profile.is_some() {
+        profile::enable();
     }
+    let threads = args.threads.unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    });
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build_global()
+        .expect("Failed to build thread pool");
+    enable_terminal_colors();
+    let colors = if args.colors.is_empty() {
+        DEFAULT_PALETTE.to_vec()
+    } else {
+        args.colors.clone()
+    };
+    let mut out: Box<dyn Write + Send> = match &args.output {
+        Some(path) => {
+            colored::control::set_override(false);
+            let file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(args.append)
+                .truncate(!args.append)
+                .open(path)
+                .expect("Failed to open output file");
+            Box::new(BufWriter::new(file))
+        }
+        None => Box::new(io::stdout()),
+    };
 
-    pub fn placements(&self) -> Vec<Bitset> {
-        let mut placements = Vec::new();
-        for x in 0..SIZE {
-            for y in 0..SIZE {
-                for z in 0..SIZE {
-                    let mut valid = true;
-                    let mut bits = Bitset::empty();
-                    for block in self.blocks.iter() {
-                        let coord = Coord {
-                            x: block.x + x as i32,
-                            y: block.y + y as i32,
-                            z: block.z + z as i32,
-                        };
-                        if coord.x >= 0
-                            && coord.x < SIZE as i32
-                            && coord.y >= 0
-                            && coord.y < SIZE as i32
-                            && coord.z >= 0
-                            && coord.z < SIZE as i32
-                        {
-                            let index = 16 * coord.z + 4 * coord.y + coord.x;
-                            bits.set(index as usize);
-                        } else {
-                            valid = false;
-                            break;
+    match args.command.take() {
+        Some(Command::Solve { search, solving }) => {
+            out = solve(&args, &search, &solving, &colors, threads, out)
+        }
+        Some(Command::Count { search, progress }) => {
+            let solving = SolveArgs {
+                count_only: true,
+                progress,
+                ..SolveArgs::default()
+            };
+            out = solve(&args, &search, &solving, &colors, threads, out)
+        }
+        Some(Command::Validate { puzzle }) => {
+            let mut puzzle = read_puzzle(&puzzle, &colors, &args);
+            puzzle.view = view(&args);
+            check_puzzle(&puzzle);
+            let mut remaining: Vec<usize> = (0..puzzle.pieces.len()).collect();
+            let preprocessed = puzzle.preprocess(&mut Placement::new(), &mut remaining);
+            for cell in preprocessed.uncoverable.iter() {
+                let cell = puzzle.grid.coord(*cell);
+                eprintln!(
+                    "error: no piece can cover cell {}{}{}",
+                    cell.x, cell.y, cell.z
+                );
+            }
+            for piece_id in preprocessed.stranded.iter() {
+                eprintln!(
+                    "error: piece {} fits nowhere",
+                    puzzle.pieces[*piece_id].name
+                );
+            }
+            if !preprocessed.uncoverable.is_empty() || !preprocessed.stranded.is_empty() {
+                std::process::exit(1);
+            }
+            writeln!(
+                out,
+                "{}: valid, {} pieces for {} target cells",
+                puzzle.name,
+                puzzle.pieces.len(),
+                puzzle.target.len()
+            )
+            .expect("Failed to write output");
+        }
+        Some(Command::Show {
+            puzzle,
+            piece_format,
+        }) => {
+            let mut puzzle = read_puzzle(&puzzle, &colors, &args);
+            puzzle.view = view(&args);
+            writeln!(
+                out,
+                "{} ({}x{}x{}), {} target cells",
+                puzzle.name,
+                puzzle.grid.x,
+                puzzle.grid.y,
+                puzzle.grid.z,
+                puzzle.target.len()
+            )
+            .and_then(|_| puzzle.piece_table(&mut out, piece_format, &Placement::new()))
+            .and_then(|_| puzzle.show(&mut out, &Placement::new()))
+            .expect("Failed to write output");
+        }
+        Some(Command::Fingerprint { puzzle }) => {
+            let puzzle = read_puzzle(&puzzle, &colors, &args);
+            writeln!(out, "{:016x}  {}", puzzle.fingerprint(), puzzle.name)
+                .expect("Failed to write output");
+        }
+        Some(Command::Provenance { file }) => {
+            let provenance = match ArchiveReader::open(&file) {
+                Ok(mut archive) => archive.provenance().expect("Failed to read archive"),
+                Err(_) => {
+                    let mut text = String::new();
+                    input::open(&file)
+                        .and_then(|mut reader| reader.read_to_string(&mut text))
+                        .unwrap_or_else(|err| {
+                            eprintln!("error: {}: {}", file.display(), err);
+                            std::process::exit(1);
+                        });
+                    Provenance::find(&text)
+                }
+            };
+            match provenance {
+                Some(provenance) => write!(out, "{}", provenance).expect("Failed to write output"),
+                None => {
+                    eprintln!("error: {} records no provenance", file.display());
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Command::Same { a, b }) => {
+            let a = read_puzzle(&a, &colors, &args);
+            let b = read_puzzle(&b, &colors, &args);
+            if a.same_as(&b) {
+                writeln!(out, "same ({:016x})", a.fingerprint()).expect("Failed to write output");
+            } else {
+                writeln!(
+                    out,
+                    "different ({:016x} vs {:016x})",
+                    a.fingerprint(),
+                    b.fingerprint()
+                )
+                .and_then(|_| out.flush())
+                .expect("Failed to write output");
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Catalog {
+            catalog,
+            choose: Some(size),
+        }) => {
+            let catalog = read_catalog(&catalog, &colors, &args);
+            let sets = catalog.sets_building_all(size).unwrap_or_else(|err| {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            });
+            for set in sets.iter() {
+                let names: Vec<&str> = set
+                    .iter()
+                    .map(|id| catalog.pieces.pieces[*id].name.as_str())
+                    .collect();
+                writeln!(out, "{}", names.join(" ")).expect("Failed to write output");
+            }
+            writeln!(
+                out,
+                "{} sets of {} pieces build all {} figures",
+                sets.len(),
+                size,
+                catalog.figures.len()
+            )
+            .expect("Failed to write output");
+        }
+        Some(Command::Catalog {
+            catalog,
+            choose: None,
+        }) => {
+            let catalog = read_catalog(&catalog, &colors, &args);
+            writeln!(
+                out,
+                "{} ({} pieces)",
+                catalog.name,
+                catalog.pieces.pieces.len()
+            )
+            .expect("Failed to write output");
+            for result in catalog.solve() {
+                let status = if result.solutions > 0 {
+                    "possible".green()
+                } else {
+                    "impossible".red()
+                };
+                writeln!(
+                    out,
+                    "{:<16} {:>3} {:<10} {}",
+                    result.name, result.volume, status, result.solutions
+                )
+                .expect("Failed to write output");
+            }
+        }
+        Some(Command::Unpack {
+            archive,
+            puzzle,
+            index,
+        }) => {
+            let mut puzzle = read_puzzle(&puzzle, &colors, &args);
+            puzzle.view = view(&args);
+            let mut archive = ArchiveReader::open(&archive).expect("Failed to open archive");
+            match index {
+                Some(index) => match archive.get(index).expect("Failed to read archive") {
+                    Some(solution) => puzzle
+                        .show(&mut out, &Placement::from_solution(&solution))
+                        .expect("Failed to write output"),
+                    None => eprintln!("archive has only {} solutions", archive.len()),
+                },
+                None => {
+                    let mut n = 0;
+                    archive
+                        .for_each(|solution| {
+                            puzzle
+                                .show(&mut out, &Placement::from_solution(&solution))
+                                .and_then(|_| writeln!(out, "{}", n))
+                                .expect("Failed to write output");
+                            n += 1;
+                        })
+                        .expect("Failed to read archive");
+                }
+            }
+        }
+        Some(Command::Tikz {
+            archive,
+            puzzle,
+            index,
+            style,
+            scale,
+            no_colors,
+        }) => {
+            let mut puzzle = read_puzzle(&puzzle, &colors, &args);
+            puzzle.view = view(&args);
+            let mut archive = ArchiveReader::open(&archive).expect("Failed to open archive");
+            archive_provenance(&mut archive, &puzzle)
+                .write(&mut out, "% ")
+                .expect("Failed to write output");
+            if !no_colors {
+                puzzle
+                    .write_tikz_colors(&mut out)
+                    .expect("Failed to write output");
+            }
+            let mut draw = |n: usize, solution| {
+                writeln!(out, "% solution {}", n)
+                    .and_then(|_| {
+                        puzzle.write_tikz(
+                            &mut out,
+                            &Placement::from_solution(&solution),
+                            style,
+                            scale,
+                        )
+                    })
+                    .expect("Failed to write output");
+            };
+            match index {
+                Some(index) => match archive.get(index).expect("Failed to read archive") {
+                    Some(solution) => draw(index, solution),
+                    None => eprintln!("archive has only {} solutions", archive.len()),
+                },
+                None => {
+                    let mut n = 0;
+                    archive
+                        .for_each(|solution| {
+                            draw(n, solution);
+                            n += 1;
+                        })
+                        .expect("Failed to read archive");
+                }
+            }
+        }
+        Some(Command::Template {
+            archive,
+            puzzle,
+            index,
+            cell,
+            columns,
+        }) => {
+            let mut puzzle = read_puzzle(&puzzle, &colors, &args);
+            puzzle.view = view(&args);
+            let mut archive = ArchiveReader::open(&archive).expect("Failed to open archive");
+            let Some(solution) = archive.get(index).expect("Failed to read archive") else {
+                eprintln!("error: archive has only {} solutions", archive.len());
+                std::process::exit(1);
+            };
+            SolutionFormat::Svg
+                .write_provenance(&mut out, &archive_provenance(&mut archive, &puzzle))
+                .expect("Failed to write output");
+            puzzle
+                .write_template(
+                    &mut out,
+                    &Placement::from_solution(&solution),
+                    cell,
+                    columns,
+                )
+                .expect("Failed to write output");
+        }
+        Some(Command::Cells {
+            archive,
+            puzzle,
+            index,
+            layout,
+        }) => {
+            let puzzle = read_puzzle(&puzzle, &colors, &args);
+            let mut archive = ArchiveReader::open(&archive).expect("Failed to open archive");
+            cells::write_cells_header(&mut out, layout).expect("Failed to write output");
+            let mut write = |n: usize, solution| {
+                puzzle
+                    .write_cells(&mut out, n, &Placement::from_solution(&solution), layout)
+                    .expect("Failed to write output");
+            };
+            match index {
+                Some(index) => match archive.get(index).expect("Failed to read archive") {
+                    Some(solution) => write(index, solution),
+                    None => eprintln!("archive has only {} solutions", archive.len()),
+                },
+                None => {
+                    let mut n = 0;
+                    archive
+                        .for_each(|solution| {
+                            write(n, solution);
+                            n += 1;
+                        })
+                        .expect("Failed to read archive");
+                }
+            }
+        }
+        Some(Command::MergeArchives {
+            puzzle,
+            archives,
+            into,
+            symmetry,
+        }) => {
+            let puzzle = read_puzzle(&puzzle, &colors, &args);
+            let symmetries = match symmetry {
+                true => puzzle.symmetries(),
+                false => Vec::new(),
+            };
+            if archives.contains(&into) {
+                eprintln!("error: {} is also an input", into.display());
+                std::process::exit(1);
+            }
+            let mut provenance = Provenance::new(&puzzle);
+            let merged: Vec<String> = archives.iter().map(|p| p.display().to_string()).collect();
+            provenance.set("merged from", merged.join(" "));
+            if symmetry {
+                provenance.set("symmetry", "rotations dropped");
+            }
+            let mut writer = ArchiveWriter::create_with(&into, 4096, &provenance)
+                .expect("Failed to create archive");
+            let mut seen = HashSet::new();
+            let mut read = 0;
+            for path in archives.iter() {
+                let mut archive = ArchiveReader::open(path).unwrap_or_else(|err| {
+                    eprintln!("error: {}: {}", path.display(), err);
+                    std::process::exit(1);
+                });
+                let before = writer.len();
+                archive
+                    .for_each(|solution| {
+                        if seen.insert(puzzle.solution_key(&solution, &symmetries)) {
+                            writer
+                                .push(&Placement::from_solution(&solution))
+                                .expect("Failed to write archive");
                         }
+                    })
+                    .expect("Failed to read archive");
+                read += archive.len();
+                writeln!(
+                    out,
+                    "{}: {} solutions, {} new",
+                    path.display(),
+                    archive.len(),
+                    writer.len() - before
+                )
+                .expect("Failed to write output");
+            }
+            writeln!(
+                out,
+                "{} solutions written to {}, {} duplicates dropped",
+                writer.len(),
+                into.display(),
+                read - writer.len()
+            )
+            .expect("Failed to write output");
+            if symmetry {
+                writeln!(
+                    out,
+                    "{} rotations of the board considered",
+                    symmetries.len()
+                )
+                .expect("Failed to write output");
+            }
+            writer.finish().expect("Failed to write archive");
+        }
+        Some(Command::Html {
+            archive,
+            puzzle,
+            index,
+            limit,
+        }) => {
+            let mut puzzle = read_puzzle(&puzzle, &colors, &args);
+            puzzle.view = view(&args);
+            let mut archive = ArchiveReader::open(&archive).expect("Failed to open archive");
+            let solutions: Vec<Placement> = match index {
+                Some(index) => match archive.get(index).expect("Failed to read archive") {
+                    Some(solution) => vec![Placement::from_solution(&solution)],
+                    None => {
+                        eprintln!("error: archive has only {} solutions", archive.len());
+                        std::process::exit(1);
                     }
-                    if valid {
-                        placements.push(bits);
-                    }
+                },
+                None => (0..archive.len().min(limit))
+                    .filter_map(|n| archive.get(n).expect("Failed to read archive"))
+                    .map(|solution| Placement::from_solution(&solution))
+                    .collect(),
+            };
+            if solutions.is_empty() {
+                eprintln!("error: archive has no solutions");
+                std::process::exit(1);
+            }
+            SolutionFormat::Html
+                .write_provenance(&mut out, &archive_provenance(&mut archive, &puzzle))
+                .expect("Failed to write output");
+            puzzle
+                .write_html(&mut out, &solutions)
+                .expect("Failed to write output");
+        }
+        Some(Command::Adjacency {
+            archive,
+            puzzle,
+            format,
+        }) => {
+            let puzzle = read_puzzle(&puzzle, &colors, &args);
+            let mut archive = ArchiveReader::open(&archive).expect("Failed to open archive");
+            let mut adjacency = Adjacency::new(&puzzle);
+            archive
+                .for_each(|solution| adjacency.add(&puzzle, &Placement::from_solution(&solution)))
+                .expect("Failed to read archive");
+            adjacency
+                .write(&mut out, &puzzle, format)
+                .expect("Failed to write output");
+        }
+        Some(Command::Recolor {
+            archive,
+            puzzle: path,
+            count,
+            write,
+        }) => {
+            let mut puzzle = read_puzzle(&path, &colors, &args);
+            let mut archive = ArchiveReader::open(&archive).expect("Failed to open archive");
+            let mut adjacency = Adjacency::new(&puzzle);
+            archive
+                .for_each(|solution| adjacency.add(&puzzle, &Placement::from_solution(&solution)))
+                .expect("Failed to read archive");
+            let used: Vec<Color> = puzzle.pieces.iter().map(|piece| piece.color).collect();
+            let count = count
+                .unwrap_or_else(|| colors.iter().filter(|c| used.contains(c)).count().max(1))
+                .min(colors.len());
+            if count == 0 {
+                eprintln!("error: no colors to choose from");
+                std::process::exit(1);
+            }
+            let before = puzzle.color_clashes(&adjacency);
+            let after = puzzle.recolor(&adjacency, &colors[..count]);
+            if write {
+                let mut file =
+                    BufWriter::new(File::create(&path).expect("Failed to create puzzle file"));
+                puzzle
+                    .write(&mut file)
+                    .and_then(|_| file.flush())
+                    .expect("Failed to write puzzle");
+                writeln!(
+                    out,
+                    "{} colors: same-colored pieces touch {} times in {} solutions, {} before",
+                    count,
+                    after,
+                    adjacency.solutions(),
+                    before
+                )
+                .expect("Failed to write output");
+            } else {
+                write_puzzle(Ok(puzzle), &mut out);
+            }
+        }
+        Some(Command::Families {
+            archive,
+            puzzle,
+            shared,
+            show,
+        }) => {
+            let mut puzzle = read_puzzle(&puzzle, &colors, &args);
+            puzzle.view = view(&args);
+            let mut archive = ArchiveReader::open(&archive).expect("Failed to open archive");
+            let mut solutions = Vec::new();
+            archive
+                .for_each(|solution| solutions.push(Placement::from_solution(&solution)))
+                .expect("Failed to read archive");
+            let shared = shared.unwrap_or(puzzle.pieces.len() / 2);
+            let families = family::families(&solutions, shared);
+            for (n, family) in families.iter().enumerate() {
+                let core: Vec<&str> = family
+                    .core
+                    .iter()
+                    .map(|(id, _)| puzzle.pieces[*id].name.as_str())
+                    .collect();
+                writeln!(
+                    out,
+                    "family {}: {} solutions, representative {}, core: {}",
+                    n + 1,
+                    family.members.len(),
+                    family.members[0],
+                    core.join(" ")
+                )
+                .expect("Failed to write output");
+                if n < show {
+                    puzzle
+                        .show(&mut out, &solutions[family.members[0]])
+                        .expect("Failed to write output");
                 }
             }
+            writeln!(
+                out,
+                "{} solutions in {} families sharing {} placements",
+                solutions.len(),
+                families.len(),
+                shared
+            )
+            .expect("Failed to write output");
         }
-        placements
-    }
-
-    pub fn normalise(&self) -> Self {
-        // Find minimum coordinates
-        let min_x = self.blocks.iter().map(|block| block.x).min().unwrap();
-        let min_y = self.blocks.iter().map(|block| block.y).min().unwrap();
-        let min_z = self.blocks.iter().map(|block| block.z).min().unwrap();
-
-        Orintaion::new(
-            self.blocks
-                .iter()
-                .map(|block| Coord {
-                    x: block.x - min_x,
-                    y: block.y - min_y,
-                    z: block.z - min_z,
+        Some(Command::Metrics {
+            archive,
+            puzzle,
+            json,
+            all_visible,
+            hidden,
+        }) => {
+            let puzzle = read_puzzle(&puzzle, &colors, &args);
+            let mut archive = ArchiveReader::open(&archive).expect("Failed to open archive");
+            let mut all = Vec::new();
+            let mut n = 0;
+            archive
+                .for_each(|solution| {
+                    let metrics = puzzle.metrics(&Placement::from_solution(&solution));
+                    let any_hidden = metrics.hidden().next().is_some();
+                    if !(all_visible && any_hidden || hidden && !any_hidden) {
+                        all.push((n, metrics));
+                    }
+                    n += 1;
                 })
-                .collect(),
-        )
-    }
-
-    fn rotate(&self, dir: Direction) -> Self {
-        let mut ori = self.clone();
-        for block in ori.blocks.iter_mut() {
-            match dir {
-                Direction::Next => {
-                    let tmp = block.y;
-                    block.y = block.z;
-                    block.z = -tmp;
-                }
-                Direction::Clk => {
-                    let tmp = block.x;
-                    block.x = block.z;
-                    block.z = -tmp;
+                .expect("Failed to read archive");
+            match json {
+                true => metrics::write_json(&mut out, &puzzle, &all),
+                false => metrics::write_csv(&mut out, &puzzle, &all),
+            }
+            .expect("Failed to write output");
+        }
+        Some(Command::Compare {
+            puzzle,
+            mut strategies,
+            nodes,
+        }) => {
+            let puzzle = read_puzzle(&puzzle, &colors, &args);
+            if strategies.is_empty() {
+                strategies = Strategy::value_variants().to_vec();
+            }
+            writeln!(
+                out,
+                "{:<18} {:>10} {:>12} {:>10} {:>12}",
+                "strategy", "solutions", "nodes", "seconds", "first"
+            )
+            .expect("Failed to write output");
+            for result in puzzle.compare(&strategies, nodes, args.seed) {
+                let first = match result.first {
+                    Some(first) => format!("{:.4}", first.as_secs_f64()),
+                    None => "-".to_string(),
+                };
+                writeln!(
+                    out,
+                    "{:<18} {:>10} {:>12} {:>10.4} {:>12}",
+                    result.strategy.name(),
+                    result.solutions,
+                    result.nodes,
+                    result.elapsed.as_secs_f64(),
+                    first
+                )
+                .expect("Failed to write output");
+            }
+        }
+        Some(Command::Fmt {
+            puzzle: path,
+            sort,
+            write,
+        }) => {
+            let mut puzzle = read_puzzle(&path, &colors, &args);
+            puzzle.canonicalize(sort);
+            if write {
+                let mut file =
+                    BufWriter::new(File::create(&path).expect("Failed to create puzzle file"));
+                puzzle
+                    .write(&mut file)
+                    .and_then(|_| file.flush())
+                    .expect("Failed to write puzzle");
+            } else {
+                write_puzzle(Ok(puzzle), &mut out);
+            }
+        }
+        Some(Command::Export { format, puzzle }) => {
+            let puzzle = read_puzzle(&puzzle, &colors, &args);
+            match format {
+                ExportFormat::Cnf => Provenance::new(&puzzle)
+                    .write(&mut out, "c ")
+                    .and_then(|_| puzzle.write_cnf(&mut out)),
+            }
+            .expect("Failed to write output");
+        }
+        Some(Command::Subset {
+            puzzle,
+            require,
+            group,
+            propagate,
+        }) => {
+            let mut puzzle = read_puzzle(&puzzle, &colors, &args);
+            puzzle.view = view(&args);
+            let groups = puzzle.select(&require, &group).unwrap_or_else(|err| {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            });
+            let mut solver = Solver::new();
+            solver.groups = groups;
+            solver.propagate = propagate;
+            solver.collected = Some(Vec::new());
+            let remaining: Vec<usize> = (0..puzzle.pieces.len()).collect();
+            solver.fill(&puzzle, &mut Placement::new(), &remaining);
+            for (index, solution) in solver.collected.unwrap_or_default().iter().enumerate() {
+                puzzle
+                    .show(&mut out, solution)
+                    .and_then(|_| {
+                        writeln!(out, "{} {}", index, puzzle.used_pieces(solution).join(" "))
+                    })
+                    .expect("Failed to write solution");
+            }
+            writeln!(out, "{} solutions", solver.num_solutions).expect("Failed to write output");
+        }
+        Some(Command::Explain {
+            puzzle,
+            steps,
+            place,
+        }) => {
+            let mut puzzle = read_puzzle(&puzzle, &colors, &args);
+            puzzle.view = view(&args);
+            let placement = parse_placements(&puzzle, &place);
+            puzzle
+                .explain(&mut out, &placement, steps)
+                .expect("Failed to write output");
+        }
+        Some(Command::Play { puzzle, nodes }) => {
+            let mut puzzle = read_puzzle(&puzzle, &colors, &args);
+            puzzle.view = view(&args);
+            play(&puzzle, nodes, &mut out).expect("Failed to write output");
+        }
+        Some(Command::Spin {
+            puzzle,
+            piece,
+            delay,
+            once,
+        }) => {
+            let mut puzzle = read_puzzle(&puzzle, &colors, &args);
+            puzzle.view = view(&args);
+            let piece_id = puzzle
+                .unplaced_piece(&Placement::new(), &piece)
+                .unwrap_or_else(|err| {
+                    eprintln!("error: {}", err);
+                    std::process::exit(1);
+                });
+            let term = console::Term::stdout();
+            match args.output.is_none() && term.is_term() {
+                true => spin(&puzzle, piece_id, &term, delay, once),
+                // Written to a file or a pipe, each orientation is listed once.
+                false => (0..puzzle.pieces[piece_id].orintations.len())
+                    .try_for_each(|index| puzzle.write_orientation(&mut out, piece_id, index)),
+            }
+            .expect("Failed to write output");
+        }
+        #[cfg(feature = "viewer")]
+        Some(Command::Viewer {
+            puzzle: path,
+            archive,
+            index,
+        }) => {
+            let mut puzzle = read_puzzle(&path, &colors, &args);
+            puzzle.view = view(&args);
+            let puzzle = Arc::new(puzzle);
+            let source = match archive {
+                Some(archive) => {
+                    let mut archive =
+                        ArchiveReader::open(&archive).expect("Failed to open archive");
+                    match archive.get(index).expect("Failed to read archive") {
+                        Some(solution) => Source::Solution(Placement::from_solution(&solution)),
+                        None => {
+                            eprintln!("error: archive has only {} solutions", archive.len());
+                            std::process::exit(1);
+                        }
+                    }
                 }
-                Direction::CClk => {
-                    let tmp = block.z;
-                    block.z = block.x;
-                    block.x = -tmp;
+                None => {
+                    let (sender, receiver) = mpsc::sync_channel(1 << 12);
+                    let search = Arc::clone(&puzzle);
+                    thread::spawn(move || {
+                        let mut solver = Solver::new();
+                        solver.events = Some(sender);
+                        let remaining: Vec<usize> = (0..search.pieces.len()).collect();
+                        solver.fill(&search, &mut Placement::new(), &remaining);
+                    });
+                    Source::Live(receiver)
                 }
+            };
+            viewer::run(&puzzle, &path.display().to_string(), source);
+        }
+        Some(Command::Split {
+            puzzle,
+            half,
+            region,
+        }) => {
+            let puzzle = read_puzzle(&puzzle, &colors, &args);
+            let region = match (half, region) {
+                (Some(axis), _) => Ok(puzzle.half(axis)),
+                (None, region) => puzzle.region(&region.unwrap_or_default()),
+            }
+            .unwrap_or_else(|err| {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            });
+            let names = |ids: &[usize]| {
+                let names: Vec<&str> = ids
+                    .iter()
+                    .map(|id| puzzle.pieces[*id].name.as_str())
+                    .collect();
+                names.join(" ")
+            };
+            let splits = puzzle.splits(region);
+            for split in splits.iter() {
+                let (first, second) = split.solutions;
+                writeln!(
+                    out,
+                    "{} | {}  {} x {} = {}",
+                    names(&split.first),
+                    names(&split.second),
+                    first,
+                    second,
+                    first * second
+                )
+                .expect("Failed to write output");
             }
+            writeln!(out, "{} splits", splits.len()).expect("Failed to write output");
         }
-        ori
-    }
-
-    fn similar(&self, other: &Self) -> bool {
-        let mut count = 0;
-        for block in self.blocks.iter() {
-            for other_block in other.blocks.iter() {
-                if block == other_block {
-                    count += 1;
-                    break;
+        Some(Command::Analyze {
+            command:
+                Some(AnalyzeCommand::Corners {
+                    puzzle,
+                    cells,
+                    place,
+                }),
+            ..
+        }) => {
+            let mut puzzle = read_puzzle(&puzzle, &colors, &args);
+            puzzle.view = view(&args);
+            let placement = parse_placements(&puzzle, &place);
+            let cells = match cells {
+                Some(cells) => {
+                    let region = puzzle.region(&cells).unwrap_or_else(|err| {
+                        eprintln!("error: {}", err);
+                        std::process::exit(1);
+                    });
+                    (0..puzzle.grid.len()).filter(|i| region.get(*i)).collect()
+                }
+                None => puzzle.corners(),
+            };
+            if !placement.placed.is_empty() {
+                puzzle
+                    .show(&mut out, &placement)
+                    .expect("Failed to write output");
+            }
+            for cell in cells {
+                let cover = puzzle.cover(&placement, cell);
+                let coord = puzzle.grid.coord(cell);
+                let status = match placement.occupied.get(cell) {
+                    true => " (filled)",
+                    false => "",
+                };
+                writeln!(
+                    out,
+                    "{}{}{}{}: {} placements of {} pieces",
+                    coord.x,
+                    coord.y,
+                    coord.z,
+                    status,
+                    cover.placements(),
+                    cover.pieces.len()
+                )
+                .expect("Failed to write output");
+                for (id, count) in cover.pieces.iter() {
+                    let name = puzzle.colored_name(*id);
+                    writeln!(out, "  {} {}", name, count).expect("Failed to write output");
                 }
             }
         }
-        count == self.blocks.len()
-    }
-
-    pub fn all_orintations(&self) -> Vec<Orintaion> {
-        let mut orintations = Vec::new();
-        let mut ori = self.normalise();
-        orintations.push(ori.clone());
-        let mut clk = true;
-        for _dir in 0..6 {
-            for _rot in 0..3 {
-                ori = if clk {
-                    ori.rotate(Direction::Clk)
-                } else {
-                    ori.rotate(Direction::CClk)
+        Some(Command::Analyze {
+            command:
+                Some(AnalyzeCommand::Reach {
+                    puzzle,
+                    piece,
+                    place,
+                }),
+            ..
+        }) => {
+            let mut puzzle = read_puzzle(&puzzle, &colors, &args);
+            puzzle.view = view(&args);
+            let placement = parse_placements(&puzzle, &place);
+            let piece_id = puzzle
+                .unplaced_piece(&placement, &piece)
+                .unwrap_or_else(|err| {
+                    eprintln!("error: {}", err);
+                    std::process::exit(1);
+                });
+            let counts = puzzle.reach(&placement, piece_id);
+            let fitting = puzzle.pieces[piece_id]
+                .placements
+                .iter()
+                .filter(|bits| placement.is_valid(bits))
+                .count();
+            let reached = counts.iter().filter(|count| **count > 0).count();
+            writeln!(
+                out,
+                "{}: {} placements covering {} cells",
+                puzzle.colored_name(piece_id),
+                fitting,
+                reached
+            )
+            .and_then(|_| puzzle.write_counts(&mut out, &placement, &counts))
+            .expect("Failed to write output");
+        }
+        Some(Command::Analyze {
+            command:
+                Some(AnalyzeCommand::Keystone {
+                    puzzle,
+                    piece,
+                    place,
+                    format,
+                }),
+            ..
+        }) => {
+            let mut puzzle = read_puzzle(&puzzle, &colors, &args);
+            puzzle.view = view(&args);
+            let placement = parse_placements(&puzzle, &place);
+            let piece_id = puzzle
+                .unplaced_piece(&placement, &piece)
+                .unwrap_or_else(|err| {
+                    eprintln!("error: {}", err);
+                    std::process::exit(1);
+                });
+            let counts = puzzle.placement_counts(&placement, piece_id);
+            puzzle
+                .write_placement_counts(&mut out, &counts, format)
+                .expect("Failed to write output");
+        }
+        Some(Command::Analyze {
+            command: None,
+            solutions,
+            puzzle,
+            piece,
+            place,
+            all_visible,
+            hidden,
+        }) => {
+            let mut puzzle = read_puzzle(&puzzle.unwrap(), &colors, &args);
+            puzzle.view = view(&args);
+            let piece_id = piece.map(|piece| {
+                puzzle
+                    .unplaced_piece(&Placement::new(), &piece)
+                    .unwrap_or_else(|err| {
+                        eprintln!("error: {}", err);
+                        std::process::exit(1);
+                    })
+            });
+            let filter = SolutionFilter {
+                placed: parse_placements(&puzzle, &place),
+                all_visible,
+                hidden,
+            };
+            let mut report = SolutionReport::new(&puzzle, filter);
+            let mut archive =
+                ArchiveReader::open(&solutions.unwrap()).expect("Failed to open archive");
+            archive_provenance(&mut archive, &puzzle)
+                .write(&mut out, "")
+                .and_then(|_| writeln!(out))
+                .expect("Failed to write output");
+            archive
+                .for_each(|solution| report.add(&puzzle, &solution))
+                .expect("Failed to read archive");
+            report
+                .write(&mut out, &puzzle, piece_id)
+                .expect("Failed to write output");
+        }
+        Some(Command::Shape {
+            command:
+                ShapeCommand::Gen {
+                    preset,
+                    size,
+                    thickness,
+                    set,
+                },
+        }) => {
+            let shape = preset.shape(size, thickness).unwrap_or_else(|err| {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            });
+            match set {
+                Some(set) => write_puzzle(Ok(shape.puzzle(set)), &mut out),
+                None => shape
+                    .write_figure(&mut out)
+                    .expect("Failed to write output"),
+            }
+        }
+        Some(Command::Shape {
+            command:
+                ShapeCommand::Random {
+                    size,
+                    volume,
+                    set,
+                    attempts,
+                },
+        }) => {
+            let volume = volume.unwrap_or_else(|| {
+                let pieces = set.map(|set| set.puzzle().pieces).unwrap_or_default();
+                pieces.iter().map(|p| p.size).sum()
+            });
+            let mut rng = Rng::new(args.seed);
+            let mut reason = String::new();
+            let shape = (0..attempts).find_map(|_| {
+                let shape = Shape::random(size, volume, &mut rng).unwrap_or_else(|err| {
+                    eprintln!("error: {}", err);
+                    std::process::exit(1);
+                });
+                match set.and_then(|set| shape.infeasible(set)) {
+                    Some(why) => {
+                        reason = why;
+                        None
+                    }
+                    None => Some(shape),
                 }
-                .normalise();
-                if orintations.iter().all(|o| !o.similar(&ori)) {
-                    orintations.push(ori.clone());
+            });
+            match (shape, set) {
+                (Some(shape), Some(set)) => write_puzzle(Ok(shape.puzzle(set)), &mut out),
+                (Some(shape), None) => shape
+                    .write_figure(&mut out)
+                    .expect("Failed to write output"),
+                (None, _) => {
+                    eprintln!(
+                        "error: no fillable shape in {} attempts, last: {}",
+                        attempts, reason
+                    );
+                    std::process::exit(1);
                 }
             }
-            ori = ori.rotate(Direction::Next).normalise();
-            if orintations.iter().all(|o| !o.similar(&ori)) {
-                orintations.push(ori.clone());
+        }
+        Some(Command::Generate { set }) => write_puzzle(Ok(set.puzzle()), &mut out),
+        Some(Command::Verify { puzzle, model }) => {
+            let mut puzzle = read_puzzle(&puzzle, &colors, &args);
+            puzzle.view = view(&args);
+            let model = input::open(&model)
+                .and_then(io::read_to_string)
+                .expect("Failed to read model");
+            match puzzle.decode_cnf(&model) {
+                Ok(placement) => puzzle
+                    .show(&mut out, &placement)
+                    .and_then(|_| writeln!(out, "valid"))
+                    .expect("Failed to write output"),
+                Err(err) => {
+                    eprintln!("invalid: {}", err);
+                    std::process::exit(1);
+                }
             }
-            clk = !clk;
         }
-        // orintations.iter().map(|o| o.normalise_first()).collect()
-        orintations
+        Some(Command::Merge { a, b }) => {
+            let a = read_puzzle(&a, &colors, &args);
+            let b = read_puzzle(&b, &colors, &args);
+            write_puzzle(a.merge(&b), &mut out);
+        }
+        Some(Command::Subtract { a, b }) => {
+            let a = read_puzzle(&a, &colors, &args);
+            let b = read_puzzle(&b, &colors, &args);
+            write_puzzle(a.subtract(&b), &mut out);
+        }
+        Some(Command::Transform {
+            puzzle,
+            steps,
+            pieces,
+        }) => {
+            let puzzle = read_puzzle(&puzzle, &colors, &args);
+            write_puzzle(puzzle.transform(&steps, pieces.as_deref()), &mut out);
+        }
+        Some(Command::Remove { puzzle, pieces }) => {
+            let puzzle = read_puzzle(&puzzle, &colors, &args);
+            write_puzzle(puzzle.remove(&pieces), &mut out);
+        }
+        Some(Command::Extract { puzzle, pieces }) => {
+            let puzzle = read_puzzle(&puzzle, &colors, &args);
+            write_puzzle(puzzle.extract(&pieces), &mut out);
+        }
+        None => unreachable!("a subcommand is required"),
     }
-}
+    out.flush().expect("Failed to write output");
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-struct Bitset {
-    bits: u64,
+    if let Some(path) = &args.profile {
+        let mut file = File::create(path).expect("Failed to create profile file");
+        profile::export(&mut file).expect("Failed to write profile");
+    }
 }
 
-impl Bitset {
-    pub fn empty() -> Self {
-        Self { bits: 0 }
+/// Turns on escape sequence processing in Windows consoles, leaving colors
+/// off where the console can't do it. Elsewhere `colored` already leaves
+/// them off when stdout isn't a terminal.
+fn enable_terminal_colors() {
+    #[cfg(windows)]
+    if colored::control::set_virtual_terminal(true).is_err() {
+        colored::control::set_override(false);
     }
+}
 
-    pub fn full() -> Self {
-        Self { bits: !0 }
+fn view(args: &Args) -> View {
+    View {
+        palette: args.palette,
+        // Half blocks only make sense in color, fall back to letters.
+        compact: args.compact && colored::control::SHOULD_COLORIZE.should_colorize(),
+        labels: args.labels,
+        origin: args.origin,
+        // Not set when stdout isn't a terminal, files get one long row.
+        width: console::Term::stdout()
+            .size_checked()
+            .map(|(_, columns)| columns as usize),
     }
+}
 
-    pub fn and(&self, other: &Bitset) -> Bitset {
-        Bitset {
-            bits: self.bits & other.bits,
-        }
-    }
+const PLAY_HELP: &str = "\
+place NAME CELLS  place a piece on blocks or a hex mask, e.g. place A 000-100-110
+undo              take back the last move
+show              print the board
+check             find the first move after which the puzzle can't be finished
+quit              stop playing";
 
-    pub fn or(&self, other: &Bitset) -> Bitset {
-        Bitset {
-            bits: self.bits | other.bits,
+/// Draws each orientation of `piece_id` over the last, `delay`
+/// milliseconds apart, going round once with `once` or until interrupted.
+fn spin(
+    puzzle: &Puzzle,
+    piece_id: usize,
+    term: &console::Term,
+    delay: u64,
+    once: bool,
+) -> io::Result<()> {
+    let count = puzzle.pieces[piece_id].orintations.len();
+    let mut shown = 0;
+    for (n, index) in (0..count).cycle().enumerate() {
+        if once && n == count {
+            break;
         }
+        let mut frame = Vec::new();
+        puzzle.write_orientation(&mut frame, piece_id, index)?;
+        term.clear_last_lines(shown)?;
+        term.write_str(&String::from_utf8_lossy(&frame))?;
+        shown = frame.iter().filter(|b| **b == b'\n').count();
+        std::thread::sleep(std::time::Duration::from_millis(delay));
     }
+    Ok(())
+}
 
-    pub fn xor(&self, other: &Bitset) -> Bitset {
-        Bitset {
-            bits: self.bits ^ other.bits,
+/// Reads moves from stdin until it ends, reporting after each one whether
+/// the puzzle can still be finished.
+fn play(puzzle: &Puzzle, nodes: u64, out: &mut dyn Write) -> io::Result<()> {
+    let mut placement = Placement::new();
+    writeln!(out, "{}, type help for commands", puzzle.name)?;
+    for line in io::stdin().lines() {
+        let line = line?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            [] => continue,
+            ["place", name, cells] => match puzzle.parse_move(&placement, name, cells) {
+                Ok((id, bits)) => placement.place(id, bits),
+                Err(err) => {
+                    writeln!(out, "error: {}", err)?;
+                    continue;
+                }
+            },
+            ["undo"] => {
+                if placement.pop().is_none() {
+                    writeln!(out, "nothing to undo")?;
+                    continue;
+                }
+            }
+            ["show"] => {
+                puzzle.show(out, &placement)?;
+                continue;
+            }
+            ["check"] => {
+                match puzzle.first_bad_move(&placement, nodes) {
+                    Some(index) => {
+                        let (id, _) = placement.placed[index];
+                        let name = puzzle.colored_name(id);
+                        writeln!(
+                            out,
+                            "move {} ({}) made the puzzle unsolvable",
+                            index + 1,
+                            name
+                        )?
+                    }
+                    None => writeln!(out, "no bad move found")?,
+                }
+                continue;
+            }
+            ["help"] => {
+                writeln!(out, "{}", PLAY_HELP)?;
+                continue;
+            }
+            ["quit"] => break,
+            _ => {
+                writeln!(
+                    out,
+                    "unknown command {}, type help for commands",
+                    line.trim()
+                )?;
+                continue;
+            }
         }
+        if placement.occupied.and(&puzzle.target) == puzzle.target {
+            puzzle.show(out, &placement)?;
+            writeln!(out, "{}", "solved".green())?;
+            continue;
+        }
+        match puzzle.outlook(&placement, nodes) {
+            Outlook::Solvable => writeln!(out, "still solvable")?,
+            Outlook::Stuck => writeln!(out, "{}", "can no longer be solved".red())?,
+            Outlook::Unknown => writeln!(out, "unknown, no answer within {} nodes", nodes)?,
+        }
+        out.flush()?;
     }
-
-    pub fn not(&self) -> Bitset {
-        Bitset { bits: !self.bits }
-    }
-
-    pub fn set(&mut self, index: usize) {
-        self.bits |= 1 << index;
-    }
-
-    pub fn get(&self, index: usize) -> bool {
-        self.bits & (1 << index) != 0
-    }
+    Ok(())
 }
 
-impl From<u64> for Bitset {
-    fn from(bits: u64) -> Self {
-        Self { bits }
-    }
+/// What to do at a stop of an interactive search.
+enum StepKey {
+    Next,
+    Previous,
+    Quit,
 }
 
-const SIZE: usize = 4;
-
-struct Placement {
-    occupied: Bitset,
-    placed: Vec<(usize, Bitset)>,
+/// Shows the boards an interactive search stops at, each until a key is
+/// pressed. Stops already shown can be gone back to; going forward from
+/// the last one lets the search go on.
+struct Stepper {
+    puzzle: Arc<Puzzle>,
+    /// Stop at placements as well as at solutions.
+    step: bool,
+    term: console::Term,
+    /// Every board stopped at so far, with what happened.
+    stops: Vec<(Placement, String)>,
 }
 
-impl Placement {
-    pub fn new() -> Self {
+impl Stepper {
+    fn new(puzzle: Arc<Puzzle>, step: bool) -> Self {
         Self {
-            occupied: Bitset::empty(),
-            placed: Vec::new(),
+            puzzle,
+            step,
+            term: console::Term::stdout(),
+            stops: Vec::new(),
         }
     }
 
-    pub fn pop(&mut self) -> Option<(usize, Bitset)> {
-        match self.placed.pop() {
-            Some((id, bits)) => {
-                self.occupied = self.occupied.xor(&bits);
-                Some((id, bits))
+    /// Whether the search should go on after `event`.
+    fn pause(&mut self, placement: &Placement, event: &Event) -> bool {
+        let title = match event {
+            Event::SolutionFound { index } => format!("solution {}", index + 1).green().to_string(),
+            Event::PiecePlaced {
+                piece_id, depth, ..
+            } if self.step => format!(
+                "placed {}, {} of {} pieces",
+                self.puzzle.colored_name(*piece_id),
+                depth + 1,
+                self.puzzle.pieces.len()
+            ),
+            _ => return true,
+        };
+        self.stops.push((placement.clone(), title));
+        let mut shown = self.stops.len() - 1;
+        loop {
+            self.draw(shown).expect("Failed to write output");
+            match self.key().expect("Failed to read key") {
+                StepKey::Next if shown + 1 < self.stops.len() => shown += 1,
+                StepKey::Next => return true,
+                StepKey::Previous => shown = shown.saturating_sub(1),
+                StepKey::Quit => return false,
             }
-            None => None,
         }
     }
 
-    pub fn is_valid(&self, bits: Bitset) -> bool {
-        bits.and(&self.occupied).bits == 0
+    fn draw(&self, shown: usize) -> io::Result<()> {
+        let (placement, title) = &self.stops[shown];
+        let mut frame = Vec::new();
+        writeln!(frame, "{} ({}/{})", title, shown + 1, self.stops.len())?;
+        self.puzzle.show(&mut frame, placement)?;
+        write!(frame, "n next, p previous, q quit")?;
+        if self.term.is_term() {
+            self.term.clear_screen()?;
+        } else {
+            writeln!(frame)?;
+        }
+        self.term.write_str(&String::from_utf8_lossy(&frame))?;
+        self.term.flush()
     }
 
-    pub fn place(&mut self, id: usize, bits: Bitset) {
-        self.occupied = self.occupied.or(&bits);
-        self.placed.push((id, bits));
+    /// The next key pressed, or the first letter of the next line read when
+    /// stdin isn't a terminal, quitting once it ends.
+    fn key(&self) -> io::Result<StepKey> {
+        if !self.term.is_term() {
+            let mut line = String::new();
+            if io::stdin().read_line(&mut line)? == 0 {
+                return Ok(StepKey::Quit);
+            }
+            return Ok(match line.trim().chars().next() {
+                Some('p') => StepKey::Previous,
+                Some('q') => StepKey::Quit,
+                _ => StepKey::Next,
+            });
+        }
+        loop {
+            match self.term.read_key()? {
+                console::Key::Char('n' | ' ')
+                | console::Key::Enter
+                | console::Key::ArrowRight
+                | console::Key::ArrowDown => return Ok(StepKey::Next),
+                console::Key::Char('p')
+                | console::Key::Backspace
+                | console::Key::ArrowLeft
+                | console::Key::ArrowUp => return Ok(StepKey::Previous),
+                console::Key::Char('q') | console::Key::Escape => return Ok(StepKey::Quit),
+                _ => {}
+            }
+        }
     }
 }
 
-struct Puzzle {
-    name: String,
-    dim: Coord,
-    pieces: Vec<Piece>,
+fn read_puzzle(path: &Path, colors: &[Color], args: &Args) -> Puzzle {
+    loaded(path, Puzzle::read(path, colors), args)
 }
 
-impl Puzzle {
-    fn read(filepath: &Path) -> io::Result<Self> {
-        let file = File::open(filepath)?;
-        let reader = BufReader::new(file);
-        let mut pieces = Vec::new();
-        let mut lines = reader.lines();
-        let top = lines.next().unwrap()?;
-        let top: Vec<&str> = top.split(",").collect();
-        let name = top[0];
-        let dim = top[1];
-        // let dim = top[1].parse::<usize>().unwrap();
-        // println!("{} {}", name, dim);
-        for (piece_id, line) in lines.enumerate() {
-            let line = line?;
-            let line: Vec<&str> = line.split(",").collect();
-            pieces.push(Piece::new(
-                piece_id as usize,
-                line[0].to_string(),
-                match line[1] {
-                    "red" => Color::Red,
-                    "yellow" => Color::Yellow,
-                    "blue" => Color::Blue,
-                    "white" => Color::White,
-                    _ => panic!("Invalid color"),
-                },
-                Orintaion::new(
-                    line[2]
-                        .split('-')
-                        .map(|block_str| {
-                            let coords: Vec<i32> = block_str
-                                .chars()
-                                .filter_map(|c| c.to_digit(10))
-                                .map(|num| num as i32)
-                                .collect();
-
-                            Coord {
-                                x: coords[0],
-                                y: coords[1],
-                                z: coords[2],
-                            }
-                        })
-                        .collect(),
-                ),
-            ));
-        }
-        Ok(Puzzle {
-            name: name.to_string(),
-            dim: Coord::new(4, 4, 4),
-            pieces,
-        })
-    }
-
-    pub fn show(&self, placement: &Placement) {
-        for y in (0..self.dim.y).rev() {
-            for z in 0..self.dim.z {
-                for x in 0..self.dim.x {
-                    let index = z * self.dim.y * self.dim.x + y * self.dim.x + x;
-                    if placement.occupied.get(index as usize) {
-                        for (id, bits) in placement.placed.iter() {
-                            if bits.get(index as usize) {
-                                print!("{} ", self.pieces[*id].colored_id());
-                                break;
-                            }
-                        }
-                    } else {
-                        print!(". ");
-                    }
-                }
-                print!("  ");
-            }
-            println!();
-        }
+/// The catalog read from `path`, its pieces restricted as `args` ask, or
+/// exits showing where the file is wrong.
+fn read_catalog(path: &Path, colors: &[Color], args: &Args) -> Catalog {
+    let mut catalog = Catalog::read(path, colors).unwrap_or_else(|err| {
+        eprintln!("error: {}", err.diagnostic(path));
+        std::process::exit(1);
+    });
+    if args.translate_only {
+        catalog.pieces.translate_only();
     }
+    catalog
+}
 
-    pub fn show_bit(&self, bits: &Bitset) {
-        for y in (0..self.dim.y).rev() {
-            for z in 0..self.dim.z {
-                for x in 0..self.dim.x {
-                    let index = z * self.dim.y * self.dim.x + y * self.dim.x + x;
-                    if bits.get(index as usize) {
-                        print!("X ");
-                    } else {
-                        print!(". ");
-                    }
-                }
-                print!("  ");
-            }
-            println!();
-        }
-        println!();
+/// The puzzle read from `path`, turned and restricted as `args` ask, or
+/// exits showing where the file is wrong.
+fn loaded(path: &Path, read: Result<Puzzle, PuzzleError>, args: &Args) -> Puzzle {
+    let puzzle = read.unwrap_or_else(|err| {
+        eprintln!("error: {}", err.diagnostic(path));
+        std::process::exit(1);
+    });
+    let mut puzzle = match args.axes {
+        Some(axes) => puzzle.with_axes(axes).unwrap_or_else(|err| {
+            eprintln!("error: {}", err);
+            std::process::exit(1);
+        }),
+        None => puzzle,
+    };
+    if args.translate_only {
+        puzzle.translate_only();
     }
+    puzzle
 }
 
-struct Solver {
-    num_solutions: usize,
+/// Exits listing what keeps `puzzle` from having solutions, if anything
+/// does, rather than searching for none.
+fn check_puzzle(puzzle: &Puzzle) {
+    let problems = puzzle.validate();
+    for problem in problems.iter() {
+        eprintln!("error: {}", problem);
+    }
+    if !problems.is_empty() {
+        std::process::exit(1);
+    }
 }
 
-impl Solver {
-    fn still_possible(&self, puzzle: &Puzzle, occ: &Bitset, remaining: &Vec<usize>) -> bool {
-        for piece_id in remaining.iter() {
-            let mut possible = false;
-            let piece = &puzzle.pieces[*piece_id];
-            for bits in piece.placements.iter() {
-                if occ.and(bits).bits == 0 {
-                    possible = true;
-                    break;
-                }
-            }
-            if !possible {
-                return false;
+/// Places pieces given as `NAME=CELLS`, in order, exiting on a bad one.
+fn parse_placements(puzzle: &Puzzle, places: &[String]) -> Placement {
+    let mut placement = Placement::new();
+    for place in places.iter() {
+        let parsed = place
+            .split_once('=')
+            .ok_or_else(|| format!("placement {} is not NAME=CELLS", place))
+            .and_then(|(name, cells)| puzzle.parse_move(&placement, name, cells));
+        match parsed {
+            Ok((id, bits)) => placement.place(id, bits),
+            Err(err) => {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
             }
         }
-        true
     }
+    placement
+}
 
-    fn solve(&mut self, puzzle: &Puzzle, placement: &mut Placement, remaining: &Vec<usize>) {
-        if remaining.is_empty() {
-            puzzle.show(placement);
-            println!("{}", self.num_solutions);
-            self.num_solutions += 1;
-            return;
-        }
+fn write_puzzle(puzzle: Result<Puzzle, String>, out: &mut dyn Write) {
+    let puzzle = puzzle.unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    });
+    puzzle.write(out).expect("Failed to write puzzle");
+}
 
-        for piece_id in remaining.iter() {
-            let piece = &puzzle.pieces[*piece_id];
-            let mut new_remaining = remaining.clone();
-            new_remaining.retain(|&id| id != *piece_id);
-            for bits in piece.placements.iter() {
-                let occ = bits.or(&placement.occupied);
-                if placement.is_valid(*bits) && self.still_possible(puzzle, &occ, &new_remaining) {
-                    placement.place(piece.piece_id, *bits);
-                    self.solve(puzzle, placement, &new_remaining);
-                    placement.pop();
-                }
-            }
+/// Runs the search, printing solutions to `out` and handing it back after.
+fn solve(
+    args: &Args,
+    search: &SearchArgs,
+    solving: &SolveArgs,
+    colors: &[Color],
+    threads: usize,
+    out: Box<dyn Write + Send>,
+) -> Box<dyn Write + Send> {
+    let path = &search.puzzle;
+    let mut warm = search
+        .cache
+        .as_ref()
+        .map(|dir| WarmStart::load(dir, 1 << 22).expect("Failed to read cache"));
+    let read = match warm.as_ref() {
+        Some(warm) => {
+            Puzzle::read_with(path, colors, |piece_id, name, color, ori, grid, region| {
+                warm.piece(piece_id, name, color, ori, grid, region)
+            })
         }
+        None => Puzzle::read(path, colors),
+    };
+    let mut puzzle = loaded(path, read, args);
+    check_puzzle(&puzzle);
+    if let Some(warm) = warm.as_mut() {
+        warm.attach(&puzzle).expect("Failed to read cache");
+        warm.save().expect("Failed to write cache");
+        let learned = warm.order(&mut puzzle, search.limit);
+        println!(
+            "warm start: {} cached pieces, {} dead states, {} placements ordered by past runs",
+            warm.cached_pieces(),
+            warm.dead_states(),
+            learned
+        );
     }
-
-    fn corner_solve(
-        &mut self,
-        puzzle: &Puzzle,
-        placement: &mut Placement,
-        corners: &Vec<Bitset>,
-        remaining: &Vec<usize>,
-    ) {
-        if corners.is_empty() {
-            // println!("{} {}", corners.len(), remaining.len());
-            self.solve(puzzle, placement, remaining);
-            return;
-        }
-
-        let mut new_corners = corners.clone();
-        let corner = new_corners.pop().unwrap();
-        for piece_id in remaining.iter() {
-            let piece = &puzzle.pieces[*piece_id];
-            let mut new_remaining = remaining.clone();
-            new_remaining.retain(|&id| id != *piece_id);
-            for bits in piece.placements.iter() {
-                let occ = bits.or(&placement.occupied);
-                if placement.is_valid(*bits)
-                    && bits.and(&corner).bits != 0
-                    && self.still_possible(puzzle, &occ, &new_remaining)
-                {
-                    placement.place(piece.piece_id, *bits);
-                    self.corner_solve(puzzle, placement, &new_corners, &new_remaining);
-                    placement.pop();
-                }
-            }
-        }
+    puzzle.view = view(args);
+    let mut budget = Budget::new(search.memory_limit);
+    if !budget.reserve("placement tables", puzzle.table_bytes()) {
+        eprintln!(
+            "error: placement tables need {} but the memory limit is {}",
+            memory::format_size(puzzle.table_bytes()),
+            memory::format_size(search.memory_limit.unwrap_or(0)),
+        );
+        std::process::exit(1);
+    }
+    if warm
+        .as_ref()
+        .is_some_and(|warm| !budget.reserve("warm start", warm.bytes()))
+    {
+        println!("warm start: no room within the memory limit, running without it");
+        warm = None;
     }
-}
 
-fn main() {
-    let args = Args::parse();
-    let puzzle = Puzzle::read(&args.puzzle).expect("Failed to read puzzle file");
+    println!(
+        "{} ({}x{}x{}) threads: {}",
+        puzzle.name, puzzle.grid.x, puzzle.grid.y, puzzle.grid.z, threads
+    );
+
+    let mut placement = parse_placements(&puzzle, &search.place);
+    let mut remaining: Vec<usize> = (0..puzzle.pieces.len())
+        .filter(|id| placement.placed.iter().all(|(placed, _)| placed != id))
+        .collect();
 
+    let preprocessed = {
+        let _span = profile::span("preprocess");
+        puzzle.preprocess(&mut placement, &mut remaining)
+    };
     println!(
-        "{} ({}x{}x{})",
-        puzzle.name, puzzle.dim.x, puzzle.dim.y, puzzle.dim.z
+        "preprocess: {} placements removed, {} pieces forced",
+        preprocessed.removed,
+        preprocessed.forced.len()
     );
-    for piece in puzzle.pieces.iter() {
+    for cell in preprocessed.uncoverable.iter() {
+        let cell = puzzle.grid.coord(*cell);
+        println!("cell {}{}{} cannot be covered", cell.x, cell.y, cell.z);
+    }
+    for piece_id in preprocessed.stranded.iter() {
         println!(
-            "{} {} {} {} {}",
-            piece.char_id(),
-            piece.size,
-            piece.colored_name(),
-            piece.orintations.len(),
-            piece.placements.len()
+            "piece {} has no placements left",
+            puzzle.colored_name(*piece_id)
         );
     }
+    puzzle
+        .piece_table(&mut io::stdout(), solving.piece_format, &placement)
+        .expect("Failed to write piece table");
 
-    let mut placement = Placement::new();
-    placement.place(1, Bitset::from(0x0000000000000272));
-    // placement.place(1, Bitset::from(0x0000000002720000));
-
-    let mut corners = vec![
-        Bitset::from(0x0000000000000001),
-        Bitset::from(0x0000000000000008),
-        Bitset::from(0x0000000000001000),
-        Bitset::from(0x0000000000008000),
-        Bitset::from(0x0001000000000000),
-        Bitset::from(0x0008000000000000),
-        Bitset::from(0x1000000000000000),
-        Bitset::from(0x8000000000000000),
-    ];
+    let mut corners: Vec<Bitset> = puzzle
+        .corners()
+        .iter()
+        .map(|corner| Bitset::cell(*corner))
+        .collect();
+    corners.retain(|corner| corner.is_disjoint(&placement.occupied));
 
-    let mut remaining = vec![0, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+    let puzzle = Arc::new(puzzle);
+    let mut solver = Solver::new();
+    if let Some(depth) = solving.trace {
+        solver.trace = Some(Trace::new(
+            depth,
+            match &solving.trace_file {
+                Some(path) => Box::new(File::create(path).expect("Failed to create trace file")),
+                None => Box::new(io::stderr()),
+            },
+        ));
+    }
 
-    let mut solver = Solver { num_solutions: 0 };
+    if solving.histogram.is_some() {
+        solver.histogram = Some(Histogram::new());
+    }
+    solver.propagate = search.propagate;
+    solver.open_face = search.open_face;
+    if solving.timeline.is_some() {
+        solver.timeline = Some(Timeline::new());
+    }
+    solver.warm = warm;
+    if search.nogoods {
+        solver.nogoods = Some(Nogoods::new(&puzzle, 1 << 22));
+    }
+    solver.budget = budget;
+    solver.near_misses = solving.near_misses.map(NearMisses::new);
+    solver.deepest = placement.clone();
+    solver.output = Some(out);
+    solver.count_only = solving.count_only;
+    solver.solution_limit = search.limit;
+    if search.unique {
+        let reflections = search.reflections.then(|| {
+            puzzle.reflections().unwrap_or_else(|err| {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            })
+        });
+        solver.unique = Some(Unique::new(&puzzle, reflections));
+    }
+    if solving.interactive {
+        let mut stepper = Stepper::new(Arc::clone(&puzzle), solving.step);
+        solver.pause = Some(Box::new(move |placement, event| {
+            stepper.pause(placement, event)
+        }));
+    }
+    let spinner = solving.progress.then(|| progress_spinner(&mut solver));
+    let provenance = search_provenance(args, search, solving, &puzzle, threads);
+    if let Some(path) = &solving.archive {
+        let archive = ArchiveWriter::create_with(path, 4096, &provenance);
+        solver.archive = Some(archive.expect("Failed to create archive"));
+    }
+    if let Some(dir) = &solving.output_dir {
+        if solving.output_format == SolutionFormat::Text {
+            colored::control::set_override(false);
+        }
+        let mut dir = OutputDir::create(dir, solving.output_format, solving.file_names)
+            .expect("Failed to create output directory");
+        dir.provenance = Some(provenance.clone());
+        solver.output_dir = Some(dir);
+    }
+    // Solutions are shown as the search stops at them rather than printed.
+    if solving.interactive && solver.archive.is_none() && solver.output_dir.is_none() {
+        solver.count_only = true;
+    }
+    if let Some(path) = &solving.solutions_file {
+        let file = SolutionsFile::open(path, solving.dedup);
+        solver.solutions_file = Some(file.expect("Failed to open solutions file"));
+    }
+    if let Some(path) = &solving.record {
+        let recorder = Recorder::create(path, &puzzle, &placement);
+        solver.recorder = Some(recorder.expect("Failed to create recording"));
+    }
+    if solving.dot.is_some() {
+        solver.tree = Some(SearchTree::new(
+            puzzle.grid,
+            solving.dot_depth,
+            solving.dot_nodes,
+        ));
+    }
 
-    solver.corner_solve(&puzzle, &mut placement, &mut corners, &mut remaining);
+    let started = Instant::now();
+    {
+        let _span = profile::span("search");
+        if let Some(path) = &solving.replay {
+            placement = Placement::new();
+            if let Err(err) = solver.replay(&puzzle, &mut placement, path) {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            }
+        } else if let Some(width) = solving.beam {
+            let mut best = placement.clone();
+            if !solver.beam_solve(&puzzle, &mut best, &remaining, width) {
+                report_best(&puzzle, &best);
+            }
+        } else if let Some(steps) = solving.anneal {
+            let mut best = placement.clone();
+            let schedule = Anneal::new(steps, args.seed);
+            if !solver.anneal_solve(&puzzle, &mut best, &remaining, &schedule) {
+                report_best(&puzzle, &best);
+            }
+        } else if let Some(budget) = solving.restart_budget {
+            let restarts = Restarts {
+                seed: args.seed,
+                budget,
+                luby: solving.luby,
+                max_runs: solving.max_restarts,
+            };
+            match solver.restart_solve(&puzzle, &mut placement.clone(), &remaining, &restarts) {
+                Finish::Solved(run) => println!("solved in run {}", run + 1),
+                Finish::Unsolvable => println!("search completed, no solution exists"),
+                Finish::OutOfRuns => println!("no solution in {} runs", solving.max_restarts),
+            }
+        } else if search.algorithm == Algorithm::Dlx {
+            solver.solve_dlx(&puzzle, &mut placement, &remaining);
+        } else if threads > 1
+            && solver.trace.is_none()
+            && solver.histogram.is_none()
+            && solver.warm.is_none()
+            && solver.timeline.is_none()
+            && solver.nogoods.is_none()
+            && solver.recorder.is_none()
+            && solver.tree.is_none()
+            && solver.near_misses.is_none()
+            && solver.pause.is_none()
+            && solver.solution_limit.is_none()
+        {
+            solver.par_corner_solve(&puzzle, &mut placement, &corners, &remaining);
+        } else {
+            solver.corner_solve(&puzzle, &mut placement, &corners, &remaining);
+        }
+    }
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+    if solving.count_only {
+        writeln!(
+            solver.output.as_mut().unwrap(),
+            "{} solutions",
+            solver.num_solutions
+        )
+        .expect("Failed to write output");
+    }
+    if solving.verbose {
+        println!(
+            "{} placements tried in {:.2?}",
+            solver.nodes,
+            started.elapsed()
+        );
+    }
+    if solver.stopped {
+        println!("stopped after {} solutions", solver.num_solutions);
+    }
+    if let Some(unique) = &solver.unique {
+        println!(
+            "{} solutions found, {} unique under {} symmetries",
+            unique.total,
+            unique.len(),
+            unique.symmetries()
+        );
+    }
+    if let Some(archive) = solver.archive.take() {
+        println!("{} solutions archived", archive.len());
+        archive.finish().expect("Failed to write archive");
+    }
+    if let (Some(dir), Some(path)) = (&solver.output_dir, &solving.output_dir) {
+        println!("{} solution files written to {}", dir.len(), path.display());
+    }
+    if let (Some(file), Some(path)) = (solver.solutions_file.take(), &solving.solutions_file) {
+        println!(
+            "{} solutions added to {}, which had {}",
+            file.written,
+            path.display(),
+            file.known
+        );
+        file.finish().expect("Failed to write solutions file");
+    }
+    if let Some(recorder) = solver.recorder.take() {
+        recorder.finish().expect("Failed to write recording");
+    }
+    if let (Some(tree), Some(path)) = (solver.tree.as_mut(), &solving.dot) {
+        let mut file = BufWriter::new(File::create(path).expect("Failed to create DOT file"));
+        tree.write(&mut file)
+            .and_then(|_| file.flush())
+            .expect("Failed to write DOT file");
+    }
+    if let Some(warm) = solver.warm.take() {
+        warm.save().expect("Failed to write cache");
+    }
+    if let Some(nogoods) = &solver.nogoods {
+        println!(
+            "nogoods: {} learned, {} hits",
+            nogoods.len(),
+            nogoods.hits()
+        );
+    }
+    if let (Some(timeline), Some(path)) = (&solver.timeline, &solving.timeline) {
+        let mut file = BufWriter::new(File::create(path).expect("Failed to create timeline file"));
+        match path.extension().is_some_and(|ext| ext == "json") {
+            true => timeline.write_json(&mut file),
+            false => timeline.write_csv(&mut file),
+        }
+        .and_then(|_| file.flush())
+        .expect("Failed to write timeline");
+    }
+    if let (Some(histogram), Some(format)) = (&solver.histogram, solving.histogram) {
+        histogram
+            .write(&mut io::stdout(), format)
+            .expect("Failed to write histogram");
+    }
+    if let Some(near_misses) = &solver.near_misses {
+        near_misses
+            .write(&mut io::stdout(), &puzzle, solving.near_misses_shown)
+            .expect("Failed to write output");
+    }
+    if solver.num_solutions == 0 && solving.beam.is_none() && solving.anneal.is_none() {
+        report_deepest(&puzzle, &solver.deepest);
+    }
 
-    // remaining.pop()
+    for what in solver.budget.refused() {
+        println!("memory limit reached: {} stopped growing", what);
+    }
+    if search.memory {
+        print!("{}", solver.budget);
+    }
+    solver.output.take().unwrap()
+}
 
-    for piece in puzzle.pieces.iter() {
-        let mut count = 0;
-        if piece.piece_id == 1 {
-            continue;
+/// A spinner on stderr following `solver`'s progress, with the placements
+/// and solutions of every thread added up.
+fn progress_spinner(solver: &mut Solver) -> ProgressBar {
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_style(
+        ProgressStyle::with_template("{spinner} {elapsed} {msg}").expect("Invalid progress style"),
+    );
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    let nodes = AtomicU64::new(0);
+    let solutions = AtomicUsize::new(0);
+    let shown = spinner.clone();
+    solver.progress = Some(Arc::new(move |placed, found| {
+        let solutions = solutions.fetch_add(found, Ordering::Relaxed) + found;
+        if placed > 0 {
+            let nodes = nodes.fetch_add(placed, Ordering::Relaxed) + placed;
+            shown.set_message(format!("{} placements, {} solutions", nodes, solutions));
         }
+    }));
+    spinner
+}
 
-        for bits in piece.placements.iter() {
-            if placement.is_valid(*bits) && bits.and(&Bitset::from(0x0000000000000001)).bits != 0 {
-                // placement.place(piece.piece_id, *bits);
-                count += 1;
-                // break;
-            }
-        }
-        println!("{} {}", piece.colored_id(), count);
+/// What `archive` recorded about how it was made, or just the puzzle and
+/// the time for archives from before provenance was kept.
+fn archive_provenance(archive: &mut ArchiveReader, puzzle: &Puzzle) -> Provenance {
+    archive
+        .provenance()
+        .expect("Failed to read archive")
+        .unwrap_or_else(|| Provenance::new(puzzle))
+}
+
+/// The settings of the search `solve` runs, for the files it writes.
+fn search_provenance(
+    args: &Args,
+    search: &SearchArgs,
+    solving: &SolveArgs,
+    puzzle: &Puzzle,
+    threads: usize,
+) -> Provenance {
+    let mut provenance = Provenance::new(puzzle);
+    let (method, randomized) = if let Some(path) = &solving.replay {
+        (format!("replay of {}", path.display()), false)
+    } else if let Some(width) = solving.beam {
+        (format!("beam of width {}", width), false)
+    } else if let Some(steps) = solving.anneal {
+        (format!("annealing for {} steps", steps), true)
+    } else if let Some(budget) = solving.restart_budget {
+        let growth = if solving.luby { ", luby" } else { "" };
+        (format!("restarts every {} nodes{}", budget, growth), true)
+    } else if search.algorithm == Algorithm::Dlx {
+        ("dancing links".to_string(), false)
+    } else {
+        ("backtracking".to_string(), false)
+    };
+    provenance.set("search", method);
+    provenance.set("threads", threads);
+    if let Some(limit) = search.limit {
+        provenance.set("limit", limit);
+    }
+    let mut heuristics: Vec<String> = [
+        (search.propagate, "propagate"),
+        (search.nogoods, "nogoods"),
+        (search.cache.is_some(), "cache"),
+        (args.translate_only, "translate-only"),
+    ]
+    .into_iter()
+    .filter(|(on, _)| *on)
+    .map(|(_, name)| name.to_string())
+    .collect();
+    if let Some(face) = search.open_face {
+        heuristics.push(format!("open-face {:?}", face).to_lowercase());
+    }
+    if !heuristics.is_empty() {
+        provenance.set("heuristics", heuristics.join(", "));
+    }
+    if randomized {
+        provenance.set("seed", args.seed);
+    }
+    if let Some(limit) = search.memory_limit {
+        provenance.set("memory limit", memory::format_size(limit));
     }
+    if solving.restart_budget.is_some() {
+        provenance.set("max restarts", solving.max_restarts);
+    }
+    if !search.place.is_empty() {
+        provenance.set("placed", search.place.join(" "));
+    }
+    if let Some(axes) = args.axes {
+        provenance.set("axes", axes);
+    }
+    provenance
+}
+
+/// Shows how far a search without solutions got and which pieces it never
+/// managed to place on top of that.
+fn report_deepest(puzzle: &Puzzle, deepest: &Placement) {
+    println!(
+        "no solutions, deepest partial: {}/{} pieces placed, {}/{} cells filled",
+        deepest.placed.len(),
+        puzzle.pieces.len(),
+        deepest.occupied.len(),
+        puzzle.target.len()
+    );
+    puzzle
+        .show(&mut io::stdout(), deepest)
+        .expect("Failed to write output");
+    let unplaced: Vec<String> = puzzle
+        .pieces
+        .iter()
+        .filter(|piece| deepest.placed.iter().all(|(id, _)| *id != piece.piece_id))
+        .map(|piece| puzzle.colored_name(piece.piece_id))
+        .collect();
+    println!("never placed: {}", unplaced.join(" "));
+}
 
-    puzzle.show(&placement);
-    puzzle.show_bit(&placement.occupied);
+/// Shows the packing an incomplete solver ended with.
+fn report_best(puzzle: &Puzzle, best: &Placement) {
+    println!(
+        "best packing: {}/{} pieces placed, {}/{} cells filled",
+        best.placed.len(),
+        puzzle.pieces.len(),
+        best.occupied.len(),
+        puzzle.target.len()
+    );
+    puzzle
+        .show(&mut io::stdout(), best)
+        .expect("Failed to write output");
 }
@@ -0,0 +1,147 @@
+use crate::{Bitset, Coord, Placement, Puzzle};
+use std::{fmt, mem::size_of};
+
+/// Bookkeeping of the memory held by the solver's tables. Anything that can
+/// live without its table (caches, stores that can stream to disk instead)
+/// should ask `reserve` first and fall back when it is refused.
+pub struct Budget {
+    limit: Option<usize>,
+    used: Vec<(&'static str, usize)>,
+    peak: usize,
+    refused: Vec<&'static str>,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl Budget {
+    pub fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            used: Vec::new(),
+            peak: 0,
+            refused: Vec::new(),
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.used.iter().map(|(_, bytes)| bytes).sum()
+    }
+
+    /// Records `bytes` under `what`, unless that would exceed the limit.
+    pub fn reserve(&mut self, what: &'static str, bytes: usize) -> bool {
+        if self.limit.is_some_and(|limit| self.total() + bytes > limit) {
+            if !self.refused.contains(&what) {
+                self.refused.push(what);
+            }
+            return false;
+        }
+        match self.used.iter_mut().find(|(name, _)| *name == what) {
+            Some((_, used)) => *used += bytes,
+            None => self.used.push((what, bytes)),
+        }
+        self.peak = self.peak.max(self.total());
+        true
+    }
+
+    /// Whether there is a limit to keep within.
+    pub fn is_limited(&self) -> bool {
+        self.limit.is_some()
+    }
+
+    /// What was refused room at least once, in the order it first was.
+    pub fn refused(&self) -> &[&'static str] {
+        &self.refused
+    }
+}
+
+impl fmt::Display for Budget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (what, bytes) in self.used.iter() {
+            writeln!(f, "{:<20} {:>10}", what, format_size(*bytes))?;
+        }
+        writeln!(f, "{:<20} {:>10}", "peak tracked", format_size(self.peak))?;
+        if let Some(rss) = peak_rss() {
+            writeln!(f, "{:<20} {:>10}", "peak resident", format_size(rss))?;
+        }
+        if let Some(limit) = self.limit {
+            writeln!(f, "{:<20} {:>10}", "limit", format_size(limit))?;
+        }
+        Ok(())
+    }
+}
+
+impl Puzzle {
+    /// Bytes held by the precomputed orientations and placements, and by
+    /// the cell index the search makes from them.
+    pub fn table_bytes(&self) -> usize {
+        self.pieces
+            .iter()
+            .map(|piece| {
+                piece.placements.len() * size_of::<Bitset>()
+                    + piece.placements.len() * piece.size * size_of::<(usize, Bitset)>()
+                    + piece
+                        .orintations
+                        .iter()
+                        .map(|ori| ori.blocks.len() * size_of::<Coord>())
+                        .sum::<usize>()
+            })
+            .sum()
+    }
+}
+
+impl Placement {
+    /// Bytes a copy of this placement holds.
+    pub fn bytes(&self) -> usize {
+        size_of::<Placement>() + self.placed.len() * size_of::<(usize, Bitset)>()
+    }
+}
+
+/// Peak resident set size of the process, where the OS reports it.
+pub fn peak_rss() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kb: usize = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// Parses sizes such as `4096`, `64K`, `512M` or `2G`.
+///
+/// ```
+/// use puzzle_cubes::memory::parse_size;
+///
+/// assert_eq!(parse_size("64K"), Ok(64 << 10));
+/// assert!(parse_size("99999999999G").is_err());
+/// ```
+pub fn parse_size(size: &str) -> Result<usize, String> {
+    let size = size.trim();
+    let (number, unit) = match size.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => size.split_at(index),
+        None => (size, ""),
+    };
+    let number: usize = number
+        .parse()
+        .map_err(|_| format!("invalid size {}", size))?;
+    let scale = match unit.to_ascii_uppercase().trim_end_matches('B') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return Err(format!("invalid size unit {}", unit)),
+    };
+    number
+        .checked_mul(scale)
+        .ok_or_else(|| format!("size {} is too large", size))
+}
+
+pub fn format_size(bytes: usize) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1048575 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        1048576..=1073741823 => format!("{:.1} MiB", bytes as f64 / 1048576.0),
+        _ => format!("{:.1} GiB", bytes as f64 / 1073741824.0),
+    }
+}
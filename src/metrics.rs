@@ -0,0 +1,281 @@
+use crate::{grid::NEIGHBOURS, Bitset, Color, Coord, Placement, Puzzle};
+use std::io::{self, Write};
+
+/// The direction each of `grid::NEIGHBOURS` points in, `x` being right, `y`
+/// up and `z` away from the viewer.
+pub const FACES: [&str; 6] = ["right", "left", "top", "bottom", "back", "front"];
+
+/// How a solution would stand as a physical assembly, `y` being up.
+pub struct Metrics {
+    /// Mean position of every cube.
+    pub center: [f64; 3],
+    /// Mean position of each piece's cubes, by piece id, `None` for pieces
+    /// the solution leaves out.
+    pub pieces: Vec<Option<[f64; 3]>>,
+    /// Pieces with a cube on the bottom layer.
+    pub base_pieces: usize,
+    /// Cubes with nothing under them that aren't on the bottom layer.
+    pub overhangs: usize,
+    /// Whether each piece shows on the outside of the assembly, by piece id,
+    /// `None` for pieces the solution leaves out.
+    pub visible: Vec<Option<bool>>,
+    /// Outside squares of each piece facing each of `FACES`, by piece id.
+    pub faces: [Vec<usize>; 6],
+}
+
+impl Metrics {
+    /// Pieces used but completely hidden inside the assembly.
+    pub fn hidden(&self) -> impl Iterator<Item = usize> + '_ {
+        self.visible
+            .iter()
+            .enumerate()
+            .filter(|(_, visible)| **visible == Some(false))
+            .map(|(id, _)| id)
+    }
+}
+
+impl Puzzle {
+    fn center(&self, bits: &Bitset) -> [f64; 3] {
+        let cells: Vec<_> = self
+            .grid
+            .cells()
+            .filter(|cell| bits.get(self.grid.index(cell)))
+            .collect();
+        let n = cells.len().max(1) as f64;
+        let sum = |f: fn(&Coord) -> i32| cells.iter().map(f).sum::<i32>() as f64 / n;
+        [sum(|c| c.x), sum(|c| c.y), sum(|c| c.z)]
+    }
+
+    /// Empty cells connected to the edge of the grid, which see out.
+    fn outside(&self, occupied: &Bitset) -> Bitset {
+        let grid = self.grid;
+        let mut outside = Bitset::empty();
+        let mut stack: Vec<Coord> = grid
+            .cells()
+            .filter(|c| {
+                c.x == 0
+                    || c.y == 0
+                    || c.z == 0
+                    || c.x == grid.x - 1
+                    || c.y == grid.y - 1
+                    || c.z == grid.z - 1
+            })
+            .collect();
+        while let Some(cell) = stack.pop() {
+            let index = grid.index(&cell);
+            if occupied.get(index) || outside.get(index) {
+                continue;
+            }
+            outside.set(index);
+            for (dx, dy, dz) in NEIGHBOURS {
+                let next = Coord::new(cell.x + dx, cell.y + dy, cell.z + dz);
+                if grid.contains(&next) {
+                    stack.push(next);
+                }
+            }
+        }
+        outside
+    }
+
+    pub fn metrics(&self, solution: &Placement) -> Metrics {
+        let occupied = &solution.occupied;
+        let pieces = self
+            .pieces
+            .iter()
+            .map(|piece| {
+                let (_, bits) = solution
+                    .placed
+                    .iter()
+                    .find(|(id, _)| *id == piece.piece_id)?;
+                Some(self.center(bits))
+            })
+            .collect();
+        let base_pieces = solution
+            .placed
+            .iter()
+            .filter(|(_, bits)| {
+                self.grid
+                    .cells()
+                    .any(|cell| cell.y == 0 && bits.get(self.grid.index(&cell)))
+            })
+            .count();
+        let overhangs = self
+            .grid
+            .cells()
+            .filter(|cell| cell.y > 0 && occupied.get(self.grid.index(cell)))
+            .filter(|cell| {
+                let below = Coord::new(cell.x, cell.y - 1, cell.z);
+                !occupied.get(self.grid.index(&below))
+            })
+            .count();
+        let outside = self.outside(occupied);
+        let mut visible: Vec<Option<bool>> = vec![None; self.pieces.len()];
+        let mut faces: [Vec<usize>; 6] = Default::default();
+        for counts in faces.iter_mut() {
+            counts.resize(self.pieces.len(), 0);
+        }
+        for (id, bits) in solution.placed.iter() {
+            visible[*id] = Some(false);
+            for cell in self.grid.cells() {
+                if !bits.get(self.grid.index(&cell)) {
+                    continue;
+                }
+                for (face, (dx, dy, dz)) in NEIGHBOURS.iter().enumerate() {
+                    let next = Coord::new(cell.x + dx, cell.y + dy, cell.z + dz);
+                    if !self.grid.contains(&next) || outside.get(self.grid.index(&next)) {
+                        faces[face][*id] += 1;
+                        visible[*id] = Some(true);
+                    }
+                }
+            }
+        }
+        Metrics {
+            center: self.center(occupied),
+            pieces,
+            base_pieces,
+            overhangs,
+            visible,
+            faces,
+        }
+    }
+}
+
+/// The piece colors, each once, in piece order.
+fn colors(puzzle: &Puzzle) -> Vec<Color> {
+    let mut colors: Vec<Color> = Vec::new();
+    for piece in puzzle.pieces.iter() {
+        if !colors.contains(&piece.color) {
+            colors.push(piece.color);
+        }
+    }
+    colors
+}
+
+/// Outside squares of each color facing each of `FACES`.
+fn face_colors(puzzle: &Puzzle, metrics: &Metrics, colors: &[Color]) -> [Vec<usize>; 6] {
+    metrics.faces.clone().map(|counts| {
+        colors
+            .iter()
+            .map(|color| {
+                puzzle
+                    .pieces
+                    .iter()
+                    .filter(|piece| piece.color == *color)
+                    .map(|piece| counts[piece.piece_id])
+                    .sum()
+            })
+            .collect()
+    })
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// One row per solution, numbered as given, with `x`, `y` and `z` columns
+/// for each piece and a column for the outside squares of each color on
+/// each face.
+pub fn write_csv(
+    out: &mut dyn Write,
+    puzzle: &Puzzle,
+    metrics: &[(usize, Metrics)],
+) -> io::Result<()> {
+    let colors = colors(puzzle);
+    write!(out, "solution,x,y,z,base_pieces,overhangs,hidden_pieces")?;
+    for piece in puzzle.pieces.iter() {
+        write!(out, ",{0}_x,{0}_y,{0}_z", piece.name)?;
+    }
+    for face in FACES {
+        for color in colors.iter() {
+            write!(out, ",{}_{}", face, color.name())?;
+        }
+    }
+    writeln!(out)?;
+    for (n, m) in metrics.iter() {
+        let [x, y, z] = m.center;
+        write!(
+            out,
+            "{},{:.3},{:.3},{:.3},{},{},{}",
+            n,
+            x,
+            y,
+            z,
+            m.base_pieces,
+            m.overhangs,
+            m.hidden().count()
+        )?;
+        for center in m.pieces.iter() {
+            match center {
+                Some([x, y, z]) => write!(out, ",{:.3},{:.3},{:.3}", x, y, z)?,
+                None => write!(out, ",,,")?,
+            }
+        }
+        for counts in face_colors(puzzle, m, &colors) {
+            for count in counts {
+                write!(out, ",{}", count)?;
+            }
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+pub fn write_json(
+    out: &mut dyn Write,
+    puzzle: &Puzzle,
+    metrics: &[(usize, Metrics)],
+) -> io::Result<()> {
+    let colors = colors(puzzle);
+    writeln!(out, "[")?;
+    for (i, (n, m)) in metrics.iter().enumerate() {
+        let [x, y, z] = m.center;
+        let pieces: Vec<String> = puzzle
+            .pieces
+            .iter()
+            .zip(m.pieces.iter())
+            .filter_map(|(piece, center)| {
+                let [x, y, z] = (*center)?;
+                Some(format!(
+                    "{}: [{:.3}, {:.3}, {:.3}]",
+                    json_string(&piece.name),
+                    x,
+                    y,
+                    z
+                ))
+            })
+            .collect();
+        let hidden: Vec<String> = m
+            .hidden()
+            .map(|id| json_string(&puzzle.pieces[id].name))
+            .collect();
+        let faces: Vec<String> = FACES
+            .iter()
+            .zip(face_colors(puzzle, m, &colors))
+            .map(|(face, counts)| {
+                let counts: Vec<String> = colors
+                    .iter()
+                    .zip(counts)
+                    .filter(|(_, count)| *count > 0)
+                    .map(|(color, count)| format!("\"{}\": {}", color.name(), count))
+                    .collect();
+                format!("\"{}\": {{{}}}", face, counts.join(", "))
+            })
+            .collect();
+        let comma = if i + 1 < metrics.len() { "," } else { "" };
+        writeln!(
+            out,
+            "  {{\"solution\": {}, \"center\": [{:.3}, {:.3}, {:.3}], \"base_pieces\": {}, \"overhangs\": {}, \"hidden\": [{}], \"pieces\": {{{}}}, \"faces\": {{{}}}}}{}",
+            n,
+            x,
+            y,
+            z,
+            m.base_pieces,
+            m.overhangs,
+            hidden.join(", "),
+            pieces.join(", "),
+            faces.join(", "),
+            comma
+        )?;
+    }
+    writeln!(out, "]")
+}
@@ -0,0 +1,98 @@
+use crate::{Bitset, Placement, Puzzle};
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    io::{self, Write},
+};
+
+/// Dead ends that come close: partial assemblies leaving only a few target
+/// cells empty that no remaining piece can be added to, counted by how
+/// often the search runs into them.
+///
+/// A puzzle is hard when many paths lead to the same attractive assembly
+/// that can't be finished, and the counts show which ones those are. The
+/// same pieces placed in another order are the same assembly.
+pub struct NearMisses {
+    max_empty: usize,
+    seen: HashMap<Vec<(usize, Bitset)>, u64>,
+}
+
+impl NearMisses {
+    /// Collects dead ends with at most `max_empty` target cells left.
+    pub fn new(max_empty: usize) -> Self {
+        Self {
+            max_empty,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Notes a node of the search that had no way to go on.
+    pub(crate) fn dead_end(&mut self, puzzle: &Puzzle, placement: &Placement) {
+        let empty = puzzle.target.without(&placement.occupied).len();
+        if empty == 0 || empty > self.max_empty {
+            return;
+        }
+        let mut key = placement.placed.clone();
+        key.sort_unstable();
+        *self.seen.entry(key).or_insert(0) += 1;
+    }
+
+    /// Distinct assemblies found.
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    /// The `n` assemblies reached most often, with how often.
+    pub fn most_reached(&self, n: usize) -> Vec<(Placement, u64)> {
+        let mut seen: Vec<(&Vec<(usize, Bitset)>, u64)> =
+            self.seen.iter().map(|(key, count)| (key, *count)).collect();
+        // Ties go to the fuller assembly, then the key, for a stable report.
+        seen.sort_by_key(|(key, count)| (Reverse(*count), Reverse(key.len()), *key));
+        seen.into_iter()
+            .take(n)
+            .map(|(key, count)| {
+                let mut placement = Placement::new();
+                for (id, bits) in key.iter() {
+                    placement.place(*id, bits.clone());
+                }
+                (placement, count)
+            })
+            .collect()
+    }
+
+    /// Summary and the `n` most reached assemblies, each with its empty
+    /// cells and the pieces left over.
+    pub fn write(&self, out: &mut dyn Write, puzzle: &Puzzle, n: usize) -> io::Result<()> {
+        let reached: u64 = self.seen.values().sum();
+        writeln!(
+            out,
+            "near misses: {} dead ends with at most {} empty cells, {} distinct",
+            reached,
+            self.max_empty,
+            self.len()
+        )?;
+        for (placement, count) in self.most_reached(n) {
+            let empty = puzzle.target.xor(&placement.occupied.and(&puzzle.target));
+            let left: Vec<&str> = puzzle
+                .pieces
+                .iter()
+                .filter(|piece| placement.placed.iter().all(|(id, _)| *id != piece.piece_id))
+                .map(|piece| piece.name.as_str())
+                .collect();
+            writeln!(
+                out,
+                "reached {} times, {} empty: {}, left over: {}",
+                count,
+                empty.len(),
+                puzzle.format_cells(&empty),
+                left.join(" ")
+            )?;
+            puzzle.show(out, &placement)?;
+        }
+        Ok(())
+    }
+}
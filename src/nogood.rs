@@ -0,0 +1,153 @@
+use crate::{memory::Budget, warmstart::shape_hashes, Bitset, Puzzle};
+use std::{collections::HashSet, mem::size_of};
+
+/// Search states found to have no completion, learned as subtrees fail.
+///
+/// The reason kept for a failure is just the cells still to fill and the
+/// shapes of the pieces left, not the placements that led there. Reaching
+/// the same cells by placing pieces in another order, or with identical
+/// pieces swapped, is recognized as the same conflict and not searched
+/// again. Pieces of one shape that differ in whether they can be turned over
+/// or left out aren't identical.
+pub struct Nogoods {
+    shapes: Vec<u64>,
+    failed: HashSet<(Bitset, u64)>,
+    capacity: usize,
+    hits: u64,
+}
+
+impl Nogoods {
+    /// Bytes a learned conflict takes, with room for the table to grow.
+    pub const ENTRY_BYTES: usize = 2 * size_of::<(Bitset, u64)>();
+
+    /// Learns at most `capacity` conflicts.
+    pub fn new(puzzle: &Puzzle, capacity: usize) -> Self {
+        Self {
+            shapes: shape_hashes(puzzle),
+            failed: HashSet::new(),
+            capacity,
+            hits: 0,
+        }
+    }
+
+    pub(crate) fn key(&self, occupied: &Bitset, remaining: &[usize]) -> (Bitset, u64) {
+        let shapes = remaining
+            .iter()
+            .fold(0, |key: u64, id| key.wrapping_add(self.shapes[*id]));
+        (occupied.clone(), shapes)
+    }
+
+    pub(crate) fn is_known(&mut self, key: &(Bitset, u64)) -> bool {
+        let known = self.failed.contains(key);
+        if known {
+            self.hits += 1;
+        }
+        known
+    }
+
+    /// Learns `key` while under capacity and while `budget` has room.
+    pub(crate) fn learn(&mut self, key: (Bitset, u64), budget: &mut Budget) {
+        if self.failed.len() < self.capacity
+            && !self.failed.contains(&key)
+            && budget.reserve("nogoods", Self::ENTRY_BYTES)
+        {
+            self.failed.insert(key);
+        }
+    }
+
+    /// Conflicts learned so far.
+    pub fn len(&self) -> usize {
+        self.failed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Times a learned conflict saved a search.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Nogoods;
+    use crate::{memory::Budget, unique::Unique, Placement, Puzzle, Solver, DEFAULT_PALETTE};
+    use std::path::Path;
+
+    fn count(puzzle: &Puzzle, nogoods: Option<Nogoods>) -> (usize, Option<Nogoods>) {
+        let mut solver = Solver::new();
+        solver.count_only = true;
+        solver.nogoods = nogoods;
+        let remaining: Vec<usize> = (0..puzzle.pieces.len()).collect();
+        solver.solve(puzzle, &mut Placement::new(), &remaining);
+        (solver.num_solutions, solver.nogoods)
+    }
+
+    #[test]
+    fn learning_keeps_every_solution() {
+        let puzzle = Puzzle::read(Path::new("puzzles/soma.txt"), DEFAULT_PALETTE).unwrap();
+        let (solutions, nogoods) = count(&puzzle, Some(Nogoods::new(&puzzle, 1 << 16)));
+        assert_eq!(solutions, count(&puzzle, None).0);
+        let nogoods = nogoods.unwrap();
+        assert!(!nogoods.is_empty());
+        assert!(nogoods.hits() > 0);
+    }
+
+    #[test]
+    fn nothing_learned_with_unique() {
+        let puzzle = Puzzle::read(Path::new("puzzles/soma.txt"), DEFAULT_PALETTE).unwrap();
+        let mut solver = Solver::new();
+        solver.count_only = true;
+        solver.nogoods = Some(Nogoods::new(&puzzle, 1 << 16));
+        solver.unique = Some(Unique::new(&puzzle, None));
+        let remaining: Vec<usize> = (0..puzzle.pieces.len()).collect();
+        solver.solve(&puzzle, &mut Placement::new(), &remaining);
+        assert_eq!(solver.num_solutions, 480);
+        assert!(solver.nogoods.unwrap().is_empty());
+    }
+
+    #[test]
+    fn identical_pieces_share_conflicts() {
+        // Two dominoes and a monomino don't fit on a 2x2x1 board. Which
+        // domino goes in first doesn't matter, so the second try is known.
+        let text = "stuck,2x2x1\nI,red,000-100\nJ,blue,000-100\nA,green,000\n";
+        let puzzle = Puzzle::parse(text, DEFAULT_PALETTE).unwrap();
+        let (solutions, nogoods) = count(&puzzle, Some(Nogoods::new(&puzzle, 1 << 10)));
+        assert_eq!(solutions, 0);
+        assert!(nogoods.unwrap().hits() > 0);
+    }
+
+    #[test]
+    fn spare_pieces_stand_apart_from_required_ones() {
+        // B may be left out and A may not, so a conflict with B left isn't
+        // one with A left.
+        let text = "ng,3x1x1,200\nB,blue,000,0-1\nA,red,000\nC,green,000-100\n";
+        let puzzle = Puzzle::parse(text, DEFAULT_PALETTE).unwrap();
+        let (solutions, _) = count(&puzzle, Some(Nogoods::new(&puzzle, 1 << 10)));
+        assert_eq!(solutions, count(&puzzle, None).0);
+        assert_eq!(solutions, 2);
+    }
+
+    #[test]
+    fn capacity_bounds_what_is_learned() {
+        let puzzle = Puzzle::read(Path::new("puzzles/soma.txt"), DEFAULT_PALETTE).unwrap();
+        let (_, nogoods) = count(&puzzle, Some(Nogoods::new(&puzzle, 5)));
+        assert_eq!(nogoods.unwrap().len(), 5);
+    }
+
+    #[test]
+    fn budget_bounds_what_is_learned() {
+        let puzzle = Puzzle::read(Path::new("puzzles/soma.txt"), DEFAULT_PALETTE).unwrap();
+        let mut solver = Solver::new();
+        solver.count_only = true;
+        solver.nogoods = Some(Nogoods::new(&puzzle, 1 << 16));
+        solver.budget = Budget::new(Some(3 * Nogoods::ENTRY_BYTES));
+        let remaining: Vec<usize> = (0..puzzle.pieces.len()).collect();
+        solver.solve(&puzzle, &mut Placement::new(), &remaining);
+        assert_eq!(solver.num_solutions, count(&puzzle, None).0);
+        assert_eq!(solver.nogoods.unwrap().len(), 3);
+        assert_eq!(solver.budget.refused(), ["nogoods"]);
+    }
+}
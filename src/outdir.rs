@@ -0,0 +1,145 @@
+use crate::{fingerprint::fnv1a, provenance::Provenance, tikz::TikzStyle, Placement, Puzzle};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// How each solution file is written.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum SolutionFormat {
+    /// The board as `show` prints it
+    #[default]
+    Text,
+    /// A TikZ picture with its color definitions
+    Tikz,
+    /// A printable SVG template of the layers
+    Svg,
+    /// A web page with a 3D viewer
+    Html,
+    /// An OpenSCAD model with a colored union of cubes per piece
+    Scad,
+    /// An STL mesh for 3D printing, a solid per piece
+    Stl,
+}
+
+impl SolutionFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            SolutionFormat::Text => "txt",
+            SolutionFormat::Tikz => "tex",
+            SolutionFormat::Svg => "svg",
+            SolutionFormat::Html => "html",
+            SolutionFormat::Scad => "scad",
+            SolutionFormat::Stl => "stl",
+        }
+    }
+
+    /// Writes `provenance` as a comment this format allows at the top.
+    pub fn write_provenance(&self, out: &mut dyn Write, provenance: &Provenance) -> io::Result<()> {
+        match self {
+            SolutionFormat::Text => provenance.write(out, "# "),
+            SolutionFormat::Tikz => provenance.write(out, "% "),
+            SolutionFormat::Scad => provenance.write(out, "// "),
+            // STL has nowhere to put it.
+            SolutionFormat::Stl => Ok(()),
+            SolutionFormat::Svg | SolutionFormat::Html => {
+                writeln!(out, "<!--")?;
+                provenance.write(out, "")?;
+                writeln!(out, "-->")
+            }
+        }
+    }
+}
+
+/// How solution files are named.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum FileNames {
+    /// The solution's number in the run, zero padded
+    #[default]
+    Index,
+    /// A hash of the solution, the same whichever run or order found it
+    Hash,
+}
+
+/// Writes every solution to its own file in a directory.
+pub struct OutputDir {
+    dir: PathBuf,
+    format: SolutionFormat,
+    names: FileNames,
+    written: usize,
+    /// Written at the top of every file when set.
+    pub provenance: Option<Provenance>,
+}
+
+impl OutputDir {
+    /// Creates `dir` if it doesn't exist yet.
+    pub fn create(dir: &Path, format: SolutionFormat, names: FileNames) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            format,
+            names,
+            written: 0,
+            provenance: None,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.written
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.written == 0
+    }
+
+    fn path(&self, puzzle: &Puzzle, placement: &Placement) -> PathBuf {
+        let name = match self.names {
+            FileNames::Index => format!("{:06}", self.written),
+            FileNames::Hash => {
+                let key = puzzle.solution_key(&placement.placed, &[]);
+                // Each mask takes at least its first word and none past the
+                // last with cells in it, so the names of boards within 64
+                // cells don't change.
+                let bytes: Vec<u8> = key
+                    .iter()
+                    .flat_map(|(id, bits)| {
+                        let mut words = bits.words();
+                        if words.is_empty() {
+                            words.push(0);
+                        }
+                        std::iter::once(*id as u64).chain(words)
+                    })
+                    .flat_map(u64::to_le_bytes)
+                    .collect();
+                format!("{:016x}", fnv1a(&bytes))
+            }
+        };
+        self.dir.join(name).with_extension(self.format.extension())
+    }
+
+    pub fn write(&mut self, puzzle: &Puzzle, placement: &Placement) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(self.path(puzzle, placement))?);
+        if let Some(provenance) = &self.provenance {
+            self.format.write_provenance(&mut out, provenance)?;
+        }
+        match self.format {
+            SolutionFormat::Text => puzzle.show(&mut out, placement)?,
+            SolutionFormat::Tikz => {
+                puzzle.write_tikz_colors(&mut out)?;
+                puzzle.write_tikz(&mut out, placement, TikzStyle::default(), 0.5)?;
+            }
+            SolutionFormat::Svg => puzzle.write_template(&mut out, placement, 20.0, 2)?,
+            SolutionFormat::Html => puzzle.write_html(&mut out, std::slice::from_ref(placement))?,
+            SolutionFormat::Scad => puzzle.write_scad(&mut out, placement, 20.0)?,
+            SolutionFormat::Stl => puzzle.write_stl(&mut out, placement, 20.0)?,
+        }
+        out.flush()?;
+        self.written += 1;
+        Ok(())
+    }
+}
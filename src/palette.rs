@@ -0,0 +1,133 @@
+use crate::{Color, Puzzle};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+#[cfg(feature = "cli")]
+use colored::Colorize;
+
+/// How piece colors are rendered. Every output goes through the palette so
+/// terminal and file exports agree on what each piece looks like.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum Palette {
+    /// The piece colors as written in the puzzle file
+    #[default]
+    Default,
+    /// Okabe-Ito colors, distinguishable with common color vision deficiencies
+    OkabeIto,
+    /// Paul Tol's bright scheme, also colorblind safe
+    Tol,
+    /// No colors at all, pieces are told apart by their letters only
+    Mono,
+}
+
+const OKABE_ITO: &[(u8, u8, u8)] = &[
+    (230, 159, 0),
+    (86, 180, 233),
+    (0, 158, 115),
+    (240, 228, 66),
+    (0, 114, 178),
+    (213, 94, 0),
+    (204, 121, 167),
+    (153, 153, 153),
+];
+
+const TOL: &[(u8, u8, u8)] = &[
+    (68, 119, 170),
+    (102, 204, 238),
+    (34, 136, 51),
+    (204, 187, 68),
+    (238, 102, 119),
+    (170, 51, 119),
+    (187, 187, 187),
+];
+
+impl Color {
+    pub fn rgb(&self) -> (u8, u8, u8) {
+        match self {
+            Color::Red => (205, 49, 49),
+            Color::Yellow => (229, 229, 16),
+            Color::Blue => (36, 114, 200),
+            Color::White => (229, 229, 229),
+            Color::Green => (13, 188, 121),
+            Color::Magenta => (188, 63, 188),
+            Color::Cyan => (17, 168, 205),
+            Color::BrightRed => (241, 76, 76),
+            Color::BrightYellow => (245, 245, 67),
+            Color::BrightBlue => (59, 142, 234),
+            Color::BrightGreen => (35, 209, 139),
+            Color::BrightMagenta => (214, 112, 214),
+            Color::BrightCyan => (41, 184, 219),
+        }
+    }
+}
+
+impl Palette {
+    /// RGB used by exporters for a piece of the given color. The colorblind
+    /// safe palettes have fewer entries than there are colors, so they take
+    /// entry `shade` instead, see `Puzzle::rgb`.
+    pub fn rgb(&self, color: Color, shade: usize) -> (u8, u8, u8) {
+        match self {
+            Palette::Default => color.rgb(),
+            Palette::OkabeIto => OKABE_ITO[shade % OKABE_ITO.len()],
+            Palette::Tol => TOL[shade % TOL.len()],
+            Palette::Mono => (255, 255, 255),
+        }
+    }
+
+    /// Paints `text` for the terminal.
+    pub fn paint(&self, color: Color, shade: usize, text: &str) -> String {
+        match self {
+            Palette::Default => color.color(text),
+            Palette::Mono => text.to_string(),
+            _ => truecolor(text, self.rgb(color, shade)),
+        }
+    }
+}
+
+impl Puzzle {
+    /// Which entry of a colorblind safe palette pieces of `color` get. The
+    /// colors are numbered in the order the pieces first use them, so two
+    /// colors only share an entry when the puzzle has more than the palette.
+    fn shade(&self, color: Color) -> usize {
+        let mut used: Vec<Color> = Vec::new();
+        for piece in self.pieces.iter() {
+            if piece.color == color {
+                return used.len();
+            }
+            if !used.contains(&piece.color) {
+                used.push(piece.color);
+            }
+        }
+        used.len()
+    }
+
+    /// RGB of pieces of `color` in the palette of the view.
+    pub fn rgb(&self, color: Color) -> (u8, u8, u8) {
+        self.view.palette.rgb(color, self.shade(color))
+    }
+
+    /// Paints `text` in the color of pieces of `color` for the terminal.
+    pub fn paint(&self, color: Color, text: &str) -> String {
+        self.view.palette.paint(color, self.shade(color), text)
+    }
+
+    pub fn colored_id(&self, piece_id: usize) -> String {
+        let piece = &self.pieces[piece_id];
+        self.paint(piece.color, &piece.char_id().to_string())
+    }
+
+    pub fn colored_name(&self, piece_id: usize) -> String {
+        let piece = &self.pieces[piece_id];
+        self.paint(piece.color, &piece.name)
+    }
+}
+
+#[cfg(feature = "cli")]
+fn truecolor(text: &str, (r, g, b): (u8, u8, u8)) -> String {
+    text.truecolor(r, g, b).to_string()
+}
+
+#[cfg(not(feature = "cli"))]
+fn truecolor(text: &str, _: (u8, u8, u8)) -> String {
+    text.to_string()
+}
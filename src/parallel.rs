@@ -0,0 +1,136 @@
+use crate::{Bitset, Placement, Puzzle, Solver};
+use rayon::prelude::*;
+use std::{
+    collections::BTreeMap,
+    sync::{mpsc, Arc},
+    thread,
+};
+
+impl Solver {
+    /// Same solutions in the same order as `corner_solve`, whatever the number
+    /// of threads. Each way of covering the first corner is searched as its
+    /// own task with its own solver; finished tasks are sent back over a
+    /// channel and their solutions output in task order, which is the order
+    /// the serial search visits them in. Tracing, histograms, timelines and
+    /// warm starts only see the first level of the search. Tasks hold their
+    /// solutions outside any memory limit, so with one the search runs
+    /// serially.
+    pub fn par_corner_solve(
+        &mut self,
+        puzzle: &Puzzle,
+        placement: &mut Placement,
+        corners: &[Bitset],
+        remaining: &[usize],
+    ) {
+        if self.budget.is_limited() {
+            return self.corner_solve(puzzle, placement, corners, remaining);
+        }
+        self.indexed(puzzle, |solver| {
+            solver.par_corner_node(puzzle, placement, corners, remaining)
+        })
+    }
+
+    fn par_corner_node(
+        &mut self,
+        puzzle: &Puzzle,
+        placement: &mut Placement,
+        corners: &[Bitset],
+        remaining: &[usize],
+    ) {
+        let Some((corner, new_corners)) = corners.split_last() else {
+            return self.corner_solve(puzzle, placement, corners, remaining);
+        };
+        let mut tasks = Vec::new();
+        for piece_id in remaining.iter() {
+            if !puzzle.usable(*piece_id, remaining) {
+                continue;
+            }
+            let piece = &puzzle.pieces[*piece_id];
+            let mut new_remaining = remaining.to_vec();
+            new_remaining.retain(|&id| id != *piece_id);
+            for bits in piece.placements.iter() {
+                if !bits.is_disjoint(corner)
+                    && self
+                        .check(puzzle, placement, piece, bits, &new_remaining)
+                        .is_none()
+                {
+                    tasks.push((*piece_id, bits.clone(), new_remaining.clone()));
+                }
+            }
+        }
+
+        let (propagate, open_face) = (self.propagate, self.open_face);
+        // Tasks can't tell which solutions are symmetric to another task's,
+        // so with `unique` they hand every solution back, as they do for the
+        // solutions file.
+        let count_only = self.count_only && self.unique.is_none() && self.solutions_file.is_none();
+        // Solutions that are handed back are told of when they're merged,
+        // so tasks only pass on their placements.
+        let progress = self.progress.clone().map(|progress| {
+            if count_only {
+                progress
+            } else {
+                Arc::new(move |placed, _| {
+                    if placed > 0 {
+                        progress(placed, 0)
+                    }
+                })
+            }
+        });
+        let groups = self.groups.clone();
+        let cell_index = self.cell_index.clone();
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| {
+            let placement = &*placement;
+            scope.spawn(move || {
+                tasks.par_iter().enumerate().for_each_with(
+                    sender,
+                    |sender, (index, (piece_id, bits, new_remaining))| {
+                        let mut solver = Solver::new();
+                        solver.collected = (!count_only).then(Vec::new);
+                        solver.count_only = count_only;
+                        solver.progress = progress.clone();
+                        solver.propagate = propagate;
+                        solver.open_face = open_face;
+                        solver.groups = groups.clone();
+                        solver.cell_index = cell_index.clone();
+                        let mut placement = placement.clone();
+                        solver.place(&mut placement, &puzzle.pieces[*piece_id], bits);
+                        solver.corner_solve(puzzle, &mut placement, new_corners, new_remaining);
+                        // The receiver only goes away if output failed.
+                        let _ = sender.send((index, solver));
+                    },
+                )
+            });
+
+            // Tasks finish in any order; each is output once those before
+            // it have been, so a task's solutions are freed as soon as it
+            // can go rather than once the whole search is done.
+            let mut finished = BTreeMap::new();
+            let mut next = 0;
+            for (index, solver) in receiver {
+                finished.insert(index, solver);
+                while let Some(solver) = finished.remove(&next) {
+                    self.merge(puzzle, solver);
+                    next += 1;
+                }
+            }
+        });
+    }
+
+    /// Adds what a task's solver did to this one and outputs its solutions.
+    fn merge(&mut self, puzzle: &Puzzle, solver: Solver) {
+        self.nodes += solver.nodes;
+        if solver.deepest.placed.len() > self.deepest.placed.len() {
+            self.deepest = solver.deepest;
+        }
+        match solver.collected {
+            Some(collected) => {
+                for solution in collected.iter() {
+                    self.emit(puzzle, solution);
+                }
+            }
+            None => self.num_solutions += solver.num_solutions,
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use crate::{Piece, Puzzle, MAX_PIECES};
+use std::collections::HashMap;
+
+impl Piece {
+    /// A piece can be referred to by its name or by its single character id.
+    pub fn matches(&self, name: &str) -> bool {
+        self.name == name || self.char_id().to_string() == name
+    }
+}
+
+impl Puzzle {
+    /// A puzzle of `pieces`, numbered again in order. Their ids, and the
+    /// ids their copies refer to, must be unique among them beforehand.
+    fn with_pieces(&self, name: String, pieces: Vec<Piece>) -> Puzzle {
+        let mut new_id = HashMap::new();
+        for (new, piece) in pieces.iter().enumerate() {
+            new_id.insert(piece.piece_id, new);
+        }
+        Puzzle {
+            name,
+            grid: self.grid,
+            target: self.target.clone(),
+            optional: self.optional.clone(),
+            pieces: pieces
+                .into_iter()
+                .map(|mut piece| {
+                    piece.piece_id = new_id[&piece.piece_id];
+                    piece.copy_of = piece.copy_of.and_then(|old| new_id.get(&old).copied());
+                    piece
+                })
+                .collect(),
+            view: self.view,
+        }
+    }
+
+    /// The piece and all other copies of the piece it's a copy of.
+    fn uses_of(&self, piece_id: usize) -> Vec<usize> {
+        let mut first = piece_id;
+        while let Some(previous) = self.pieces[first].copy_of {
+            first = previous;
+        }
+        self.copies(first)
+    }
+
+    /// Every piece matching one of `names`, with its copies.
+    fn named(&self, names: &[String]) -> Vec<usize> {
+        self.pieces
+            .iter()
+            .filter(|p| names.iter().any(|name| p.matches(name)))
+            .flat_map(|p| self.uses_of(p.piece_id))
+            .collect()
+    }
+
+    /// All pieces of `self` followed by all pieces of `other`. The pieces'
+    /// placements are kept, so both puzzles must have the same board: the
+    /// same target, don't-care and blocked cells.
+    pub fn merge(&self, other: &Puzzle) -> Result<Puzzle, String> {
+        if self.grid != other.grid {
+            return Err(format!(
+                "dimensions differ: {}x{}x{} vs {}x{}x{}",
+                self.grid.x, self.grid.y, self.grid.z, other.grid.x, other.grid.y, other.grid.z
+            ));
+        }
+        if self.target != other.target {
+            return Err("target cells differ".to_string());
+        }
+        if self.optional != other.optional {
+            return Err("don't-care cells differ".to_string());
+        }
+        if self.pieces.len() + other.pieces.len() > MAX_PIECES {
+            return Err(format!(
+                "{} pieces counting copies, a puzzle has at most {}",
+                self.pieces.len() + other.pieces.len(),
+                MAX_PIECES
+            ));
+        }
+        // The pieces of `other` are numbered after those of `self` first.
+        let offset = self.pieces.len();
+        let others = other.pieces.iter().map(|piece| Piece {
+            piece_id: piece.piece_id + offset,
+            copy_of: piece.copy_of.map(|copy_of| copy_of + offset),
+            ..piece.clone()
+        });
+        let pieces = self.pieces.iter().cloned().chain(others);
+        Ok(self.with_pieces(format!("{} + {}", self.name, other.name), pieces.collect()))
+    }
+
+    /// Removes one piece of `self` for every piece of `other` with the same
+    /// shape, regardless of name, color or rotation. The last such piece
+    /// goes, so it's a piece's last copy rather than one the others follow.
+    pub fn subtract(&self, other: &Puzzle) -> Result<Puzzle, String> {
+        let mut pieces = self.pieces.clone();
+        for piece in other.pieces.iter() {
+            let form = piece.canonical_form();
+            match pieces.iter().rposition(|p| p.canonical_form() == form) {
+                Some(index) => {
+                    pieces.remove(index);
+                }
+                None => return Err(format!("no piece with the shape of {}", piece.name)),
+            }
+        }
+        Ok(self.with_pieces(format!("{} - {}", self.name, other.name), pieces))
+    }
+
+    /// Drops every piece matching one of `names`, and all its copies.
+    pub fn remove(&self, names: &[String]) -> Result<Puzzle, String> {
+        self.check_names(names)?;
+        let named = self.named(names);
+        let pieces = self
+            .pieces
+            .iter()
+            .filter(|p| !named.contains(&p.piece_id))
+            .cloned();
+        Ok(self.with_pieces(self.name.clone(), pieces.collect()))
+    }
+
+    /// Keeps only the pieces matching one of `names`, and all their copies,
+    /// in their original order.
+    pub fn extract(&self, names: &[String]) -> Result<Puzzle, String> {
+        self.check_names(names)?;
+        let named = self.named(names);
+        let pieces = self
+            .pieces
+            .iter()
+            .filter(|p| named.contains(&p.piece_id))
+            .cloned();
+        Ok(self.with_pieces(self.name.clone(), pieces.collect()))
+    }
+
+    /// Puts the puzzle in the form `write` gives canonical files in: each
+    /// piece's blocks moved to the origin and sorted by `z`, `y` then `x`,
+    /// and with `sort` the pieces ordered by name, copies staying together.
+    pub fn canonicalize(&mut self, sort: bool) {
+        for piece in self.pieces.iter_mut() {
+            let mut ori = piece.orintations[0].normalise();
+            ori.blocks.sort_by_key(|b| (b.z, b.y, b.x));
+            piece.orintations[0] = ori;
+        }
+        if !sort {
+            return;
+        }
+        let mut groups: Vec<Vec<usize>> = self
+            .pieces
+            .iter()
+            .filter(|piece| piece.copy_of.is_none())
+            .map(|piece| self.copies(piece.piece_id))
+            .collect();
+        groups.sort_by(|a, b| self.pieces[a[0]].name.cmp(&self.pieces[b[0]].name));
+        let order: Vec<usize> = groups.concat();
+        let mut new_id = vec![0; order.len()];
+        for (new, old) in order.iter().enumerate() {
+            new_id[*old] = new;
+        }
+        let mut pieces: Vec<Option<Piece>> = self.pieces.drain(..).map(Some).collect();
+        self.pieces = order
+            .iter()
+            .map(|old| {
+                let mut piece = pieces[*old].take().unwrap();
+                piece.piece_id = new_id[*old];
+                piece.copy_of = piece.copy_of.map(|copy_of| new_id[copy_of]);
+                piece
+            })
+            .collect();
+    }
+
+    pub(crate) fn check_names(&self, names: &[String]) -> Result<(), String> {
+        for name in names.iter() {
+            if !self.pieces.iter().any(|p| p.matches(name)) {
+                return Err(format!("no piece named {}", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Puzzle, DEFAULT_PALETTE};
+
+    fn parse(text: &str) -> Puzzle {
+        Puzzle::parse(text, DEFAULT_PALETTE).unwrap()
+    }
+
+    /// Each piece's name and the id of the piece it's a copy of.
+    fn pieces(puzzle: &Puzzle) -> Vec<(&str, Option<usize>)> {
+        puzzle
+            .pieces
+            .iter()
+            .map(|piece| (piece.name.as_str(), piece.copy_of))
+            .collect()
+    }
+
+    #[test]
+    fn merge_keeps_copies_together() {
+        let a = parse("a,3x1x1\nI,red,000-100\n");
+        let b = parse("b,3x1x1\nA,blue,000,2\n");
+        let merged = a.merge(&b).unwrap();
+        assert_eq!(pieces(&merged), [("I", None), ("A", None), ("A", Some(1))]);
+        assert_eq!(
+            merged.fingerprint(),
+            parse("c,3x1x1\nI,red,000-100\nA,blue,000,2\n").fingerprint()
+        );
+    }
+
+    #[test]
+    fn merge_needs_the_same_board() {
+        let a = parse("a,3x1x1\nI,red,000-100\n");
+        for other in ["b,3x1x1,200\nA,blue,000\n", "b,3x1x1,,,200\nA,blue,000\n"] {
+            assert!(a.merge(&parse(other)).is_err());
+            assert!(parse(other).merge(&a).is_err());
+        }
+        assert!(a.merge(&parse("b,2x1x1\nA,blue,000\n")).is_err());
+    }
+
+    #[test]
+    fn merge_keeps_to_the_piece_cap() {
+        let a = parse("a,4x4x4\nA,blue,000,30\n");
+        assert!(a.merge(&parse("b,4x4x4\nB,red,000,6\n")).is_ok());
+        assert!(a.merge(&parse("b,4x4x4\nB,red,000,7\n")).is_err());
+    }
+
+    #[test]
+    fn remove_and_extract_take_copies_along() {
+        let puzzle = parse("p,3x1x1\nA,blue,000,2\nI,red,000-100\n");
+        let names = ["A".to_string()];
+        assert_eq!(pieces(&puzzle.remove(&names).unwrap()), [("I", None)]);
+        assert_eq!(
+            pieces(&puzzle.extract(&names).unwrap()),
+            [("A", None), ("A", Some(0))]
+        );
+        let names = ["I".to_string()];
+        assert_eq!(
+            pieces(&puzzle.remove(&names).unwrap()),
+            [("A", None), ("A", Some(0))]
+        );
+    }
+
+    #[test]
+    fn subtract_drops_the_last_copy() {
+        let puzzle = parse("p,3x1x1\nA,blue,000,3\n");
+        let other = parse("o,3x1x1\nB,red,000\n");
+        assert_eq!(
+            pieces(&puzzle.subtract(&other).unwrap()),
+            [("A", None), ("A", Some(0))]
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{parse_blocks, Bitset, Placement, Puzzle, Solver};
+
+/// What a search from a position found out about finishing it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outlook {
+    /// The remaining pieces can still fill the target.
+    Solvable,
+    /// No way of placing the remaining pieces fills the target.
+    Stuck,
+    /// The search ran out of nodes before finding out.
+    Unknown,
+}
+
+impl Puzzle {
+    /// Cells written as blocks like in a puzzle file, e.g. `000-100-110`, or
+    /// as a hex mask of cell indices like `show_bit` prints, e.g. `0x13`.
+    pub fn parse_cells(&self, cells: &str) -> Result<Bitset, String> {
+        if let Some(hex) = cells.strip_prefix("0x") {
+            let bits = Bitset::from_hex(hex).ok_or_else(|| format!("invalid mask {}", cells))?;
+            if bits.and(&self.grid.full()) != bits {
+                return Err(format!("mask {} is outside the puzzle", cells));
+            }
+            return Ok(bits);
+        }
+        let is_block = |block: &str| block.len() == 3 && block.chars().all(|c| c.is_ascii_digit());
+        if !cells.split('-').all(is_block) {
+            return Err(format!(
+                "invalid cells {}, expected e.g. 000-100-110 or 0x13",
+                cells
+            ));
+        }
+        let mut bits = Bitset::empty();
+        for cell in parse_blocks(cells).iter() {
+            if !self.grid.contains(cell) {
+                return Err(format!(
+                    "cell {}{}{} is outside the puzzle",
+                    cell.x, cell.y, cell.z
+                ));
+            }
+            bits.set(self.grid.index(cell));
+        }
+        Ok(bits)
+    }
+
+    /// Cells as blocks, the form `parse_cells` reads.
+    pub fn format_cells(&self, bits: &Bitset) -> String {
+        let blocks: Vec<String> = self
+            .grid
+            .cells()
+            .filter(|cell| bits.get(self.grid.index(cell)))
+            .map(|cell| format!("{}{}{}", cell.x, cell.y, cell.z))
+            .collect();
+        blocks.join("-")
+    }
+
+    /// The first copy of the piece called `name` that `placement` has not
+    /// placed yet.
+    pub fn unplaced_piece(&self, placement: &Placement, name: &str) -> Result<usize, String> {
+        self.check_names(&[name.to_string()])?;
+        self.pieces
+            .iter()
+            .filter(|piece| piece.matches(name))
+            .find(|piece| placement.placed.iter().all(|(id, _)| *id != piece.piece_id))
+            .map(|piece| piece.piece_id)
+            .ok_or_else(|| format!("piece {} is already placed", name))
+    }
+
+    /// The piece and cells of a move placing `name` on `cells`, given as for
+    /// `parse_cells`. When the piece has copies the first one not yet placed
+    /// is used.
+    pub fn parse_move(
+        &self,
+        placement: &Placement,
+        name: &str,
+        cells: &str,
+    ) -> Result<(usize, Bitset), String> {
+        let piece = &self.pieces[self.unplaced_piece(placement, name)?];
+        let bits = self.parse_cells(cells)?;
+        if !piece.placements.contains(&bits) {
+            return Err(format!("piece {} can't cover {}", piece.name, cells));
+        }
+        if !placement.is_valid(&bits) {
+            return Err(format!("{} overlaps a placed piece", cells));
+        }
+        Ok((piece.piece_id, bits))
+    }
+
+    /// Whether the pieces not in `placement` can finish it, searching at
+    /// most `nodes` placements.
+    pub fn outlook(&self, placement: &Placement, nodes: u64) -> Outlook {
+        let remaining: Vec<usize> = self
+            .pieces
+            .iter()
+            .map(|piece| piece.piece_id)
+            .filter(|id| placement.placed.iter().all(|(placed, _)| placed != id))
+            .collect();
+        let mut solver = Solver::new();
+        solver.propagate = true;
+        solver.node_limit = Some(nodes);
+        solver.solution_limit = Some(1);
+        solver.fill(self, &mut placement.clone(), &remaining);
+        if solver.num_solutions > 0 {
+            Outlook::Solvable
+        } else if solver.nodes >= nodes {
+            Outlook::Unknown
+        } else {
+            Outlook::Stuck
+        }
+    }
+
+    /// The index of the first move of `placement` after which the puzzle
+    /// could no longer be finished, if the searches find one.
+    pub fn first_bad_move(&self, placement: &Placement, nodes: u64) -> Option<usize> {
+        let mut position = Placement::new();
+        for (index, (id, bits)) in placement.placed.iter().enumerate() {
+            position.place(*id, bits.clone());
+            if self.outlook(&position, nodes) == Outlook::Stuck {
+                return Some(index);
+            }
+        }
+        None
+    }
+}
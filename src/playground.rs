@@ -0,0 +1,92 @@
+//! The solver as a WebAssembly module for the page in `web/`. The exports
+//! are plain C functions, so the page loads the module without a bindings
+//! generator: the puzzle goes in through memory from `playground_alloc` and
+//! the solutions come back as JSON from `playground_result`.
+
+use crate::{metrics::json_string, Placement, Puzzle, Solver, DEFAULT_PALETTE};
+use std::cell::RefCell;
+
+thread_local! {
+    /// The JSON of the last search, kept until the next one.
+    static RESULT: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Room for `len` bytes that the page writes a puzzle file into.
+#[no_mangle]
+pub extern "C" fn playground_alloc(len: usize) -> *mut u8 {
+    let mut buffer = Vec::<u8>::with_capacity(len);
+    let ptr = buffer.as_mut_ptr();
+    std::mem::forget(buffer);
+    ptr
+}
+
+/// Gives back memory from `playground_alloc`.
+///
+/// # Safety
+///
+/// `ptr` must come from `playground_alloc` called with the same `len`.
+#[no_mangle]
+pub unsafe extern "C" fn playground_free(ptr: *mut u8, len: usize) {
+    drop(Vec::from_raw_parts(ptr, 0, len));
+}
+
+/// Solves the puzzle file in the `len` bytes at `text`, see `solve`, and
+/// returns the length of its JSON. A limit of zero is no limit.
+///
+/// # Safety
+///
+/// `text` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn playground_solve(
+    text: *const u8,
+    len: usize,
+    solutions: u32,
+    placements: u32,
+) -> usize {
+    let text = std::slice::from_raw_parts(text, len);
+    let json = match std::str::from_utf8(text) {
+        Ok(text) => solve(
+            text,
+            (solutions > 0).then_some(solutions as usize),
+            (placements > 0).then_some(placements as u64),
+        ),
+        Err(_) => error("the puzzle is not UTF-8 text"),
+    };
+    RESULT.with(|result| {
+        *result.borrow_mut() = json;
+        result.borrow().len()
+    })
+}
+
+/// The JSON of the last `playground_solve`, valid until the next one.
+#[no_mangle]
+pub extern "C" fn playground_result() -> *const u8 {
+    RESULT.with(|result| result.borrow().as_ptr())
+}
+
+fn error(message: &str) -> String {
+    format!("{{\"error\": {}}}", json_string(message))
+}
+
+/// Searches the puzzle file `text` for solutions, up to the limits, and
+/// returns them as JSON: the puzzle's board, pieces and solutions as for
+/// `Puzzle::write_html`, the placements made and whether a limit cut the
+/// search short, or `{"error": ...}` for a puzzle that doesn't read.
+pub fn solve(text: &str, solution_limit: Option<usize>, node_limit: Option<u64>) -> String {
+    let puzzle = match Puzzle::parse(text, DEFAULT_PALETTE) {
+        Ok(puzzle) => puzzle,
+        Err(err) => return error(&err.to_string()),
+    };
+    let mut solver = Solver::new();
+    solver.collected = Some(Vec::new());
+    solver.solution_limit = solution_limit;
+    solver.node_limit = node_limit;
+    let remaining: Vec<usize> = (0..puzzle.pieces.len()).collect();
+    solver.solve(&puzzle, &mut Placement::new(), &remaining);
+    format!(
+        "{{\"placements\": {}, \"limited\": {}, \"puzzle\": {}}}",
+        solver.nodes,
+        solver.should_stop(),
+        puzzle.html_data(&solver.collected.unwrap_or_default())
+    )
+}
@@ -0,0 +1,100 @@
+use crate::{Bitset, Placement, Puzzle};
+
+/// What `Puzzle::preprocess` found.
+#[derive(Default)]
+pub struct Preprocessed {
+    /// Placements dropped from the tables.
+    pub removed: usize,
+    /// Pieces that fit in only one way and were placed.
+    pub forced: Vec<usize>,
+    /// Target cells no remaining placement covers.
+    pub uncoverable: Vec<usize>,
+    /// Pieces left without any placement.
+    pub stranded: Vec<usize>,
+}
+
+impl Preprocessed {
+    /// True if the search can be skipped, it has no solutions.
+    pub fn is_impossible(&self) -> bool {
+        !self.uncoverable.is_empty() || !self.stranded.is_empty()
+    }
+}
+
+impl Puzzle {
+    /// Shrinks the placement tables of `remaining` to a fixed point before
+    /// searching. Placements overlapping `placement` or leaving the region are
+    /// dropped, a piece with a single placement left is placed, and when every
+    /// placement covering an empty target cell belongs to one piece, that
+    /// piece loses its placements elsewhere. Forced pieces are added to
+    /// `placement` and taken out of `remaining`.
+    pub fn preprocess(
+        &mut self,
+        placement: &mut Placement,
+        remaining: &mut Vec<usize>,
+    ) -> Preprocessed {
+        let mut result = Preprocessed::default();
+        let region = self.target.or(&self.optional);
+        let total = self.placement_count(remaining);
+        loop {
+            let before = self.placement_count(remaining);
+            for piece_id in remaining.iter() {
+                let occupied = &placement.occupied;
+                self.pieces[*piece_id]
+                    .placements
+                    .retain(|bits| bits.is_disjoint(occupied) && bits.and(&region) == *bits);
+            }
+
+            for cell in self.target.without(&placement.occupied).iter() {
+                let cell = Bitset::cell(cell);
+                let mut owners = remaining.iter().filter(|id| {
+                    self.pieces[**id]
+                        .placements
+                        .iter()
+                        .any(|bits| !bits.is_disjoint(&cell))
+                });
+                if let (Some(&owner), None) = (owners.next(), owners.next()) {
+                    self.pieces[owner]
+                        .placements
+                        .retain(|bits| !bits.is_disjoint(&cell));
+                }
+            }
+
+            let single = remaining
+                .iter()
+                .position(|id| self.pieces[*id].placements.len() == 1 && !self.pieces[*id].spare);
+            if let Some(index) = single {
+                let piece_id = remaining.remove(index);
+                placement.place(piece_id, self.pieces[piece_id].placements[0].clone());
+                result.forced.push(piece_id);
+                continue;
+            }
+            if self.placement_count(remaining) == before {
+                break;
+            }
+        }
+        result.removed = total - self.placement_count(remaining) - result.forced.len();
+
+        let mut covered = placement.occupied.clone();
+        for piece_id in remaining.iter() {
+            for bits in self.pieces[*piece_id].placements.iter() {
+                covered = covered.or(bits);
+            }
+        }
+        result.uncoverable = (0..self.grid.len())
+            .filter(|cell| self.target.get(*cell) && !covered.get(*cell))
+            .collect();
+        result.stranded = remaining
+            .iter()
+            .copied()
+            .filter(|id| self.pieces[*id].placements.is_empty() && !self.pieces[*id].spare)
+            .collect();
+        result
+    }
+
+    fn placement_count(&self, pieces: &[usize]) -> usize {
+        pieces
+            .iter()
+            .map(|id| self.pieces[*id].placements.len())
+            .sum()
+    }
+}
@@ -0,0 +1,87 @@
+use std::{
+    cell::Cell,
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex, OnceLock,
+    },
+    time::Instant,
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static SPANS: Mutex<Vec<Span>> = Mutex::new(Vec::new());
+static NEXT_THREAD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static THREAD: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+struct Span {
+    name: &'static str,
+    thread: usize,
+    start: u128,
+    duration: u128,
+}
+
+/// Records a span from its creation until it is dropped.
+pub struct Guard {
+    name: &'static str,
+    start: Option<Instant>,
+}
+
+fn epoch() -> Instant {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    *EPOCH.get_or_init(Instant::now)
+}
+
+fn thread_index() -> usize {
+    THREAD.with(|thread| match thread.get() {
+        Some(index) => index,
+        None => {
+            let index = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+            thread.set(Some(index));
+            index
+        }
+    })
+}
+
+/// Starts collecting spans of the major phases for export in the
+/// chrome://tracing JSON format. Until then `span` records nothing.
+pub fn enable() {
+    epoch();
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn span(name: &'static str) -> Guard {
+    Guard {
+        name,
+        start: ENABLED.load(Ordering::Relaxed).then(Instant::now),
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            SPANS.lock().unwrap().push(Span {
+                name: self.name,
+                thread: thread_index(),
+                start: start.duration_since(epoch()).as_micros(),
+                duration: start.elapsed().as_micros(),
+            });
+        }
+    }
+}
+
+pub fn export(out: &mut impl Write) -> io::Result<()> {
+    let spans = SPANS.lock().unwrap();
+    writeln!(out, "{{\"traceEvents\":[")?;
+    for (i, span) in spans.iter().enumerate() {
+        let sep = if i + 1 < spans.len() { "," } else { "" };
+        writeln!(
+            out,
+            "{{\"name\":\"{}\",\"ph\":\"X\",\"pid\":1,\"tid\":{},\"ts\":{},\"dur\":{}}}{}",
+            span.name, span.thread, span.start, span.duration, sep
+        )?;
+    }
+    writeln!(out, "]}}")
+}
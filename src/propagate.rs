@@ -0,0 +1,112 @@
+use crate::{Bitset, Placement, Puzzle, Solver};
+
+impl Puzzle {
+    /// Placements forced around `occupied`, found by repeatedly placing any
+    /// remaining piece that fits in only one way and covering any empty target
+    /// cell that only one placement reaches. `None` if some piece or target
+    /// cell can no longer be covered at all. Spare pieces are only forced to
+    /// cover a cell.
+    pub fn forced_placements(
+        &self,
+        occupied: Bitset,
+        remaining: &[usize],
+    ) -> Option<Vec<(usize, Bitset)>> {
+        let mut occupied = occupied;
+        let mut remaining = remaining.to_vec();
+        let mut forced = Vec::new();
+        loop {
+            let mut counts = vec![0usize; self.grid.len()];
+            let mut last = vec![None; self.grid.len()];
+            let mut single = None;
+            for piece_id in remaining.iter() {
+                let piece = &self.pieces[*piece_id];
+                let mut viable = piece
+                    .placements
+                    .iter()
+                    .filter(|bits| occupied.is_disjoint(bits))
+                    .peekable();
+                let first = match viable.peek() {
+                    Some(first) => (*first).clone(),
+                    None if piece.spare => continue,
+                    None => return None,
+                };
+                let mut n = 0;
+                for bits in viable {
+                    n += 1;
+                    for cell in bits.iter() {
+                        counts[cell] += 1;
+                        last[cell] = Some((*piece_id, bits.clone()));
+                    }
+                }
+                if n == 1 && single.is_none() && !piece.spare {
+                    single = Some((*piece_id, first));
+                }
+            }
+
+            for cell in self.target.without(&occupied).iter() {
+                match counts[cell] {
+                    0 => return None,
+                    1 if single.is_none() => single = last[cell].take(),
+                    _ => {}
+                }
+            }
+
+            match single {
+                Some((piece_id, bits)) => {
+                    occupied = occupied.or(&bits);
+                    remaining.retain(|id| *id != piece_id);
+                    forced.push((piece_id, bits));
+                }
+                None => return Some(forced),
+            }
+        }
+    }
+}
+
+/// What propagation did at a search node.
+pub(crate) enum Propagated {
+    /// Nothing was forced, branch as usual.
+    Nothing,
+    /// The node can't lead to a solution.
+    Dead,
+    /// These placements were made, leaving the remaining pieces.
+    Forced(Vec<(usize, Bitset)>, Vec<usize>),
+}
+
+impl Solver {
+    /// Applies forced placements when propagation is on. Callers that get
+    /// `Forced` recurse on the new remaining pieces and then `unpropagate`.
+    pub(crate) fn propagate(
+        &mut self,
+        puzzle: &Puzzle,
+        placement: &mut Placement,
+        remaining: &[usize],
+    ) -> Propagated {
+        if !self.propagate {
+            return Propagated::Nothing;
+        }
+        match puzzle.forced_placements(placement.occupied.clone(), remaining) {
+            None => Propagated::Dead,
+            Some(forced) if forced.is_empty() => Propagated::Nothing,
+            Some(forced) => {
+                for (piece_id, bits) in forced.iter() {
+                    self.place(placement, &puzzle.pieces[*piece_id], bits);
+                }
+                let mut remaining = remaining.to_vec();
+                remaining.retain(|id| forced.iter().all(|(forced_id, _)| forced_id != id));
+                Propagated::Forced(forced, remaining)
+            }
+        }
+    }
+
+    pub(crate) fn unpropagate(
+        &mut self,
+        puzzle: &Puzzle,
+        placement: &mut Placement,
+        forced: &[(usize, Bitset)],
+    ) {
+        for (piece_id, _) in forced.iter().rev() {
+            self.backtrack(placement, &puzzle.pieces[*piece_id]);
+        }
+    }
+}
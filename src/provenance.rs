@@ -0,0 +1,116 @@
+use crate::Puzzle;
+use std::{
+    fmt,
+    io::{self, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// How a set of results was made: by which version, from which puzzle,
+/// with which search settings and when. Written at the top of archives and
+/// exported files so results that get passed around can be traced back.
+///
+/// In text it is a `provenance:` line followed by indented `key: value`
+/// lines, each behind the comment marker of the file format.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Provenance {
+    pub fields: Vec<(String, String)>,
+}
+
+impl Provenance {
+    /// The version, the puzzle with its fingerprint and the current time.
+    pub fn new(puzzle: &Puzzle) -> Self {
+        let mut provenance = Self { fields: Vec::new() };
+        provenance.set(
+            "version",
+            format!("puzzle-cubes {}", env!("CARGO_PKG_VERSION")),
+        );
+        provenance.set(
+            "puzzle",
+            format!("{} ({:016x})", puzzle.name, puzzle.fingerprint()),
+        );
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        provenance.set("created", format_time(now));
+        provenance
+    }
+
+    /// Adds a setting, or replaces it if already there.
+    pub fn set(&mut self, key: &str, value: impl fmt::Display) {
+        let value = value.to_string();
+        match self.fields.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value,
+            None => self.fields.push((key.to_string(), value)),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The block with every line after `prefix`, such as `% ` for TikZ.
+    pub fn write(&self, out: &mut dyn Write, prefix: &str) -> io::Result<()> {
+        writeln!(out, "{}provenance:", prefix)?;
+        for (key, value) in self.fields.iter() {
+            writeln!(out, "{}  {}: {}", prefix, key, value)?;
+        }
+        Ok(())
+    }
+
+    /// The first block in `text`, whatever comment marker it is behind.
+    pub fn find(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        let prefix = lines.find_map(|line| line.strip_suffix("provenance:"))?;
+        let fields = lines
+            .map_while(|line| line.strip_prefix(prefix)?.strip_prefix("  "))
+            .filter_map(|line| line.split_once(": "))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        Some(Self { fields })
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut text = Vec::new();
+        self.write(&mut text, "").map_err(|_| fmt::Error)?;
+        write!(f, "{}", String::from_utf8_lossy(&text))
+    }
+}
+
+/// Seconds since the epoch as a UTC date and time like
+/// `2024-03-01 12:00:00 UTC`.
+///
+/// ```
+/// use puzzle_cubes::provenance::format_time;
+///
+/// assert_eq!(format_time(0), "1970-01-01 00:00:00 UTC");
+/// assert_eq!(format_time(1709294400), "2024-03-01 12:00:00 UTC");
+/// ```
+pub fn format_time(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let secs = secs % 86400;
+    // Days to a civil date, counting in 400 year eras from 0000-03-01.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
@@ -0,0 +1,87 @@
+use crate::{adjacency::Adjacency, Color, Puzzle};
+
+impl Puzzle {
+    /// The first of the copies of `piece_id`, which all share its color.
+    fn root(&self, mut piece_id: usize) -> usize {
+        while let Some(previous) = self.pieces[piece_id].copy_of {
+            piece_id = previous;
+        }
+        piece_id
+    }
+
+    /// How often pieces of the same color touch, summed over the solutions
+    /// `adjacency` counted, each pair once per solution.
+    pub fn color_clashes(&self, adjacency: &Adjacency) -> u64 {
+        let n = self.pieces.len();
+        (0..n)
+            .flat_map(|a| (a + 1..n).map(move |b| (a, b)))
+            .filter(|(a, b)| self.pieces[*a].color == self.pieces[*b].color)
+            .map(|(a, b)| adjacency.count(a, b))
+            .sum()
+    }
+
+    /// Gives the pieces colors from `colors` so that pieces of the same
+    /// color touch as rarely as possible in the solutions `adjacency`
+    /// counted, copies of a piece keeping one color. Returns how often they
+    /// still do, as `color_clashes`.
+    ///
+    /// Pieces that touch most are colored first, each with the color that
+    /// clashes least with those colored so far, the least used on a tie.
+    /// Then pieces are moved to other colors while that helps.
+    pub fn recolor(&mut self, adjacency: &Adjacency, colors: &[Color]) -> u64 {
+        let roots: Vec<usize> = (0..self.pieces.len())
+            .filter(|id| self.pieces[*id].copy_of.is_none())
+            .collect();
+        let group: Vec<usize> = (0..self.pieces.len())
+            .map(|id| {
+                let root = self.root(id);
+                roots.iter().position(|r| *r == root).unwrap()
+            })
+            .collect();
+        let mut weight = vec![vec![0u64; roots.len()]; roots.len()];
+        for a in 0..self.pieces.len() {
+            for b in 0..self.pieces.len() {
+                let (ga, gb) = (group[a], group[b]);
+                if ga != gb {
+                    weight[ga][gb] += adjacency.count(a, b);
+                }
+            }
+        }
+        let clashes = |chosen: &[Option<usize>], g: usize, color: usize| -> u64 {
+            (0..roots.len())
+                .filter(|h| *h != g && chosen[*h] == Some(color))
+                .map(|h| weight[g][h])
+                .sum()
+        };
+        let mut order: Vec<usize> = (0..roots.len()).collect();
+        order.sort_by_key(|g| std::cmp::Reverse(weight[*g].iter().sum::<u64>()));
+        let mut chosen: Vec<Option<usize>> = vec![None; roots.len()];
+        for g in order {
+            let color = (0..colors.len())
+                .min_by_key(|c| {
+                    let used = chosen.iter().filter(|chosen| **chosen == Some(*c)).count();
+                    (clashes(&chosen, g, *c), used)
+                })
+                .unwrap();
+            chosen[g] = Some(color);
+        }
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for g in 0..roots.len() {
+                let current = chosen[g].unwrap();
+                let best = (0..colors.len())
+                    .min_by_key(|c| clashes(&chosen, g, *c))
+                    .unwrap();
+                if clashes(&chosen, g, best) < clashes(&chosen, g, current) {
+                    chosen[g] = Some(best);
+                    improved = true;
+                }
+            }
+        }
+        for id in 0..self.pieces.len() {
+            self.pieces[id].color = colors[chosen[group[id]].unwrap()];
+        }
+        self.color_clashes(adjacency)
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{Bitset, Placement, Puzzle, Solver};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+const LEVEL: i32 = 3;
+
+/// The decisions of a search, written as they are made to a zstd stream of
+/// lines: `p ID BITS` places a piece, `b N` takes back the last `N` pieces
+/// and `s` marks a solution. Replaying them repeats the run without
+/// searching, so it stays the same when the search itself changes.
+pub struct Recorder {
+    out: zstd::Encoder<'static, BufWriter<File>>,
+    backtracks: usize,
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl Recorder {
+    /// Starts a recording of a search from `placement`.
+    pub fn create(path: &Path, puzzle: &Puzzle, placement: &Placement) -> io::Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut recorder = Self {
+            out: zstd::Encoder::new(file, LEVEL)?,
+            backtracks: 0,
+        };
+        writeln!(
+            recorder.out,
+            "puzzle {:016x} {}",
+            puzzle.fingerprint(),
+            puzzle.pieces.len()
+        )?;
+        for (id, bits) in placement.placed.iter() {
+            recorder.place(*id, bits);
+        }
+        Ok(recorder)
+    }
+
+    fn write(&mut self, line: std::fmt::Arguments) {
+        if self.backtracks > 0 {
+            writeln!(self.out, "b {}", self.backtracks).expect("Failed to write recording");
+            self.backtracks = 0;
+        }
+        writeln!(self.out, "{}", line).expect("Failed to write recording");
+    }
+
+    pub fn place(&mut self, id: usize, bits: &Bitset) {
+        self.write(format_args!("p {} {:x}", id, bits));
+    }
+
+    pub fn backtrack(&mut self) {
+        self.backtracks += 1;
+    }
+
+    pub fn solution(&mut self) {
+        self.write(format_args!("s"));
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        if self.backtracks > 0 {
+            writeln!(self.out, "b {}", self.backtracks)?;
+        }
+        self.out.finish()?.flush()
+    }
+}
+
+impl Solver {
+    /// Repeats a recorded run on `placement`, which should start empty,
+    /// outputting its solutions in the same order. Placements are checked
+    /// against the pieces and each other, not against the placement tables,
+    /// so changes to preprocessing don't invalidate a recording.
+    pub fn replay(
+        &mut self,
+        puzzle: &Puzzle,
+        placement: &mut Placement,
+        path: &Path,
+    ) -> io::Result<()> {
+        let reader = BufReader::new(zstd::Decoder::new(File::open(path)?)?);
+        let mut lines = reader.lines();
+        let header = lines.next().transpose()?.unwrap_or_default();
+        let expected = format!(
+            "puzzle {:016x} {}",
+            puzzle.fingerprint(),
+            puzzle.pieces.len()
+        );
+        if header != expected {
+            return Err(invalid("the recording is of another puzzle".to_string()));
+        }
+        for (number, line) in lines.enumerate() {
+            let line = line?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let bad = || invalid(format!("line {}: invalid event {}", number + 2, line));
+            match fields[..] {
+                ["p", id, bits] => {
+                    let id: usize = id.parse().map_err(|_| bad())?;
+                    let bits = Bitset::from_hex(bits).ok_or_else(bad)?;
+                    let piece = puzzle.pieces.get(id).ok_or_else(bad)?;
+                    if bits.len() != piece.size || !placement.is_valid(&bits) {
+                        return Err(bad());
+                    }
+                    self.place(placement, piece, &bits);
+                }
+                ["b", count] => {
+                    for _ in 0..count.parse::<usize>().map_err(|_| bad())? {
+                        let (id, _) = *placement.placed.last().ok_or_else(bad)?;
+                        self.backtrack(placement, &puzzle.pieces[id]);
+                    }
+                }
+                ["s"] => {
+                    if placement.occupied.and(&puzzle.target) != puzzle.target {
+                        return Err(bad());
+                    }
+                    self.emit(puzzle, placement);
+                }
+                _ => return Err(bad()),
+            }
+        }
+        Ok(())
+    }
+}
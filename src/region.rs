@@ -0,0 +1,83 @@
+use crate::{cover::CellIndex, grid::NEIGHBOURS, Bitset, Coord, Puzzle};
+
+impl Puzzle {
+    /// The separate parts of the board pieces may cover, target and
+    /// don't-care cells joined face to face, ordered by their lowest cell.
+    /// Most puzzles have one; a tray with several wells has one per well.
+    pub fn regions(&self) -> Vec<Bitset> {
+        let mut left = self.target.or(&self.optional);
+        let mut regions = Vec::new();
+        while let Some(first) = left.first() {
+            let mut region = Bitset::empty();
+            let mut stack = vec![self.grid.coord(first)];
+            while let Some(cell) = stack.pop() {
+                let index = self.grid.index(&cell);
+                if !left.get(index) {
+                    continue;
+                }
+                left = left.xor(&Bitset::cell(index));
+                region.set(index);
+                for (dx, dy, dz) in NEIGHBOURS {
+                    let next = Coord::new(cell.x + dx, cell.y + dy, cell.z + dz);
+                    if self.grid.contains(&next) {
+                        stack.push(next);
+                    }
+                }
+            }
+            regions.push(region);
+        }
+        regions
+    }
+
+    /// An empty part of the board around `occupied` that the pieces in
+    /// `remaining` can't fill: no choice of them has as many blocks as its
+    /// target cells, don't-care cells in it making room for more. Pieces
+    /// are whole, so one that's too small for its smallest piece, or of a
+    /// size no sum of piece sizes makes, is a dead end.
+    pub fn dead_region(
+        &self,
+        index: &CellIndex,
+        occupied: &Bitset,
+        remaining: &[usize],
+    ) -> Option<Bitset> {
+        // Bit `n` is set when some of the pieces have `n` blocks in all.
+        let mut sums: u128 = 1;
+        for piece_id in remaining.iter() {
+            sums |= sums << self.pieces[*piece_id].size.min(127);
+        }
+        let mut left = self.target.or(&self.optional).without(occupied);
+        while let Some(start) = left.and(&self.target).first() {
+            let mut region = Bitset::cell(start);
+            let mut frontier = region.clone();
+            while let Some(cell) = frontier.first() {
+                let new = index.neighbours(cell).and(&left).without(&region);
+                region = region.or(&new);
+                frontier = frontier.xor(&Bitset::cell(cell)).or(&new);
+            }
+            left = left.without(&region);
+            let least = region.and(&self.target).len();
+            let most = region.len();
+            // Sums from `least` up to `most` blocks. Regions too large for
+            // `sums` to tell are let through.
+            if most > 127 {
+                continue;
+            }
+            let fitting = (u128::MAX << least) & (u128::MAX >> (127 - most));
+            if sums & fitting == 0 {
+                return Some(region);
+            }
+        }
+        None
+    }
+
+    /// Drops placements that aren't inside a single region, so no piece
+    /// covers a cell outside the board or spans two wells.
+    pub fn confine_to_regions(&mut self) {
+        let regions = self.regions();
+        for piece in self.pieces.iter_mut() {
+            piece
+                .placements
+                .retain(|bits| regions.iter().any(|region| bits.and(region) == *bits));
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{
+    adjacency::Adjacency, archive::Solution, transform::Permutation, view::TableFormat, Bitset,
+    Placement, Puzzle,
+};
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+};
+
+/// Which saved solutions a report looks at.
+#[derive(Default)]
+pub struct SolutionFilter {
+    /// Placements every solution must make.
+    pub placed: Placement,
+    /// Only solutions where every piece shows on the outside.
+    pub all_visible: bool,
+    /// Only solutions with a piece completely hidden inside.
+    pub hidden: bool,
+}
+
+impl SolutionFilter {
+    fn keeps(&self, puzzle: &Puzzle, solution: &Placement) -> bool {
+        if !self
+            .placed
+            .placed
+            .iter()
+            .all(|p| solution.placed.contains(p))
+        {
+            return false;
+        }
+        if !self.all_visible && !self.hidden {
+            return true;
+        }
+        let any_hidden = puzzle.metrics(solution).hidden().next().is_some();
+        !(self.all_visible && any_hidden || self.hidden && !any_hidden)
+    }
+}
+
+/// The analyses that only need solutions, gathered in one pass over a
+/// saved set so an expensive search is run once and looked at many times.
+pub struct SolutionReport {
+    filter: SolutionFilter,
+    symmetries: Vec<Permutation>,
+    /// Solutions read and solutions the filter kept.
+    read: u64,
+    kept: u64,
+    /// How many kept solutions put each piece in each cell, by piece id.
+    cells: Vec<Vec<usize>>,
+    adjacency: Adjacency,
+    distinct: HashSet<Vec<(usize, Bitset)>>,
+    /// Solutions some rotation turns into themselves.
+    symmetric: u64,
+    with_hidden: u64,
+    overhangs: u64,
+    base_pieces: u64,
+}
+
+impl SolutionReport {
+    pub fn new(puzzle: &Puzzle, filter: SolutionFilter) -> Self {
+        Self {
+            filter,
+            symmetries: puzzle.symmetries(),
+            read: 0,
+            kept: 0,
+            cells: vec![vec![0; puzzle.grid.len()]; puzzle.pieces.len()],
+            adjacency: Adjacency::new(puzzle),
+            distinct: HashSet::new(),
+            symmetric: 0,
+            with_hidden: 0,
+            overhangs: 0,
+            base_pieces: 0,
+        }
+    }
+
+    pub fn add(&mut self, puzzle: &Puzzle, solution: &Solution) {
+        self.read += 1;
+        let placement = Placement::from_solution(solution);
+        if !self.filter.keeps(puzzle, &placement) {
+            return;
+        }
+        self.kept += 1;
+        for (id, bits) in solution.iter() {
+            for (cell, count) in self.cells[*id].iter_mut().enumerate() {
+                if bits.get(cell) {
+                    *count += 1;
+                }
+            }
+        }
+        self.adjacency.add(puzzle, &placement);
+        let own = puzzle.solution_key(solution, &[]);
+        let turned =
+            self.symmetries.iter().skip(1).any(|rotation| {
+                puzzle.solution_key(solution, std::slice::from_ref(rotation)) == own
+            });
+        self.symmetric += turned as u64;
+        self.distinct
+            .insert(puzzle.solution_key(solution, &self.symmetries));
+        let metrics = puzzle.metrics(&placement);
+        self.with_hidden += metrics.hidden().next().is_some() as u64;
+        self.overhangs += metrics.overhangs as u64;
+        self.base_pieces += metrics.base_pieces as u64;
+    }
+
+    /// How many kept solutions cover each cell with `piece_id`.
+    pub fn piece_cells(&self, piece_id: usize) -> &[usize] {
+        &self.cells[piece_id]
+    }
+
+    /// How many different pieces the kept solutions put in each cell. A
+    /// cell with one always holds the same piece.
+    pub fn variety(&self) -> Vec<usize> {
+        let cells = self.cells.first().map_or(0, |counts| counts.len());
+        (0..cells)
+            .map(|cell| self.cells.iter().filter(|counts| counts[cell] > 0).count())
+            .collect()
+    }
+
+    /// Counts, symmetry, metrics, a heatmap of `piece_id`, or of how many
+    /// pieces each cell can hold when `None`, and the adjacency table.
+    pub fn write(
+        &self,
+        out: &mut dyn Write,
+        puzzle: &Puzzle,
+        piece_id: Option<usize>,
+    ) -> io::Result<()> {
+        writeln!(out, "{} of {} solutions kept", self.kept, self.read)?;
+        if self.kept == 0 {
+            return Ok(());
+        }
+        let mean = |total: u64| total as f64 / self.kept as f64;
+        writeln!(
+            out,
+            "{} distinct under the puzzle's {} rotations, {} turned into themselves by one",
+            self.distinct.len(),
+            self.symmetries.len(),
+            self.symmetric
+        )?;
+        writeln!(
+            out,
+            "{} with a hidden piece, {:.2} overhangs and {:.2} pieces on the bottom on average",
+            self.with_hidden,
+            mean(self.overhangs),
+            mean(self.base_pieces)
+        )?;
+        writeln!(out)?;
+        match piece_id {
+            Some(id) => {
+                writeln!(
+                    out,
+                    "solutions with {} in each cell:",
+                    puzzle.colored_name(id)
+                )?;
+                puzzle.write_counts(out, &Placement::new(), self.piece_cells(id))?;
+            }
+            None => {
+                writeln!(out, "pieces found in each cell:")?;
+                puzzle.write_counts(out, &Placement::new(), &self.variety())?;
+            }
+        }
+        writeln!(out)?;
+        self.adjacency.write(out, puzzle, TableFormat::Table)
+    }
+}
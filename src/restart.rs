@@ -0,0 +1,175 @@
+use crate::{propagate::Propagated, rng::Rng, Bitset, Placement, Puzzle, Solver};
+
+/// Settings for finding one solution with randomized restarts. Each run
+/// shuffles the piece and placement order and is abandoned after visiting
+/// its node budget, so one unlucky ordering can't stall the whole search.
+pub struct Restarts {
+    pub seed: u64,
+    /// Placements a run may make before it is abandoned.
+    pub budget: u64,
+    /// Scale the budget of the `i`-th run by the `i`-th Luby number.
+    pub luby: bool,
+    pub max_runs: usize,
+}
+
+/// How a restarted search ended.
+pub enum Finish {
+    /// A solution was found in this run, counting from zero.
+    Solved(usize),
+    /// A run finished within its budget without a solution, so none exists.
+    Unsolvable,
+    /// Every run ran out of budget.
+    OutOfRuns,
+}
+
+/// The `i`-th term of the Luby sequence 1 1 2 1 1 2 4 1 1 2 ..., from one.
+pub fn luby(mut i: u64) -> u64 {
+    loop {
+        let mut k = 1;
+        while (1 << k) - 1 < i {
+            k += 1;
+        }
+        if (1 << k) - 1 == i {
+            return 1 << (k - 1);
+        }
+        i -= (1 << (k - 1)) - 1;
+    }
+}
+
+enum Outcome {
+    Found,
+    Failed,
+    Exhausted,
+}
+
+struct Run {
+    /// Order in which each piece's placements are tried.
+    order: Vec<Vec<usize>>,
+    budget: u64,
+}
+
+impl Solver {
+    /// Looks for one solution, restarting with a new random ordering whenever
+    /// a run uses up its budget. The solution is left in `placement` and
+    /// output like any other.
+    pub fn restart_solve(
+        &mut self,
+        puzzle: &Puzzle,
+        placement: &mut Placement,
+        remaining: &[usize],
+        restarts: &Restarts,
+    ) -> Finish {
+        self.indexed(puzzle, |solver| {
+            solver.restart_runs(puzzle, placement, remaining, restarts)
+        })
+    }
+
+    fn restart_runs(
+        &mut self,
+        puzzle: &Puzzle,
+        placement: &mut Placement,
+        remaining: &[usize],
+        restarts: &Restarts,
+    ) -> Finish {
+        let mut rng = Rng::new(restarts.seed);
+        for i in 0..restarts.max_runs {
+            if self.should_stop() {
+                break;
+            }
+            let mut remaining = remaining.to_vec();
+            rng.shuffle(&mut remaining);
+            let order = puzzle
+                .pieces
+                .iter()
+                .map(|piece| {
+                    let mut order: Vec<usize> = (0..piece.placements.len()).collect();
+                    rng.shuffle(&mut order);
+                    order
+                })
+                .collect();
+            let scale = if restarts.luby { luby(i as u64 + 1) } else { 1 };
+            let run = Run {
+                order,
+                budget: restarts.budget.saturating_mul(scale),
+            };
+            match self.restart_run(puzzle, placement, &remaining, &run, &mut 0) {
+                Outcome::Found => {
+                    self.emit(puzzle, placement);
+                    return Finish::Solved(i);
+                }
+                Outcome::Failed => return Finish::Unsolvable,
+                Outcome::Exhausted => {}
+            }
+        }
+        Finish::OutOfRuns
+    }
+
+    /// Covers the lowest empty target cell next, like `fill`, trying pieces
+    /// and placements in the run's order.
+    fn restart_run(
+        &mut self,
+        puzzle: &Puzzle,
+        placement: &mut Placement,
+        remaining: &[usize],
+        run: &Run,
+        nodes: &mut u64,
+    ) -> Outcome {
+        match self.propagate(puzzle, placement, remaining) {
+            Propagated::Dead => return Outcome::Failed,
+            Propagated::Forced(forced, remaining) => {
+                let outcome = self.restart_run(puzzle, placement, &remaining, run, nodes);
+                if !matches!(outcome, Outcome::Found) {
+                    self.unpropagate(puzzle, placement, &forced);
+                }
+                return outcome;
+            }
+            Propagated::Nothing => {}
+        }
+        let cell = puzzle
+            .target
+            .without(&placement.occupied)
+            .first()
+            .map(Bitset::cell);
+        let required = remaining.iter().position(|id| !puzzle.pieces[*id].spare);
+        let pieces = match (&cell, required) {
+            (None, None) => return Outcome::Found,
+            (None, Some(index)) => &remaining[index..index + 1],
+            (Some(_), _) => remaining,
+        };
+
+        for piece_id in pieces.iter() {
+            if !puzzle.usable(*piece_id, remaining) {
+                continue;
+            }
+            let piece = &puzzle.pieces[*piece_id];
+            let mut new_remaining = remaining.to_vec();
+            new_remaining.retain(|&id| id != *piece_id);
+            for index in run.order[*piece_id].iter() {
+                let bits = &piece.placements[*index];
+                if cell.as_ref().is_some_and(|cell| bits.is_disjoint(cell)) {
+                    continue;
+                }
+                if self
+                    .check(puzzle, placement, piece, bits, &new_remaining)
+                    .is_some()
+                {
+                    continue;
+                }
+                *nodes += 1;
+                if *nodes > run.budget || self.should_stop() {
+                    return Outcome::Exhausted;
+                }
+                self.place(placement, piece, bits);
+                match self.restart_run(puzzle, placement, &new_remaining, run, nodes) {
+                    Outcome::Found => return Outcome::Found,
+                    Outcome::Exhausted => {
+                        self.backtrack(placement, piece);
+                        return Outcome::Exhausted;
+                    }
+                    Outcome::Failed => self.backtrack(placement, piece),
+                }
+            }
+        }
+        Outcome::Failed
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{
+    generate::StandardSet,
+    grid::{Grid, NEIGHBOURS},
+    rng::Rng,
+    Bitset, Coord, Placement, Puzzle,
+};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use std::io::{self, Write};
+
+/// A family of target shapes, sized by a box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum Preset {
+    /// Every cell of the box
+    Box,
+    /// The box with its inside hollowed out, leaving walls of the thickness
+    Shell,
+    /// Square layers shrinking by one cell per layer up `y`, from a corner
+    Pyramid,
+    /// Steps rising by one cell per step along `x`
+    Staircase,
+}
+
+/// A generated target, `cells` inside the box given by `size`.
+pub struct Shape {
+    pub name: String,
+    pub size: Grid,
+    pub cells: Vec<Coord>,
+}
+
+/// Checks `size` fits the default cube.
+fn check_size(size: &Grid) -> Result<(), String> {
+    let max = Grid::default();
+    if [(size.x, max.x), (size.y, max.y), (size.z, max.z)]
+        .iter()
+        .any(|(d, max)| !(1..=*max).contains(d))
+    {
+        return Err(format!(
+            "size {}x{}x{} must fit in {}x{}x{}",
+            size.x, size.y, size.z, max.x, max.y, max.z
+        ));
+    }
+    Ok(())
+}
+
+impl Preset {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Box => "box",
+            Preset::Shell => "shell",
+            Preset::Pyramid => "pyramid",
+            Preset::Staircase => "staircase",
+        }
+    }
+
+    fn contains(&self, size: &Grid, thickness: i32, cell: &Coord) -> bool {
+        match self {
+            Preset::Box => true,
+            Preset::Shell => [(cell.x, size.x), (cell.y, size.y), (cell.z, size.z)]
+                .iter()
+                .any(|(c, n)| *c < thickness || *c >= n - thickness),
+            Preset::Pyramid => cell.x < size.x - cell.y && cell.z < size.z - cell.y,
+            Preset::Staircase => cell.y <= cell.x,
+        }
+    }
+
+    /// The shape filling a `size` box. `thickness` is only used by shells.
+    pub fn shape(&self, size: Grid, thickness: i32) -> Result<Shape, String> {
+        check_size(&size)?;
+        if thickness < 1 {
+            return Err("thickness must be at least 1".to_string());
+        }
+        Ok(Shape {
+            name: format!("{}-{}x{}x{}", self.name(), size.x, size.y, size.z),
+            size,
+            cells: size
+                .cells()
+                .filter(|cell| self.contains(&size, thickness, cell))
+                .collect(),
+        })
+    }
+}
+
+impl Shape {
+    /// A random connected shape of `volume` cells inside `size`, grown from
+    /// a random cell by adding a random face neighbour at a time.
+    pub fn random(size: Grid, volume: usize, rng: &mut Rng) -> Result<Shape, String> {
+        check_size(&size)?;
+        if !(1..=size.len()).contains(&volume) {
+            return Err(format!(
+                "volume {} must be between 1 and {}",
+                volume,
+                size.len()
+            ));
+        }
+        let mut cells = vec![size.coord(rng.below(size.len()))];
+        while cells.len() < volume {
+            let mut frontier: Vec<Coord> = Vec::new();
+            for cell in cells.iter() {
+                for (dx, dy, dz) in NEIGHBOURS {
+                    let next = Coord::new(cell.x + dx, cell.y + dy, cell.z + dz);
+                    if size.contains(&next) && !cells.contains(&next) && !frontier.contains(&next) {
+                        frontier.push(next);
+                    }
+                }
+            }
+            cells.push(frontier[rng.below(frontier.len())]);
+        }
+        cells.sort_by_key(|c| (c.z, c.y, c.x));
+        Ok(Shape {
+            name: format!("random-{}", volume),
+            size,
+            cells,
+        })
+    }
+
+    /// Why `set` clearly can't fill the shape, if it can't: the volumes
+    /// differ, or preprocessing finds a cell or piece with no placement left.
+    pub fn infeasible(&self, set: StandardSet) -> Option<String> {
+        let puzzle = set.puzzle();
+        let volume: usize = puzzle.pieces.iter().map(|p| p.size).sum();
+        if volume != self.cells.len() {
+            return Some(format!(
+                "the shape has {} cells but the pieces fill {}",
+                self.cells.len(),
+                volume
+            ));
+        }
+        let mut puzzle = puzzle.with_target(self.target());
+        let mut remaining: Vec<usize> = (0..puzzle.pieces.len()).collect();
+        let result = puzzle.preprocess(&mut Placement::new(), &mut remaining);
+        if let Some(cell) = result.uncoverable.first() {
+            let cell = puzzle.grid.coord(*cell);
+            Some(format!("no piece covers {}{}{}", cell.x, cell.y, cell.z))
+        } else {
+            let piece = result.stranded.first()?;
+            Some(format!("piece {} fits nowhere", puzzle.pieces[*piece].name))
+        }
+    }
+
+    /// The cells on the default cube.
+    pub fn target(&self) -> Bitset {
+        let grid = Grid::default();
+        let mut target = Bitset::empty();
+        for cell in self.cells.iter() {
+            target.set(grid.index(cell));
+        }
+        target
+    }
+
+    /// A catalog figure line, `name,blocks`.
+    pub fn write_figure(&self, out: &mut dyn Write) -> io::Result<()> {
+        let blocks: Vec<String> = self
+            .cells
+            .iter()
+            .map(|c| format!("{}{}{}", c.x, c.y, c.z))
+            .collect();
+        writeln!(out, "{},{}", self.name, blocks.join("-"))
+    }
+
+    /// A puzzle filling the shape with `set`. Cells outside the shape are
+    /// don't-care cells, the only way a puzzle file can leave cells out.
+    pub fn puzzle(&self, set: StandardSet) -> Puzzle {
+        let mut puzzle = set.puzzle();
+        puzzle.name = format!("{} {}", set.name(), self.name);
+        puzzle.target = self.target();
+        puzzle.optional = puzzle.grid.full().xor(&puzzle.target);
+        puzzle
+    }
+}
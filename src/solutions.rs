@@ -0,0 +1,156 @@
+use crate::{
+    archive::{decode_pair, encode_pair, Solution},
+    Bitset, Placement,
+};
+use std::{
+    collections::HashSet,
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+};
+
+/// Solutions appended to a file as they're found, each as its pieces' ids
+/// and placement masks. Files ending in `.jsonl` get a JSON object per line,
+/// such as `{"placed": [[0, "0x0000000000000272"], [2, "0x..."]]}`; others
+/// get the records of an archive block uncompressed, a piece count then
+/// each piece's id and mask, see `archive::encode_pair`.
+///
+/// Files are added to rather than replaced, and a record cut short by a run
+/// that was stopped is dropped on opening, so a long enumeration can be run
+/// again into the same file. With `dedup` the solutions already in it are
+/// not written again.
+pub struct SolutionsFile {
+    out: BufWriter<File>,
+    json: bool,
+    /// Solutions in the file, by piece id, when they aren't written twice.
+    seen: Option<HashSet<Vec<(usize, Bitset)>>>,
+    /// Solutions in the file before this run.
+    pub known: usize,
+    /// Solutions written by this run.
+    pub written: usize,
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "jsonl")
+}
+
+/// The solutions at the start of `raw` and how many bytes they take, up to
+/// the first record that isn't whole.
+fn decode(raw: &[u8], json: bool) -> (Vec<Solution>, usize) {
+    let mut solutions = Vec::new();
+    let mut pos = 0;
+    if json {
+        while let Some(end) = raw[pos..].iter().position(|b| *b == b'\n') {
+            let line = String::from_utf8_lossy(&raw[pos..pos + end]);
+            match parse_line(&line) {
+                Some(solution) => solutions.push(solution),
+                None => break,
+            }
+            pos += end + 1;
+        }
+        return (solutions, pos);
+    }
+    'records: while let Some(pieces) = raw.get(pos).map(|n| *n as usize) {
+        let mut solution = Vec::with_capacity(pieces);
+        let mut end = pos + 1;
+        for _ in 0..pieces {
+            let Some((pair, len)) = decode_pair(&raw[end..]) else {
+                break 'records;
+            };
+            solution.push(pair);
+            end += len;
+        }
+        solutions.push(solution);
+        pos = end;
+    }
+    (solutions, pos)
+}
+
+/// A line as `SolutionsFile` writes them.
+fn parse_line(line: &str) -> Option<Solution> {
+    let pairs = line
+        .trim()
+        .strip_prefix("{\"placed\": [")?
+        .strip_suffix("]}")?;
+    if pairs.is_empty() {
+        return Some(Vec::new());
+    }
+    pairs
+        .strip_prefix('[')?
+        .strip_suffix(']')?
+        .split("], [")
+        .map(|pair| {
+            let (id, bits) = pair.split_once(", ")?;
+            let bits = bits.strip_prefix("\"0x")?.strip_suffix('"')?;
+            Some((id.parse().ok()?, Bitset::from_hex(bits)?))
+        })
+        .collect()
+}
+
+fn key(solution: &[(usize, Bitset)]) -> Vec<(usize, Bitset)> {
+    let mut key = solution.to_vec();
+    key.sort();
+    key
+}
+
+impl SolutionsFile {
+    /// Opens `path` to add to, creating it if needed.
+    pub fn open(path: &Path, dedup: bool) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        let mut raw = Vec::new();
+        file.read_to_end(&mut raw)?;
+        let json = is_json(path);
+        let (solutions, whole) = decode(&raw, json);
+        if whole < raw.len() {
+            file.set_len(whole as u64)?;
+        }
+        let seen = dedup.then(|| solutions.iter().map(|solution| key(solution)).collect());
+        Ok(Self {
+            out: BufWriter::new(file),
+            json,
+            seen,
+            known: solutions.len(),
+            written: 0,
+        })
+    }
+
+    /// Every whole solution in the file at `path`.
+    pub fn read(path: &Path) -> io::Result<Vec<Solution>> {
+        let raw = std::fs::read(path)?;
+        Ok(decode(&raw, is_json(path)).0)
+    }
+
+    /// Adds `placement` to the file, unless it's there already and
+    /// duplicates are left out.
+    pub fn push(&mut self, placement: &Placement) -> io::Result<()> {
+        if let Some(seen) = self.seen.as_mut() {
+            if !seen.insert(key(&placement.placed)) {
+                return Ok(());
+            }
+        }
+        if self.json {
+            let pairs: Vec<String> = placement
+                .placed
+                .iter()
+                .map(|(id, bits)| format!("[{}, \"0x{:016x}\"]", id, bits))
+                .collect();
+            writeln!(self.out, "{{\"placed\": [{}]}}", pairs.join(", "))?;
+        } else {
+            let mut record = vec![placement.placed.len() as u8];
+            for (id, bits) in placement.placed.iter() {
+                encode_pair(&mut record, *id, bits);
+            }
+            self.out.write_all(&record)?;
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
@@ -0,0 +1,41 @@
+use crate::{Placement, Puzzle};
+use std::io::{self, Write};
+
+impl Puzzle {
+    /// Piece `piece_id` in its orientation `index`, pushed into the corner
+    /// of the grid, or `None` if it doesn't fit in the grid that way.
+    pub fn orientation_placement(&self, piece_id: usize, index: usize) -> Option<Placement> {
+        let piece = &self.pieces[piece_id];
+        let bits = piece
+            .orintations
+            .get(index)?
+            .placements(&self.grid)
+            .into_iter()
+            .next()?;
+        let mut placement = Placement::new();
+        placement.place(piece_id, bits);
+        Some(placement)
+    }
+
+    /// One frame of `spin`: which orientation this is, then the piece as
+    /// `show` draws it.
+    pub fn write_orientation(
+        &self,
+        out: &mut dyn Write,
+        piece_id: usize,
+        index: usize,
+    ) -> io::Result<()> {
+        let piece = &self.pieces[piece_id];
+        writeln!(
+            out,
+            "{} orientation {} ({} orientations)",
+            self.colored_name(piece.piece_id),
+            index,
+            piece.orintations.len()
+        )?;
+        match self.orientation_placement(piece_id, index) {
+            Some(placement) => self.show(out, &placement),
+            None => writeln!(out, "doesn't fit in the grid"),
+        }
+    }
+}
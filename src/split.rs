@@ -0,0 +1,114 @@
+use crate::{Bitset, Placement, Puzzle, Solver};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use rayon::prelude::*;
+
+/// An axis to cut the puzzle across.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+/// A partition of the pieces into one group filling a region and another
+/// filling the rest of the target, with the solutions of each.
+pub struct Split {
+    pub first: Vec<usize>,
+    pub second: Vec<usize>,
+    pub solutions: (usize, usize),
+}
+
+/// Every set of pieces from `sizes`, in increasing order, whose sizes add up
+/// to `volume`.
+fn subsets(sizes: &[usize], volume: usize) -> Vec<Vec<usize>> {
+    fn grow(
+        sizes: &[usize],
+        next: usize,
+        left: usize,
+        set: &mut Vec<usize>,
+        all: &mut Vec<Vec<usize>>,
+    ) {
+        if left == 0 {
+            all.push(set.clone());
+            return;
+        }
+        for id in next..sizes.len() {
+            if sizes[id] <= left {
+                set.push(id);
+                grow(sizes, id + 1, left - sizes[id], set, all);
+                set.pop();
+            }
+        }
+    }
+    let mut all = Vec::new();
+    grow(sizes, 0, volume, &mut Vec::new(), &mut all);
+    all
+}
+
+impl Puzzle {
+    /// The lower half of the target across `axis`, the cells whose
+    /// coordinate along it is below the middle of the grid.
+    pub fn half(&self, axis: Axis) -> Bitset {
+        let mut half = Bitset::empty();
+        for cell in self.grid.cells() {
+            let (c, n) = match axis {
+                Axis::X => (cell.x, self.grid.x),
+                Axis::Y => (cell.y, self.grid.y),
+                Axis::Z => (cell.z, self.grid.z),
+            };
+            if c < n / 2 {
+                half.set(self.grid.index(&cell));
+            }
+        }
+        half.and(&self.target)
+    }
+
+    /// Target cells given as for `parse_cells`.
+    pub fn region(&self, cells: &str) -> Result<Bitset, String> {
+        let region = self.parse_cells(cells)?;
+        match region.and(&self.target) == region {
+            true => Ok(region),
+            false => Err(format!("{} is outside the target", cells)),
+        }
+    }
+
+    /// Every way of dividing the pieces into one group that exactly fills
+    /// `region` and one that fills the rest of the target. Copies of a
+    /// piece go to the first group in order, so swapping them isn't another
+    /// split.
+    pub fn splits(&self, region: Bitset) -> Vec<Split> {
+        let rest = self.target.xor(&region);
+        let (first, second) = (self.with_target(region.clone()), self.with_target(rest));
+        let sizes: Vec<usize> = self.pieces.iter().map(|piece| piece.size).collect();
+        let count = |puzzle: &Puzzle, pieces: &[usize]| {
+            let mut solver = Solver::new();
+            solver.fill(puzzle, &mut Placement::new(), pieces);
+            solver.num_solutions
+        };
+        subsets(&sizes, region.len())
+            .into_par_iter()
+            .filter(|set| {
+                set.iter().all(|id| {
+                    let copy_of = self.pieces[*id].copy_of;
+                    copy_of.is_none_or(|copy_of| set.contains(&copy_of))
+                })
+            })
+            .filter_map(|set| {
+                let others: Vec<usize> = (0..self.pieces.len())
+                    .filter(|id| !set.contains(id))
+                    .collect();
+                let solutions = match count(&first, &set) {
+                    0 => return None,
+                    n => (n, count(&second, &others)),
+                };
+                (solutions.1 > 0).then_some(Split {
+                    first: set,
+                    second: others,
+                    solutions,
+                })
+            })
+            .collect()
+    }
+}
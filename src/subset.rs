@@ -0,0 +1,83 @@
+use crate::{Placement, Puzzle, Solver};
+
+/// Pieces of which exactly `count` are used, for puzzles where a solution
+/// picks which pieces to use.
+#[derive(Clone, Debug)]
+pub struct Group {
+    pub pieces: Vec<usize>,
+    pub count: usize,
+}
+
+impl Group {
+    /// Whether `count` can still be met with `remaining` left to place.
+    fn feasible(&self, placement: &Placement, remaining: &[usize]) -> bool {
+        let used = placement
+            .placed
+            .iter()
+            .filter(|(id, _)| self.pieces.contains(id))
+            .count();
+        let left = remaining
+            .iter()
+            .filter(|id| self.pieces.contains(id))
+            .count();
+        used <= self.count && used + left >= self.count
+    }
+}
+
+impl Puzzle {
+    /// Lets solutions use any subset of the pieces. Pieces matching one of
+    /// `required` must still be used, and each of `groups`, given as
+    /// `names=count` with comma separated names, limits how many of its
+    /// pieces are used.
+    pub fn select(&mut self, required: &[String], groups: &[String]) -> Result<Vec<Group>, String> {
+        self.check_names(required)?;
+        for piece in self.pieces.iter_mut() {
+            piece.spare =
+                piece.copy_of.is_some() || !required.iter().any(|name| piece.matches(name));
+        }
+        groups.iter().map(|group| self.parse_group(group)).collect()
+    }
+
+    fn parse_group(&self, group: &str) -> Result<Group, String> {
+        let (names, count) = group
+            .split_once('=')
+            .ok_or_else(|| format!("group {} is not names=count", group))?;
+        let names: Vec<String> = names
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .collect();
+        self.check_names(&names)?;
+        let pieces: Vec<usize> = self
+            .pieces
+            .iter()
+            .filter(|piece| names.iter().any(|name| piece.matches(name)))
+            .map(|piece| piece.piece_id)
+            .collect();
+        let count = count
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid count in group {}", group))?;
+        if count > pieces.len() {
+            return Err(format!("group {} has only {} pieces", group, pieces.len()));
+        }
+        Ok(Group { pieces, count })
+    }
+
+    /// Names of the pieces `placement` uses, in piece order.
+    pub fn used_pieces(&self, placement: &Placement) -> Vec<&str> {
+        self.pieces
+            .iter()
+            .filter(|piece| placement.placed.iter().any(|(id, _)| *id == piece.piece_id))
+            .map(|piece| piece.name.as_str())
+            .collect()
+    }
+}
+
+impl Solver {
+    /// Whether every group can still get its count.
+    pub(crate) fn selected(&self, placement: &Placement, remaining: &[usize]) -> bool {
+        self.groups
+            .iter()
+            .all(|group| group.feasible(placement, remaining))
+    }
+}
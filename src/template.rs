@@ -0,0 +1,137 @@
+use crate::{html::html_escape, Coord, Placement, Puzzle};
+use std::io::{self, Write};
+
+/// Margin around the page and gap between layers, in cells.
+const MARGIN: f64 = 0.5;
+const GAP: f64 = 1.0;
+
+impl Puzzle {
+    /// An SVG page of `solution` at real scale for assembling it by hand:
+    /// each layer drawn from the bottom up, `cell` millimetres to a cube and
+    /// `columns` layers to a row. Board cells get a thin grid, pieces a
+    /// thick outline, a light fill in their color and their name.
+    pub fn write_template(
+        &self,
+        out: &mut dyn Write,
+        solution: &Placement,
+        cell: f64,
+        columns: usize,
+    ) -> io::Result<()> {
+        let board = self.target.or(&self.optional);
+        // Layers with nothing to fill or cover, such as don't-care cells
+        // above a short box, are left out.
+        let used = self.target.or(&solution.occupied);
+        let layers: Vec<i32> = (0..self.grid.z)
+            .filter(|z| {
+                self.grid
+                    .cells()
+                    .any(|c| c.z == *z && used.get(self.grid.index(&c)))
+            })
+            .collect();
+        let columns = columns.clamp(1, layers.len().max(1));
+        let rows = layers.len().div_ceil(columns);
+        let layer_width = self.grid.x as f64 + GAP;
+        // One more cell above each layer for its title.
+        let layer_height = self.grid.y as f64 + GAP + 1.0;
+        let width = (2.0 * MARGIN + columns as f64 * layer_width - GAP) * cell;
+        let height = (2.0 * MARGIN + rows as f64 * layer_height - GAP) * cell;
+        writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w:.1}mm\" height=\"{h:.1}mm\" viewBox=\"0 0 {w:.1} {h:.1}\">",
+            w = width,
+            h = height
+        )?;
+        writeln!(out, "<title>{}</title>", html_escape(&self.name))?;
+        writeln!(
+            out,
+            "<g font-family=\"sans-serif\" font-size=\"{:.1}\" text-anchor=\"middle\" dominant-baseline=\"central\">",
+            cell * 0.35
+        )?;
+        for (n, &z) in layers.iter().enumerate() {
+            let left = (MARGIN + (n % columns) as f64 * layer_width) * cell;
+            let top = (MARGIN + (n / columns) as f64 * layer_height + 1.0) * cell;
+            // Rows are flipped so y grows up the page, as in `show`.
+            let corner = |x: i32, y: i32| {
+                (
+                    left + x as f64 * cell,
+                    top + (self.grid.y - y) as f64 * cell,
+                )
+            };
+            let title = match n {
+                0 => format!("layer {} (bottom)", n + 1),
+                _ if n == layers.len() - 1 => format!("layer {} (top)", n + 1),
+                _ => format!("layer {}", n + 1),
+            };
+            writeln!(
+                out,
+                "<text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+                left + self.grid.x as f64 * cell / 2.0,
+                top - cell / 2.0,
+                title
+            )?;
+            for y in 0..self.grid.y {
+                for x in 0..self.grid.x {
+                    if !board.get(self.grid.index(&Coord::new(x, y, z))) {
+                        continue;
+                    }
+                    let (px, py) = corner(x, y + 1);
+                    let fill = match self.piece_in(solution, x, y, z) {
+                        Some(id) => {
+                            let (r, g, b) = self.rgb(self.pieces[id].color);
+                            format!("fill=\"rgb({},{},{})\" fill-opacity=\"0.35\"", r, g, b)
+                        }
+                        None => "fill=\"none\"".to_string(),
+                    };
+                    writeln!(
+                        out,
+                        "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" {} stroke=\"#999\" stroke-width=\"0.2\"/>",
+                        px, py, cell, cell, fill
+                    )?;
+                }
+            }
+            // An edge is outlined where the piece on one side differs from
+            // the piece on the other, including the edge of the layer.
+            for y in 0..=self.grid.y {
+                for x in 0..self.grid.x {
+                    if self.piece_in(solution, x, y, z) != self.piece_in(solution, x, y - 1, z) {
+                        write_edge(out, corner(x, y), corner(x + 1, y))?;
+                    }
+                }
+            }
+            for x in 0..=self.grid.x {
+                for y in 0..self.grid.y {
+                    if self.piece_in(solution, x, y, z) != self.piece_in(solution, x - 1, y, z) {
+                        write_edge(out, corner(x, y), corner(x, y + 1))?;
+                    }
+                }
+            }
+            // Each piece is named once per layer, in its first cell there.
+            for (id, bits) in solution.placed.iter() {
+                let first = self
+                    .grid
+                    .cells()
+                    .find(|c| c.z == z && bits.get(self.grid.index(c)));
+                if let Some(c) = first {
+                    let (px, py) = corner(c.x, c.y);
+                    writeln!(
+                        out,
+                        "<text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+                        px + cell / 2.0,
+                        py - cell / 2.0,
+                        html_escape(&self.pieces[*id].name)
+                    )?;
+                }
+            }
+        }
+        writeln!(out, "</g>")?;
+        writeln!(out, "</svg>")
+    }
+}
+
+fn write_edge(out: &mut dyn Write, from: (f64, f64), to: (f64, f64)) -> io::Result<()> {
+    writeln!(
+        out,
+        "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#000\" stroke-width=\"0.8\" stroke-linecap=\"square\"/>",
+        from.0, from.1, to.0, to.1
+    )
+}
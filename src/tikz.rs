@@ -0,0 +1,162 @@
+use crate::{Coord, Placement, Puzzle};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use std::io::{self, Write};
+
+/// How a solution is drawn in TikZ.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum TikzStyle {
+    /// One square grid per layer, side by side
+    #[default]
+    Layers,
+    /// The assembly as cubes in an oblique projection
+    Oblique,
+}
+
+/// Shift of each layer in the oblique projection, receding up and right.
+const DEPTH: (f64, f64) = (0.45, 0.3);
+
+impl Puzzle {
+    /// Name of the TikZ color of a piece, defined by `write_tikz_colors`.
+    fn tikz_color(&self, id: usize) -> String {
+        format!("piece{}", self.pieces[id].char_id())
+    }
+
+    /// A `\definecolor` line for each piece, in the palette of the view.
+    pub fn write_tikz_colors(&self, out: &mut dyn Write) -> io::Result<()> {
+        for piece in self.pieces.iter() {
+            let (r, g, b) = self.rgb(piece.color);
+            writeln!(
+                out,
+                "\\definecolor{{{}}}{{RGB}}{{{},{},{}}} % {}",
+                self.tikz_color(piece.piece_id),
+                r,
+                g,
+                b,
+                piece.name
+            )?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn piece_in(&self, solution: &Placement, x: i32, y: i32, z: i32) -> Option<usize> {
+        if !(0..self.grid.x).contains(&x)
+            || !(0..self.grid.y).contains(&y)
+            || !(0..self.grid.z).contains(&z)
+        {
+            return None;
+        }
+        let index = self.grid.index(&Coord::new(x, y, z));
+        solution
+            .placed
+            .iter()
+            .find(|(_, bits)| bits.get(index))
+            .map(|(id, _)| *id)
+    }
+
+    /// A `tikzpicture` of `solution`, one unit per cube before `scale`.
+    pub fn write_tikz(
+        &self,
+        out: &mut dyn Write,
+        solution: &Placement,
+        style: TikzStyle,
+        scale: f64,
+    ) -> io::Result<()> {
+        writeln!(out, "\\begin{{tikzpicture}}[scale={}]", scale)?;
+        match style {
+            TikzStyle::Layers => self.write_tikz_layers(out, solution)?,
+            TikzStyle::Oblique => self.write_tikz_oblique(out, solution)?,
+        }
+        writeln!(out, "\\end{{tikzpicture}}")
+    }
+
+    fn write_tikz_layers(&self, out: &mut dyn Write, solution: &Placement) -> io::Result<()> {
+        for z in 0..self.grid.z {
+            let left = z * (self.grid.x + 1);
+            for y in 0..self.grid.y {
+                for x in 0..self.grid.x {
+                    if let Some(id) = self.piece_in(solution, x, y, z) {
+                        writeln!(
+                            out,
+                            "  \\filldraw[fill={}, draw=black] ({},{}) rectangle +(1,1);",
+                            self.tikz_color(id),
+                            left + x,
+                            y
+                        )?;
+                    }
+                }
+            }
+            writeln!(
+                out,
+                "  \\node[below] at ({},0) {{$z={}$}};",
+                left as f64 + self.grid.x as f64 / 2.0,
+                z
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Cubes from the back layer forward and bottom up, so nearer faces are
+    /// painted over farther ones. Faces against another cube are left out.
+    fn write_tikz_oblique(&self, out: &mut dyn Write, solution: &Placement) -> io::Result<()> {
+        let point = |x: i32, y: i32, z: i32| {
+            let (dx, dy) = DEPTH;
+            format!(
+                "({:.2},{:.2})",
+                x as f64 + z as f64 * dx,
+                y as f64 + z as f64 * dy
+            )
+        };
+        for z in (0..self.grid.z).rev() {
+            for y in 0..self.grid.y {
+                for x in 0..self.grid.x {
+                    let Some(id) = self.piece_in(solution, x, y, z) else {
+                        continue;
+                    };
+                    let color = self.tikz_color(id);
+                    let mut face = |shade: &str, corners: [(i32, i32, i32); 4]| {
+                        let path: Vec<String> =
+                            corners.iter().map(|(x, y, z)| point(*x, *y, *z)).collect();
+                        writeln!(
+                            out,
+                            "  \\filldraw[fill={}{}, draw=black] {} -- cycle;",
+                            color,
+                            shade,
+                            path.join(" -- ")
+                        )
+                    };
+                    if self.piece_in(solution, x + 1, y, z).is_none() {
+                        face(
+                            "!70!black",
+                            [
+                                (x + 1, y, z),
+                                (x + 1, y, z + 1),
+                                (x + 1, y + 1, z + 1),
+                                (x + 1, y + 1, z),
+                            ],
+                        )?;
+                    }
+                    if self.piece_in(solution, x, y + 1, z).is_none() {
+                        face(
+                            "!60!white",
+                            [
+                                (x, y + 1, z),
+                                (x + 1, y + 1, z),
+                                (x + 1, y + 1, z + 1),
+                                (x, y + 1, z + 1),
+                            ],
+                        )?;
+                    }
+                    if self.piece_in(solution, x, y, z - 1).is_none() {
+                        face(
+                            "",
+                            [(x, y, z), (x + 1, y, z), (x + 1, y + 1, z), (x, y + 1, z)],
+                        )?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
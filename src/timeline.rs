@@ -0,0 +1,58 @@
+use std::{
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+/// When each solution was found, in time since the start and in nodes.
+pub struct Timeline {
+    start: Instant,
+    entries: Vec<(Duration, u64)>,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, nodes: u64) {
+        self.entries.push((self.start.elapsed(), nodes));
+    }
+
+    /// Time until the first solution.
+    pub fn first(&self) -> Option<Duration> {
+        self.entries.first().map(|(elapsed, _)| *elapsed)
+    }
+
+    pub fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "solution,seconds,nodes")?;
+        for (n, (elapsed, nodes)) in self.entries.iter().enumerate() {
+            writeln!(out, "{},{:.6},{}", n, elapsed.as_secs_f64(), nodes)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "[")?;
+        for (n, (elapsed, nodes)) in self.entries.iter().enumerate() {
+            let comma = if n + 1 < self.entries.len() { "," } else { "" };
+            writeln!(
+                out,
+                "  {{\"solution\": {}, \"seconds\": {:.6}, \"nodes\": {}}}{}",
+                n,
+                elapsed.as_secs_f64(),
+                nodes,
+                comma
+            )?;
+        }
+        writeln!(out, "]")
+    }
+}
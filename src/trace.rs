@@ -0,0 +1,64 @@
+use crate::{Bitset, Coord, Piece};
+use std::io::Write;
+
+/// Why a candidate placement was rejected.
+pub enum Prune {
+    Overlap,
+    MissesCorner,
+    /// This empty cell can no longer be covered by the pieces left.
+    Uncoverable(Coord),
+    /// The empty cells joined to this one, this many of them, can't be
+    /// filled by any choice of the pieces left.
+    DeadRegion(Coord, usize),
+}
+
+/// Log of the solver's decisions, indented by depth. Decisions deeper than
+/// `max_depth` are not written.
+pub struct Trace {
+    max_depth: usize,
+    out: Box<dyn Write + Send>,
+}
+
+impl Trace {
+    pub fn new(max_depth: usize, out: Box<dyn Write + Send>) -> Self {
+        Self { max_depth, out }
+    }
+
+    fn log(&mut self, depth: usize, message: String) {
+        if depth <= self.max_depth {
+            writeln!(self.out, "{:indent$}{}", "", message, indent = depth * 2)
+                .expect("Failed to write trace");
+        }
+    }
+
+    pub fn attempt(&mut self, depth: usize, piece: &Piece, bits: &Bitset) {
+        self.log(depth, format!("try {} {:016x}", piece.char_id(), bits));
+    }
+
+    pub fn accept(&mut self, depth: usize, piece: &Piece, bits: &Bitset) {
+        self.log(depth, format!("place {} {:016x}", piece.char_id(), bits));
+    }
+
+    pub fn prune(&mut self, depth: usize, reason: &Prune) {
+        let message = match reason {
+            Prune::Overlap => "prune: overlaps placed pieces".to_string(),
+            Prune::MissesCorner => "prune: does not cover the corner".to_string(),
+            Prune::Uncoverable(cell) => {
+                format!("prune: nothing left covers {}{}{}", cell.x, cell.y, cell.z)
+            }
+            Prune::DeadRegion(cell, size) => format!(
+                "prune: the {} empty cells at {}{}{} can't be filled",
+                size, cell.x, cell.y, cell.z
+            ),
+        };
+        self.log(depth, message);
+    }
+
+    pub fn backtrack(&mut self, depth: usize, piece: &Piece) {
+        self.log(depth, format!("backtrack {}", piece.char_id()));
+    }
+
+    pub fn solution(&mut self, depth: usize, index: usize) {
+        self.log(depth, format!("solution {}", index));
+    }
+}
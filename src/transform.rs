@@ -0,0 +1,540 @@
+use crate::{archive::Solution, split::Axis, Bitset, Coord, Grid, Orintaion, Piece, Puzzle};
+use std::{fmt, str::FromStr};
+
+/// The axes a puzzle file was written with: for each digit of its blocks
+/// in order, the axis it is here, `-` before it if it runs the other way.
+/// `xyz` is this tool's own; a file with `y` up might be `xzy`, or `x-zy`
+/// if it is also of the other handedness.
+///
+/// ```
+/// use puzzle_cubes::{split::Axis, transform::Axes};
+///
+/// let axes: Axes = "x-zy".parse().unwrap();
+/// assert_eq!(axes.to_string(), "x-zy");
+/// assert!("xyz".parse::<Axes>().unwrap().is_identity());
+/// assert!("xxy".parse::<Axes>().is_err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Axes {
+    axes: [(Axis, bool); 3],
+}
+
+impl Axes {
+    pub fn is_identity(&self) -> bool {
+        self.axes == [(Axis::X, false), (Axis::Y, false), (Axis::Z, false)]
+    }
+}
+
+impl FromStr for Axes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid axes {}, expected e.g. xyz or x-zy", s);
+        let mut axes = Vec::new();
+        let mut reversed = false;
+        for c in s.trim().chars() {
+            match c {
+                '-' if !reversed => reversed = true,
+                _ => {
+                    let axis = parse_axis(&c.to_string()).map_err(|_| invalid())?;
+                    axes.push((axis, reversed));
+                    reversed = false;
+                }
+            }
+        }
+        match axes[..] {
+            [a, b, c] if !reversed && a.0 != b.0 && b.0 != c.0 && a.0 != c.0 => {
+                Ok(Axes { axes: [a, b, c] })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Axes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (axis, reversed) in self.axes.iter() {
+            if *reversed {
+                write!(f, "-")?;
+            }
+            let name = match axis {
+                Axis::X => "x",
+                Axis::Y => "y",
+                Axis::Z => "z",
+            };
+            write!(f, "{}", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// One step of reshaping a puzzle, written `rotate:AXIS[:TURNS]`,
+/// `mirror:AXIS`, `translate:X,Y,Z` or `axes:AXES`.
+///
+/// ```
+/// use puzzle_cubes::{split::Axis, transform::Transform, Coord};
+///
+/// assert_eq!("rotate:z:3".parse(), Ok(Transform::Rotate(Axis::Z, 3)));
+/// assert_eq!("mirror:x".parse(), Ok(Transform::Mirror(Axis::X)));
+/// assert_eq!(
+///     "translate:1,0,-1".parse(),
+///     Ok(Transform::Translate(Coord::new(1, 0, -1)))
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transform {
+    /// Quarter turns about an axis, counterclockwise looking down it
+    Rotate(Axis, u8),
+    /// Reflection across the plane the axis is normal to
+    Mirror(Axis),
+    /// A shift of the board within the grid; pieces have no position
+    Translate(Coord),
+    /// From a file written with other axes to this tool's
+    Axes(Axes),
+}
+
+fn parse_axis(s: &str) -> Result<Axis, String> {
+    match s.trim().to_lowercase().as_str() {
+        "x" => Ok(Axis::X),
+        "y" => Ok(Axis::Y),
+        "z" => Ok(Axis::Z),
+        _ => Err(format!("unknown axis {}, expected x, y or z", s)),
+    }
+}
+
+impl FromStr for Transform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = s.split(':').collect();
+        match parts[..] {
+            ["rotate", axis] => Ok(Transform::Rotate(parse_axis(axis)?, 1)),
+            ["rotate", axis, turns] => {
+                let turns: u8 = turns
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("invalid number of turns {}", turns))?;
+                Ok(Transform::Rotate(parse_axis(axis)?, turns % 4))
+            }
+            ["mirror", axis] => Ok(Transform::Mirror(parse_axis(axis)?)),
+            ["axes", axes] => Ok(Transform::Axes(axes.parse()?)),
+            ["translate", offset] => {
+                let offset: Vec<i32> = offset
+                    .split(',')
+                    .map(|n| n.trim().parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| format!("invalid offset {}, expected X,Y,Z", offset))?;
+                match offset[..] {
+                    [x, y, z] => Ok(Transform::Translate(Coord::new(x, y, z))),
+                    _ => Err(format!("invalid offset {}, expected X,Y,Z", s)),
+                }
+            }
+            _ => Err(format!(
+                "invalid transform {}, expected rotate:AXIS[:TURNS], mirror:AXIS, translate:X,Y,Z or axes:AXES",
+                s
+            )),
+        }
+    }
+}
+
+impl Transform {
+    /// Where the rotation or reflection takes `c`, about the origin.
+    /// Translations leave it alone.
+    fn turn(&self, c: Coord) -> Coord {
+        match *self {
+            Transform::Rotate(axis, turns) => (0..turns).fold(c, |c, _| match axis {
+                Axis::X => Coord::new(c.x, -c.z, c.y),
+                Axis::Y => Coord::new(c.z, c.y, -c.x),
+                Axis::Z => Coord::new(-c.y, c.x, c.z),
+            }),
+            Transform::Mirror(Axis::X) => Coord::new(-c.x, c.y, c.z),
+            Transform::Mirror(Axis::Y) => Coord::new(c.x, -c.y, c.z),
+            Transform::Mirror(Axis::Z) => Coord::new(c.x, c.y, -c.z),
+            Transform::Translate(_) => c,
+            Transform::Axes(axes) => {
+                let mut moved = Coord::new(0, 0, 0);
+                for ((axis, reversed), value) in axes.axes.iter().zip([c.x, c.y, c.z]) {
+                    let value = if *reversed { -value } else { value };
+                    match axis {
+                        Axis::X => moved.x = value,
+                        Axis::Y => moved.y = value,
+                        Axis::Z => moved.z = value,
+                    }
+                }
+                moved
+            }
+        }
+    }
+
+    /// The grid after this step and where a cell of `grid` ends up in it.
+    /// Rotations and reflections keep the board inside the grid, swapping
+    /// its sides as needed; translations may push cells out of it.
+    fn on_grid(&self, grid: Grid) -> (Grid, impl Fn(Coord) -> Coord + '_) {
+        let far = self.turn(Coord::new(grid.x - 1, grid.y - 1, grid.z - 1));
+        let origin = self.turn(Coord::new(0, 0, 0));
+        let min = Coord::new(
+            far.x.min(origin.x),
+            far.y.min(origin.y),
+            far.z.min(origin.z),
+        );
+        let size = Grid::new(
+            (far.x - origin.x).abs() + 1,
+            (far.y - origin.y).abs() + 1,
+            (far.z - origin.z).abs() + 1,
+        );
+        let shift = match *self {
+            Transform::Translate(offset) => offset,
+            _ => Coord::new(0, 0, 0),
+        };
+        (size, move |c: Coord| {
+            let c = self.turn(c);
+            Coord::new(
+                c.x - min.x + shift.x,
+                c.y - min.y + shift.y,
+                c.z - min.z + shift.z,
+            )
+        })
+    }
+}
+
+impl Puzzle {
+    /// Copy of the puzzle with `steps` applied in order, to the board and
+    /// every piece, or with `names` to those pieces only. Fails if a
+    /// translation moves a target or don't-care cell off the grid.
+    pub fn transform(
+        &self,
+        steps: &[Transform],
+        names: Option<&[String]>,
+    ) -> Result<Puzzle, String> {
+        if let Some(names) = names {
+            self.check_names(names)?;
+        }
+        let mut grid = self.grid;
+        let mut target = self.target.clone();
+        let mut optional = self.optional.clone();
+        let mut shapes: Vec<Orintaion> = self
+            .pieces
+            .iter()
+            .map(|piece| piece.orintations[0].clone())
+            .collect();
+        for step in steps.iter() {
+            if names.is_none() {
+                let (next, map) = step.on_grid(grid);
+                let move_cells = |cells: &Bitset| {
+                    let mut moved = Bitset::empty();
+                    for cell in grid.cells().filter(|cell| cells.get(grid.index(cell))) {
+                        let to = map(cell);
+                        if !next.contains(&to) {
+                            return Err(format!(
+                                "translation moves cell {}{}{} outside the puzzle",
+                                cell.x, cell.y, cell.z
+                            ));
+                        }
+                        moved.set(next.index(&to));
+                    }
+                    Ok(moved)
+                };
+                target = move_cells(&target)?;
+                optional = move_cells(&optional)?;
+                grid = next;
+            }
+            for (piece, shape) in self.pieces.iter().zip(shapes.iter_mut()) {
+                if names.is_some_and(|names| !names.iter().any(|name| piece.matches(name))) {
+                    continue;
+                }
+                let blocks = shape.blocks.iter().map(|b| step.turn(*b)).collect();
+                *shape = Orintaion::new(blocks).normalise();
+            }
+        }
+        let pieces = self
+            .pieces
+            .iter()
+            .zip(shapes)
+            .map(|(piece, shape)| {
+                let turned = Piece {
+                    spare: piece.spare,
+                    copy_of: piece.copy_of,
+                    glued: piece.glued.clone(),
+                    ..Piece::on_grid(
+                        piece.piece_id,
+                        piece.name.clone(),
+                        piece.color,
+                        shape,
+                        &grid,
+                    )
+                };
+                match piece.flips {
+                    true => turned.flipped(&grid, &grid.full()),
+                    false => turned,
+                }
+            })
+            .collect();
+        let mut puzzle = Puzzle {
+            name: self.name.clone(),
+            grid,
+            target,
+            optional,
+            pieces,
+            view: self.view,
+        };
+        puzzle.confine_to_regions();
+        Ok(puzzle)
+    }
+}
+
+/// A rotation as where each cell index goes.
+pub type Permutation = Vec<usize>;
+
+/// The reflections of a board onto itself, see `Puzzle::reflections`.
+pub struct Reflections {
+    pub cells: Vec<Permutation>,
+    /// The piece each piece turns into, by piece id.
+    pub pieces: Vec<usize>,
+}
+
+impl Puzzle {
+    /// The rotations that turn the board, target and don't-care cells
+    /// alike, onto itself, as where each of its cells goes. The identity
+    /// comes first. Reflections are left out as
+    /// they would turn pieces into their mirror images.
+    pub fn symmetries(&self) -> Vec<Permutation> {
+        self.onto_board(self.grid_rotations())
+    }
+
+    /// Every rotation of the grid onto itself, the identity first.
+    fn grid_rotations(&self) -> Vec<Permutation> {
+        let grid = self.grid;
+        let generators: Vec<Permutation> = [Axis::X, Axis::Y, Axis::Z]
+            .iter()
+            .flat_map(|axis| [1, 2].map(|turns| Transform::Rotate(*axis, turns)))
+            .filter_map(|step| {
+                let (next, map) = step.on_grid(grid);
+                (next == grid).then(|| grid.cells().map(|cell| grid.index(&map(cell))).collect())
+            })
+            .collect();
+        let mut rotations: Vec<Permutation> = vec![(0..grid.len()).collect()];
+        let mut next = 0;
+        while next < rotations.len() {
+            for generator in generators.iter() {
+                let composed: Permutation = rotations[next].iter().map(|i| generator[*i]).collect();
+                if !rotations.contains(&composed) {
+                    rotations.push(composed);
+                }
+            }
+            next += 1;
+        }
+        rotations
+    }
+
+    /// Those of `turns` that take the board onto itself once moved back
+    /// into place.
+    fn onto_board(&self, turns: Vec<Permutation>) -> Vec<Permutation> {
+        let grid = self.grid;
+        // A board smaller than the grid is turned about its own corner:
+        // after each rotation it is moved back to where it was.
+        let board = self.target.or(&self.optional);
+        let cells: Vec<usize> = (0..grid.len()).filter(|i| board.get(*i)).collect();
+        let corner = |cells: &mut dyn Iterator<Item = Coord>| {
+            cells.fold(Coord::new(i32::MAX, i32::MAX, i32::MAX), |min, c| {
+                Coord::new(min.x.min(c.x), min.y.min(c.y), min.z.min(c.z))
+            })
+        };
+        let home = corner(&mut cells.iter().map(|i| grid.coord(*i)));
+        let mut symmetries: Vec<Permutation> = Vec::new();
+        for rotation in turns {
+            let turned = corner(&mut cells.iter().map(|i| grid.coord(rotation[*i])));
+            let mut moved: Permutation = (0..grid.len()).collect();
+            for i in cells.iter() {
+                let c = grid.coord(rotation[*i]);
+                let c = Coord::new(
+                    c.x - turned.x + home.x,
+                    c.y - turned.y + home.y,
+                    c.z - turned.z + home.z,
+                );
+                moved[*i] = grid.index(&c);
+            }
+            let keeps = |cells: &Bitset| permute(&moved, cells) == *cells;
+            if keeps(&self.target) && keeps(&self.optional) && !symmetries.contains(&moved) {
+                symmetries.push(moved);
+            }
+        }
+        symmetries
+    }
+
+    /// The reflections that turn the board onto itself, with the piece
+    /// each piece becomes, its mirror image. An error naming a piece whose
+    /// mirror image isn't one of the pieces, as reflected solutions would
+    /// then need pieces the puzzle doesn't have.
+    pub fn reflections(&self) -> Result<Reflections, String> {
+        let shape = |blocks: Vec<Coord>| {
+            Orintaion::new(blocks)
+                .all_orintations()
+                .iter()
+                .map(|ori| ori.sorted_key())
+                .min()
+                .unwrap_or_default()
+        };
+        let shapes: Vec<String> = self
+            .pieces
+            .iter()
+            .map(|piece| shape(piece.orintations[0].blocks.clone()))
+            .collect();
+        let pieces = self
+            .pieces
+            .iter()
+            .map(|piece| {
+                let mirrored = piece.orintations[0]
+                    .blocks
+                    .iter()
+                    .map(|b| Coord::new(-b.x, b.y, b.z))
+                    .collect();
+                let mirrored = shape(mirrored);
+                shapes
+                    .iter()
+                    .position(|shape| *shape == mirrored)
+                    .ok_or_else(|| {
+                        format!("piece {} has no mirror image among the pieces", piece.name)
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        let grid = self.grid;
+        let (_, mirror) = Transform::Mirror(Axis::X).on_grid(grid);
+        let mirror: Permutation = grid.cells().map(|cell| grid.index(&mirror(cell))).collect();
+        let turns = self
+            .grid_rotations()
+            .iter()
+            .map(|rotation| rotation.iter().map(|i| mirror[*i]).collect())
+            .collect();
+        Ok(Reflections {
+            cells: self.onto_board(turns),
+            pieces,
+        })
+    }
+
+    /// The same for two solutions that differ only by one of `symmetries`
+    /// or by which copy of a piece went where.
+    pub fn solution_key(
+        &self,
+        solution: &Solution,
+        symmetries: &[Permutation],
+    ) -> Vec<(usize, Bitset)> {
+        let root = |mut id: usize| {
+            while let Some(previous) = self.pieces[id].copy_of {
+                id = previous;
+            }
+            id
+        };
+        let key = |rotation: Option<&Permutation>| {
+            let mut key: Vec<(usize, Bitset)> = solution
+                .iter()
+                .map(|(id, bits)| {
+                    let bits =
+                        rotation.map_or_else(|| bits.clone(), |rotation| permute(rotation, bits));
+                    (root(*id), bits)
+                })
+                .collect();
+            key.sort_unstable();
+            key
+        };
+        symmetries
+            .iter()
+            .map(|rotation| key(Some(rotation)))
+            .min()
+            .unwrap_or_else(|| key(None))
+    }
+
+    /// Like `solution_key`, also the same for a solution and its mirror
+    /// images.
+    pub fn mirror_key(
+        &self,
+        solution: &Solution,
+        symmetries: &[Permutation],
+        reflections: &Reflections,
+    ) -> Vec<(usize, Bitset)> {
+        let mirrored: Solution = solution
+            .iter()
+            .map(|(id, bits)| (reflections.pieces[*id], bits.clone()))
+            .collect();
+        let own = self.solution_key(solution, symmetries);
+        if reflections.cells.is_empty() {
+            return own;
+        }
+        own.min(self.solution_key(&mirrored, &reflections.cells))
+    }
+}
+
+fn permute(rotation: &Permutation, cells: &Bitset) -> Bitset {
+    let mut moved = Bitset::empty();
+    for (from, to) in rotation.iter().enumerate() {
+        if cells.get(from) {
+            moved.set(*to);
+        }
+    }
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Transform;
+    use crate::{split::Axis, Coord, Placement, Puzzle, Solver, DEFAULT_PALETTE};
+
+    const BOARD: &str = "p,3x2x1,,,210\nL,red,000-100-010\nI,blue,000-100\n";
+
+    fn parse(text: &str) -> Puzzle {
+        Puzzle::parse(text, DEFAULT_PALETTE).unwrap()
+    }
+
+    fn count(puzzle: &Puzzle) -> usize {
+        let mut solver = Solver::new();
+        solver.count_only = true;
+        let remaining: Vec<usize> = (0..puzzle.pieces.len()).collect();
+        solver.solve(puzzle, &mut Placement::new(), &remaining);
+        solver.num_solutions
+    }
+
+    #[test]
+    fn rotation_turns_the_board() {
+        let puzzle = parse(BOARD);
+        let turned = puzzle
+            .transform(&[Transform::Rotate(Axis::Z, 1)], None)
+            .unwrap();
+        assert_eq!((turned.grid.x, turned.grid.y, turned.grid.z), (2, 3, 1));
+        // The blocked corner at 210 turns to 021.
+        assert!(!turned.target.get(turned.grid.index(&Coord::new(0, 2, 0))));
+        assert_eq!(count(&turned), count(&puzzle));
+
+        let back = turned
+            .transform(&[Transform::Rotate(Axis::Z, 3)], None)
+            .unwrap();
+        assert_eq!(back.grid, puzzle.grid);
+        assert_eq!(back.target, puzzle.target);
+        assert_eq!(back.fingerprint(), puzzle.fingerprint());
+    }
+
+    #[test]
+    fn mirror_of_named_pieces_only() {
+        let puzzle = parse("p,3x2x2\nS,red,000-100-110-111\nI,blue,000-100\n");
+        let names = ["S".to_string()];
+        let mirrored = puzzle
+            .transform(&[Transform::Mirror(Axis::X)], Some(&names))
+            .unwrap();
+        assert_eq!(mirrored.grid, puzzle.grid);
+        assert_ne!(mirrored.fingerprint(), puzzle.fingerprint());
+        assert_eq!(mirrored.pieces[1].placements, puzzle.pieces[1].placements);
+    }
+
+    #[test]
+    fn translation_keeps_cells_on_the_grid() {
+        let puzzle = parse(BOARD);
+        let step = Transform::Translate(Coord::new(1, 0, 0));
+        assert!(puzzle.transform(&[step], None).is_err());
+    }
+
+    #[test]
+    fn symmetries_of_the_board() {
+        assert_eq!(parse("c,2x2x2\nA,red,000\n").symmetries().len(), 24);
+        assert_eq!(parse("b,3x2x1\nA,red,000\n").symmetries().len(), 4);
+        // The blocked corner leaves only the identity.
+        assert_eq!(parse(BOARD).symmetries().len(), 1);
+    }
+}
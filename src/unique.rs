@@ -0,0 +1,126 @@
+use crate::{
+    memory::Budget,
+    transform::{Permutation, Reflections},
+    Bitset, Placement, Puzzle,
+};
+use std::{collections::HashSet, mem::size_of};
+
+/// Lets through only the first of the solutions that are rotations of each
+/// other, or also mirror images with reflections, counting all of them.
+pub struct Unique {
+    rotations: Vec<Permutation>,
+    reflections: Option<Reflections>,
+    seen: HashSet<Vec<(usize, Bitset)>>,
+    /// Solutions let through without their keys being kept.
+    unkept: usize,
+    /// Solutions looked at, including those let through.
+    pub total: usize,
+}
+
+impl Unique {
+    pub fn new(puzzle: &Puzzle, reflections: Option<Reflections>) -> Self {
+        Self {
+            rotations: puzzle.symmetries(),
+            reflections,
+            seen: HashSet::new(),
+            unkept: 0,
+            total: 0,
+        }
+    }
+
+    /// Whether `solution` is the first of its kind. Once `budget` has no
+    /// room for another key, a solution not seen yet is let through only if
+    /// it is the one whose own key is its kind's, which a complete search
+    /// reaches exactly once.
+    pub fn is_new(&mut self, puzzle: &Puzzle, solution: &Placement, budget: &mut Budget) -> bool {
+        self.total += 1;
+        let key = match &self.reflections {
+            Some(reflections) => puzzle.mirror_key(&solution.placed, &self.rotations, reflections),
+            None => puzzle.solution_key(&solution.placed, &self.rotations),
+        };
+        if self.seen.contains(&key) {
+            return false;
+        }
+        let bytes = size_of::<Vec<(usize, Bitset)>>() + key.len() * size_of::<(usize, Bitset)>();
+        if budget.reserve("unique solutions", 2 * bytes) {
+            return self.seen.insert(key);
+        }
+        let new = puzzle.solution_key(&solution.placed, &self.rotations[..1]) == key;
+        self.unkept += new as usize;
+        new
+    }
+
+    /// Solutions let through.
+    pub fn len(&self) -> usize {
+        self.seen.len() + self.unkept
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// How many symmetries a solution is compared under.
+    pub fn symmetries(&self) -> usize {
+        self.rotations.len() + self.reflections.as_ref().map_or(0, |r| r.cells.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Unique;
+    use crate::{memory::Budget, Placement, Puzzle, Solver, DEFAULT_PALETTE};
+    use std::path::Path;
+
+    fn count(puzzle: &Puzzle, unique: Option<Unique>) -> usize {
+        let mut solver = Solver::new();
+        solver.count_only = true;
+        solver.unique = unique;
+        let remaining: Vec<usize> = (0..puzzle.pieces.len()).collect();
+        solver.solve(puzzle, &mut Placement::new(), &remaining);
+        solver.num_solutions
+    }
+
+    #[test]
+    fn soma_solutions() {
+        let puzzle = Puzzle::read(Path::new("puzzles/soma.txt"), DEFAULT_PALETTE).unwrap();
+        assert_eq!(count(&puzzle, Some(Unique::new(&puzzle, None))), 480);
+        let reflections = puzzle.reflections().unwrap();
+        let unique = Unique::new(&puzzle, Some(reflections));
+        assert_eq!(unique.symmetries(), 48);
+        assert_eq!(count(&puzzle, Some(unique)), 240);
+    }
+
+    #[test]
+    fn copies_swapped_are_the_same_solution() {
+        // Two dominoes across a 2x2x1 board, lying either way, are one
+        // solution up to rotation.
+        let puzzle = Puzzle::parse("d,2x2x1\nI,red,000-100,2\n", DEFAULT_PALETTE).unwrap();
+        assert_eq!(count(&puzzle, None), 2);
+        assert_eq!(count(&puzzle, Some(Unique::new(&puzzle, None))), 1);
+    }
+
+    #[test]
+    fn counted_alike_without_room_for_keys() {
+        let text = "m,2x2x2\nL,red,000-100-010\nI,blue,000-100,0-3\nA,green,000,0-2\n";
+        let puzzle = Puzzle::parse(text, DEFAULT_PALETTE).unwrap();
+        let unique = Unique::new(&puzzle, None);
+        let expected = count(&puzzle, Some(Unique::new(&puzzle, None)));
+
+        let mut solver = Solver::new();
+        solver.count_only = true;
+        solver.unique = Some(unique);
+        solver.budget = Budget::new(Some(0));
+        let remaining: Vec<usize> = (0..puzzle.pieces.len()).collect();
+        solver.solve(&puzzle, &mut Placement::new(), &remaining);
+        assert_eq!(solver.num_solutions, expected);
+        assert_eq!(solver.unique.unwrap().len(), expected);
+        assert_eq!(solver.budget.refused(), ["unique solutions"]);
+    }
+
+    #[test]
+    fn reflections_need_mirror_pieces() {
+        let text = "s,3x2x2\nS,red,000-100-110-111\nI,blue,000-100\n";
+        let puzzle = Puzzle::parse(text, DEFAULT_PALETTE).unwrap();
+        assert!(puzzle.reflections().is_err());
+    }
+}
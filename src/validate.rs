@@ -0,0 +1,131 @@
+use crate::{grid::NEIGHBOURS, Coord, Puzzle};
+use std::fmt;
+
+/// Why a puzzle can't have any solutions, seen from its pieces and target
+/// alone.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    /// The piece has this block twice.
+    Repeated { piece: String, block: Coord },
+    /// The piece falls apart: this block isn't joined face to face to the
+    /// piece's first block, directly or through others.
+    Disconnected { piece: String, block: Coord },
+    /// The pieces can't fill the target: those that must be used have
+    /// `required` blocks, all of them `available`, and there are `target`
+    /// cells to fill and `optional` more that may be.
+    Volume {
+        required: usize,
+        available: usize,
+        target: usize,
+        optional: usize,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::Repeated { piece, block } => write!(
+                f,
+                "piece {} has block {}{}{} twice",
+                piece, block.x, block.y, block.z
+            ),
+            Problem::Disconnected { piece, block } => write!(
+                f,
+                "piece {} falls apart, block {}{}{} doesn't touch the others face to face",
+                piece, block.x, block.y, block.z
+            ),
+            Problem::Volume {
+                required,
+                available,
+                target,
+                optional,
+            } if *required > target + optional => write!(
+                f,
+                "the pieces that must be used have {} blocks but only {} cells can be filled",
+                required,
+                target + optional
+            ),
+            Problem::Volume {
+                available, target, ..
+            } => write!(
+                f,
+                "the pieces have {} blocks but the target has {} cells",
+                available, target
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Problem {}
+
+/// The first problem with the blocks of `piece`, in the order given.
+///
+/// ```
+/// use puzzle_cubes::{validate::check_blocks, Coord};
+///
+/// let bar = [Coord::new(0, 0, 0), Coord::new(1, 0, 0)];
+/// assert!(check_blocks("bar", &bar).is_ok());
+/// let apart = [Coord::new(0, 0, 0), Coord::new(1, 1, 0)];
+/// assert!(check_blocks("apart", &apart).is_err());
+/// ```
+pub fn check_blocks(piece: &str, blocks: &[Coord]) -> Result<(), Problem> {
+    for (i, block) in blocks.iter().enumerate() {
+        if blocks[..i].contains(block) {
+            return Err(Problem::Repeated {
+                piece: piece.to_string(),
+                block: *block,
+            });
+        }
+    }
+    let mut joined: Vec<Coord> = blocks.iter().take(1).copied().collect();
+    let mut next = 0;
+    while let Some(block) = joined.get(next).copied() {
+        next += 1;
+        for (dx, dy, dz) in NEIGHBOURS {
+            let neighbour = Coord::new(block.x + dx, block.y + dy, block.z + dz);
+            if blocks.contains(&neighbour) && !joined.contains(&neighbour) {
+                joined.push(neighbour);
+            }
+        }
+    }
+    match blocks.iter().find(|block| !joined.contains(block)) {
+        Some(block) => Err(Problem::Disconnected {
+            piece: piece.to_string(),
+            block: *block,
+        }),
+        None => Ok(()),
+    }
+}
+
+impl Puzzle {
+    /// Everything that keeps the puzzle from having solutions before any
+    /// search: pieces with repeated blocks or that fall apart, and pieces
+    /// that have too few or too many blocks for the target. Files are
+    /// checked for the first two on reading; pieces made in code may not be.
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems: Vec<Problem> = self
+            .pieces
+            .iter()
+            .filter(|piece| piece.copy_of.is_none())
+            .filter_map(|piece| check_blocks(&piece.name, &piece.orintations[0].blocks).err())
+            .collect();
+        let required = self
+            .pieces
+            .iter()
+            .filter(|piece| !piece.spare)
+            .map(|piece| piece.size)
+            .sum();
+        let available = self.pieces.iter().map(|piece| piece.size).sum();
+        let target = self.target.len();
+        let optional = self.optional.len();
+        if required > target + optional || available < target {
+            problems.push(Problem::Volume {
+                required,
+                available,
+                target,
+                optional,
+            });
+        }
+        problems
+    }
+}
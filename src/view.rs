@@ -0,0 +1,230 @@
+#[cfg(feature = "cli")]
+use crate::Coord;
+use crate::{palette::Palette, Piece, Placement, Puzzle};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+#[cfg(feature = "cli")]
+use colored::Colorize;
+use std::{
+    io::{self, Write},
+    ops::Range,
+};
+
+/// Display settings shared by everything that draws a board.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct View {
+    pub palette: Palette,
+    pub compact: bool,
+    /// Number the layers, columns and rows around boards.
+    pub labels: bool,
+    /// Mark the cell at the origin, outside the compact view.
+    pub origin: bool,
+    /// Columns of the terminal. Layers that don't fit side by side are
+    /// wrapped onto further rows; unlimited if not set.
+    pub width: Option<usize>,
+}
+
+/// Layout of tabular reports.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum TableFormat {
+    /// Aligned columns for reading
+    #[default]
+    Table,
+    /// Comma-separated values for other tools
+    Csv,
+}
+
+impl Piece {
+    /// What restricts the piece beyond its shape, `-` if nothing does.
+    fn constraints(&self, fixed: &Placement) -> &'static str {
+        if fixed.placed.iter().any(|(id, _)| *id == self.piece_id) {
+            "fixed"
+        } else if self.placements.is_empty() {
+            "no placements"
+        } else if self.spare {
+            "spare"
+        } else {
+            "-"
+        }
+    }
+}
+
+impl Puzzle {
+    /// The layers split into runs that fit the width side by side, for
+    /// boards whose cells are `width` characters wide. A single run when
+    /// the width isn't known or everything fits.
+    pub(crate) fn layer_bands(&self, width: usize) -> Vec<Range<i32>> {
+        let layer_width = width * self.grid.x as usize + 2;
+        let margin = if self.view.labels { 4 } else { 0 };
+        let per_band = match self.view.width {
+            Some(columns) => (columns.saturating_sub(margin) / layer_width).max(1) as i32,
+            None => self.grid.z.max(1),
+        };
+        (0..self.grid.z)
+            .step_by(per_band as usize)
+            .map(|z| z..(z + per_band).min(self.grid.z))
+            .collect()
+    }
+
+    /// What goes above the `n`th of `bands`: a blank line between bands,
+    /// and the layer numbers once the layers are wrapped or with labels.
+    pub(crate) fn write_band_header(
+        &self,
+        out: &mut dyn Write,
+        width: usize,
+        bands: &[Range<i32>],
+        n: usize,
+    ) -> io::Result<()> {
+        if n > 0 {
+            writeln!(out)?;
+        }
+        let band = bands[n].clone();
+        let layer_width = width * self.grid.x as usize;
+        if self.view.labels {
+            write!(out, "    ")?;
+        } else if bands.len() == 1 {
+            return Ok(());
+        }
+        for z in band.clone() {
+            write!(out, "{:<1$}  ", format!("z={}", z), layer_width)?;
+        }
+        writeln!(out)?;
+        if !self.view.labels {
+            return Ok(());
+        }
+        // Column numbers under the layer numbers.
+        write!(out, "y/x ")?;
+        for _ in band {
+            for x in 0..self.grid.x {
+                write!(out, "{:<1$}", x, width)?;
+            }
+            write!(out, "  ")?;
+        }
+        writeln!(out)
+    }
+
+    /// One row per piece. Pieces already placed in `fixed` are marked as such.
+    pub fn piece_table(
+        &self,
+        out: &mut dyn Write,
+        format: TableFormat,
+        fixed: &Placement,
+    ) -> io::Result<()> {
+        match format {
+            TableFormat::Table => {
+                let width = self.pieces.iter().map(|p| p.name.len()).max().unwrap_or(0);
+                let width = width.max("name".len());
+                writeln!(
+                    out,
+                    "{:<2} {:<width$} {:>4} {:<5} {:>12} {:>10} constraints",
+                    "id", "name", "size", "color", "orientations", "placements"
+                )?;
+                for piece in self.pieces.iter() {
+                    writeln!(
+                        out,
+                        "{:<2} {:<width$} {:>4} {}    {:>12} {:>10} {}",
+                        piece.char_id(),
+                        piece.name,
+                        piece.size,
+                        self.paint(piece.color, "\u{2588}"),
+                        piece.orintations.len(),
+                        piece.placements.len(),
+                        piece.constraints(fixed)
+                    )?;
+                }
+                for piece in self.pieces.iter().filter(|p| p.copy_of.is_none()) {
+                    if let Some(derivation) = piece.derivation() {
+                        writeln!(out, "{} is glued from {}", piece.name, derivation)?;
+                    }
+                }
+            }
+            TableFormat::Csv => {
+                writeln!(
+                    out,
+                    "id,name,size,color,orientations,placements,constraints,glued"
+                )?;
+                for piece in self.pieces.iter() {
+                    writeln!(
+                        out,
+                        "{},{},{},{},{},{},{},{}",
+                        piece.char_id(),
+                        piece.name,
+                        piece.size,
+                        piece.color.name(),
+                        piece.orintations.len(),
+                        piece.placements.len(),
+                        piece.constraints(fixed),
+                        piece.derivation().unwrap_or_default()
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "cli")]
+    fn piece_at(&self, placement: &Placement, x: i32, y: i32, z: i32) -> Option<&Piece> {
+        if y < 0 {
+            return None;
+        }
+        let index = self.grid.index(&Coord::new(x, y, z));
+        placement
+            .placed
+            .iter()
+            .find(|(_, bits)| bits.get(index))
+            .map(|(id, _)| &self.pieces[*id])
+    }
+
+    /// Like `show` but packs two rows into each line: the upper half of a
+    /// character is the cell at `y`, the lower half the cell at `y - 1`.
+    #[cfg(feature = "cli")]
+    pub fn show_compact(&self, out: &mut dyn Write, placement: &Placement) -> io::Result<()> {
+        let bands = self.layer_bands(1);
+        for (n, band) in bands.iter().enumerate() {
+            self.write_band_header(out, 1, &bands, n)?;
+            self.show_compact_band(out, placement, band.clone())?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "cli")]
+    fn show_compact_band(
+        &self,
+        out: &mut dyn Write,
+        placement: &Placement,
+        band: Range<i32>,
+    ) -> io::Result<()> {
+        for y in (0..self.grid.y).rev().step_by(2) {
+            if self.view.labels {
+                write!(out, "{:>3} ", y)?;
+            }
+            for z in band.clone() {
+                for x in 0..self.grid.x {
+                    let upper = self.piece_at(placement, x, y, z);
+                    let lower = self.piece_at(placement, x, y - 1, z);
+                    let cell = match (upper, lower) {
+                        (None, None) => " ".normal(),
+                        (Some(upper), None) => {
+                            let (r, g, b) = self.rgb(upper.color);
+                            "▀".truecolor(r, g, b)
+                        }
+                        (None, Some(lower)) => {
+                            let (r, g, b) = self.rgb(lower.color);
+                            "▄".truecolor(r, g, b)
+                        }
+                        (Some(upper), Some(lower)) => {
+                            let (r, g, b) = self.rgb(upper.color);
+                            let (br, bg, bb) = self.rgb(lower.color);
+                            "▀".truecolor(r, g, b).on_truecolor(br, bg, bb)
+                        }
+                    };
+                    write!(out, "{}", cell)?;
+                }
+                write!(out, "  ")?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
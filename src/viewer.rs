@@ -0,0 +1,214 @@
+use crate::{events::Event, grid::Grid, Placement, Puzzle};
+use macroquad::{
+    camera::{set_camera, set_default_camera, Camera3D},
+    color::{Color as Rgb, BLACK, DARKGRAY, WHITE},
+    input::{
+        get_char_pressed, is_key_pressed, is_mouse_button_down, mouse_delta_position, mouse_wheel,
+        KeyCode, MouseButton,
+    },
+    math::{vec3, Vec3},
+    models::{draw_cube, draw_cube_wires},
+    text::draw_text,
+    window::{clear_background, next_frame, Conf},
+    Window,
+};
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+/// What the window shows.
+pub enum Source {
+    /// One fixed solution.
+    Solution(Placement),
+    /// A search followed as it runs, see `Solver::events`.
+    Live(Receiver<Event>),
+}
+
+struct Scene {
+    grid: Grid,
+    pieces: Vec<(char, Rgb)>,
+    hidden: Vec<bool>,
+    placement: Placement,
+    solutions: usize,
+    nodes: u64,
+    /// Search events applied each frame.
+    speed: usize,
+    paused: bool,
+    finished: bool,
+}
+
+impl Scene {
+    /// Applies one event, keeping exactly `depth` pieces below the one it
+    /// concerns so pieces placed outside the event stream don't linger.
+    fn apply(&mut self, event: Event) {
+        match event {
+            Event::PiecePlaced {
+                piece_id,
+                bits,
+                depth,
+            } => {
+                self.unwind(depth);
+                self.placement.place(piece_id, bits);
+            }
+            Event::Backtracked { depth, .. } => self.unwind(depth),
+            Event::SolutionFound { index } => {
+                self.solutions = index + 1;
+                self.paused = true;
+            }
+            Event::Progress { nodes, solutions } => {
+                self.nodes = nodes;
+                self.solutions = solutions;
+            }
+        }
+    }
+
+    fn unwind(&mut self, depth: usize) {
+        while self.placement.placed.len() > depth {
+            self.placement.pop();
+        }
+    }
+
+    fn draw(&self) {
+        let offset = vec3(
+            self.grid.x as f32 - 1.0,
+            self.grid.y as f32 - 1.0,
+            self.grid.z as f32 - 1.0,
+        ) / 2.0;
+        for cell in self.grid.cells() {
+            let index = self.grid.index(&cell);
+            let center = vec3(cell.x as f32, cell.y as f32, cell.z as f32) - offset;
+            match self
+                .placement
+                .placed
+                .iter()
+                .find(|(_, bits)| bits.get(index))
+            {
+                Some((id, _)) if !self.hidden[*id] => {
+                    draw_cube(center, Vec3::splat(0.96), None, self.pieces[*id].1);
+                    draw_cube_wires(center, Vec3::splat(0.97), BLACK);
+                }
+                _ => draw_cube_wires(center, Vec3::splat(0.2), DARKGRAY),
+            }
+        }
+    }
+
+    fn status(&self, live: bool) -> String {
+        let hidden: String = self
+            .pieces
+            .iter()
+            .zip(self.hidden.iter())
+            .filter(|(_, hidden)| **hidden)
+            .map(|((id, _), _)| *id)
+            .collect();
+        let mut status = format!("placed {}", self.placement.placed.len());
+        if live {
+            status += &format!(
+                "  solutions {}  nodes {}  speed {}",
+                self.solutions, self.nodes, self.speed
+            );
+            if self.finished {
+                status += "  finished";
+            } else if self.paused {
+                status += "  paused (space)";
+            }
+        }
+        if !hidden.is_empty() {
+            status += &format!("  hidden {}", hidden);
+        }
+        status
+    }
+}
+
+/// Opens a window drawing placed pieces as colored cubes and returns once it
+/// is closed. Dragging orbits the board, the wheel zooms and typing a piece's
+/// id hides or shows it. A live search pauses at each solution; space resumes
+/// and `+` and `-` change how many events are played per frame.
+pub fn run(puzzle: &Puzzle, title: &str, source: Source) {
+    let pieces = puzzle
+        .pieces
+        .iter()
+        .map(|piece| {
+            let (r, g, b) = puzzle.rgb(piece.color);
+            (piece.char_id(), Rgb::from_rgba(r, g, b, 255))
+        })
+        .collect();
+    let (placement, events) = match source {
+        Source::Solution(placement) => (placement, None),
+        Source::Live(events) => (Placement::new(), Some(events)),
+    };
+    let mut scene = Scene {
+        grid: puzzle.grid,
+        pieces,
+        hidden: vec![false; puzzle.pieces.len()],
+        placement,
+        solutions: 0,
+        nodes: 0,
+        speed: 1,
+        paused: false,
+        finished: false,
+    };
+    let conf = Conf {
+        window_title: title.to_string(),
+        window_width: 900,
+        window_height: 700,
+        ..Default::default()
+    };
+    Window::from_config(conf, async move {
+        let size = scene.grid.x.max(scene.grid.y).max(scene.grid.z) as f32;
+        let (mut yaw, mut pitch, mut distance) = (0.8f32, 0.5f32, size * 2.5);
+        loop {
+            if is_key_pressed(KeyCode::Escape) {
+                break;
+            }
+            if is_mouse_button_down(MouseButton::Left) {
+                let delta = mouse_delta_position();
+                yaw += delta.x * 3.0;
+                pitch = (pitch - delta.y * 3.0).clamp(-1.5, 1.5);
+            }
+            let (_, wheel) = mouse_wheel();
+            if wheel != 0.0 {
+                distance = (distance * if wheel > 0.0 { 0.9 } else { 1.1 }).clamp(size, size * 8.0);
+            }
+            while let Some(c) = get_char_pressed() {
+                match c {
+                    ' ' => scene.paused = !scene.paused,
+                    '+' | '=' => scene.speed = (scene.speed * 2).min(1 << 16),
+                    '-' => scene.speed = (scene.speed / 2).max(1),
+                    c => {
+                        let c = c.to_ascii_uppercase();
+                        if let Some(id) = scene.pieces.iter().position(|(id, _)| *id == c) {
+                            scene.hidden[id] = !scene.hidden[id];
+                        }
+                    }
+                }
+            }
+            if let Some(events) = events.as_ref() {
+                for _ in 0..scene.speed {
+                    if scene.paused || scene.finished {
+                        break;
+                    }
+                    match events.try_recv() {
+                        Ok(event) => scene.apply(event),
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => scene.finished = true,
+                    }
+                }
+            }
+
+            clear_background(WHITE);
+            set_camera(&Camera3D {
+                position: distance
+                    * vec3(
+                        yaw.cos() * pitch.cos(),
+                        pitch.sin(),
+                        yaw.sin() * pitch.cos(),
+                    ),
+                target: Vec3::ZERO,
+                up: Vec3::Y,
+                ..Default::default()
+            });
+            scene.draw();
+            set_default_camera();
+            draw_text(&scene.status(events.is_some()), 10.0, 24.0, 24.0, BLACK);
+            next_frame().await;
+        }
+    });
+}
@@ -0,0 +1,470 @@
+use crate::{
+    fingerprint::fnv1a, grid::Grid, memory::Budget, parse_checked_blocks, Bitset, Color, Orintaion,
+    Piece, Puzzle,
+};
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    mem::size_of,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+type Tables = (Vec<Orintaion>, Vec<Bitset>);
+
+/// Cache kept between runs of near-identical puzzles.
+///
+/// Piece tables are keyed by the blocks exactly as written, so an unchanged
+/// piece line gets back the same orientations and placements. Dead states
+/// are `(occupied, remaining)` pairs known to have no completion, the
+/// remaining pieces identified by `shape_hashes` rather than their ids, as
+/// nogoods are. They are kept per puzzle fingerprint, so they're only reused
+/// for the same puzzle, whatever order its pieces are in.
+///
+/// Placements are also scored by how their subtrees went, solutions found
+/// and failures, keyed by piece shape, and the next run of the puzzle tries
+/// the ones that led to solutions first and the ones that failed last.
+pub struct WarmStart {
+    dir: PathBuf,
+    pieces: Mutex<HashMap<String, Tables>>,
+    hits: Mutex<usize>,
+    dead: HashSet<(Bitset, u64)>,
+    dead_file: Option<PathBuf>,
+    shapes: Vec<u64>,
+    capacity: usize,
+    /// Solutions and failures under each `(shape, placement)`.
+    stats: HashMap<(u64, Bitset), (u64, u64)>,
+    stats_file: Option<PathBuf>,
+    /// Solution counts when each open placement was made.
+    open: Vec<usize>,
+}
+
+fn blocks_key(blocks: &Orintaion) -> String {
+    blocks
+        .blocks
+        .iter()
+        .map(|b| format!("{}{}{}", b.x, b.y, b.z))
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// A line of `pieces.txt` as `WarmStart::save` writes it, or `None` if it
+/// isn't one.
+fn parse_tables(line: &str) -> Option<(String, Tables)> {
+    let [key, orintations, placements] = line.split(';').collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let orintations = orintations
+        .split('|')
+        .map(|ori| parse_checked_blocks(ori).ok().map(Orintaion::new))
+        .collect::<Option<_>>()?;
+    let placements = placements
+        .split(',')
+        .filter(|bits| !bits.is_empty())
+        .map(Bitset::from_hex)
+        .collect::<Option<_>>()?;
+    Some((key.to_string(), (orintations, placements)))
+}
+
+/// Mixes a shape hash so that summing them gives an order independent key.
+fn mix(hash: u64) -> u64 {
+    let hash = (hash ^ (hash >> 33)).wrapping_mul(0xff51afd7ed558ccd);
+    hash ^ (hash >> 33)
+}
+
+/// A hash of each piece's `Piece::marked_form`, mixed so that summing them
+/// keys a set of pieces. Pieces only hash alike when either can stand in for
+/// the other.
+pub(crate) fn shape_hashes(puzzle: &Puzzle) -> Vec<u64> {
+    puzzle
+        .pieces
+        .iter()
+        .map(|piece| mix(fnv1a(piece.marked_form().as_bytes())))
+        .collect()
+}
+
+impl WarmStart {
+    /// Bytes a dead state takes, with room for the table to grow.
+    const DEAD_BYTES: usize = 2 * size_of::<(Bitset, u64)>();
+    /// Bytes a placement's score takes, with room for the table to grow.
+    const SCORE_BYTES: usize = 2 * size_of::<((u64, Bitset), (u64, u64))>();
+
+    pub fn load(dir: &Path, capacity: usize) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let mut pieces = HashMap::new();
+        if let Ok(bytes) = fs::read(dir.join("pieces.txt")) {
+            // Lines cut short or mangled are left out, their pieces built
+            // afresh.
+            for line in String::from_utf8_lossy(&bytes).lines() {
+                if let Some((key, tables)) = parse_tables(line) {
+                    pieces.insert(key, tables);
+                }
+            }
+        }
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            pieces: Mutex::new(pieces),
+            hits: Mutex::new(0),
+            dead: HashSet::new(),
+            dead_file: None,
+            shapes: Vec::new(),
+            capacity,
+            stats: HashMap::new(),
+            stats_file: None,
+            open: Vec::new(),
+        })
+    }
+
+    /// Builds a piece, reusing its tables when the same blocks were seen
+    /// before on a board this size. Placements are cached for the whole
+    /// board, then those leaving `region` dropped.
+    pub fn piece(
+        &self,
+        piece_id: usize,
+        name: String,
+        color: Color,
+        ori: Orintaion,
+        grid: &Grid,
+        region: &Bitset,
+    ) -> Piece {
+        let key = match *grid == Grid::default() {
+            true => blocks_key(&ori),
+            false => format!("{}x{}x{} {}", grid.x, grid.y, grid.z, blocks_key(&ori)),
+        };
+        let cached = self.pieces.lock().unwrap().get(&key).cloned();
+        let mut piece = match cached {
+            Some((orintations, placements)) => {
+                *self.hits.lock().unwrap() += 1;
+                Piece {
+                    piece_id,
+                    name,
+                    color,
+                    size: ori.blocks.len(),
+                    orintations,
+                    placements,
+                    spare: false,
+                    copy_of: None,
+                    glued: Vec::new(),
+                    flips: false,
+                }
+            }
+            None => {
+                let piece = Piece::on_grid(piece_id, name, color, ori, grid);
+                self.pieces
+                    .lock()
+                    .unwrap()
+                    .insert(key, (piece.orintations.clone(), piece.placements.clone()));
+                piece
+            }
+        };
+        piece.placements.retain(|bits| bits.and(region) == *bits);
+        piece
+    }
+
+    /// Loads the dead states and placement scores recorded for this puzzle,
+    /// by its fingerprint.
+    pub fn attach(&mut self, puzzle: &Puzzle) -> io::Result<()> {
+        self.shapes = shape_hashes(puzzle);
+        let key = puzzle.fingerprint();
+        let path = self.dir.join(format!("dead-{:016x}.txt", key));
+        if let Ok(file) = File::open(&path) {
+            for line in BufReader::new(file).lines() {
+                let line = line?;
+                if let Some((occupied, remaining)) = line.split_once(' ') {
+                    if let (Some(occupied), Ok(remaining)) = (
+                        Bitset::from_hex(occupied),
+                        u64::from_str_radix(remaining, 16),
+                    ) {
+                        self.dead.insert((occupied, remaining));
+                    }
+                }
+            }
+        }
+        self.dead_file = Some(path);
+
+        let path = self.dir.join(format!("order-{:016x}.txt", key));
+        if let Ok(file) = File::open(&path) {
+            for line in BufReader::new(file).lines() {
+                let line = line?;
+                let fields: Vec<&str> = line.split(' ').collect();
+                if let [shape, bits, solutions, failures] = fields[..] {
+                    if let (Ok(shape), Some(bits), Ok(solutions), Ok(failures)) = (
+                        u64::from_str_radix(shape, 16),
+                        Bitset::from_hex(bits),
+                        solutions.parse(),
+                        failures.parse(),
+                    ) {
+                        self.stats.insert((shape, bits), (solutions, failures));
+                    }
+                }
+            }
+        }
+        self.stats_file = Some(path);
+        Ok(())
+    }
+
+    /// Orders each piece's placements by what earlier runs learned: those
+    /// that led to more solutions first, then those that failed less often.
+    /// Returns how many placements had been seen before. A search stopping
+    /// after `limit` solutions keeps the puzzle's own order, so that it finds
+    /// the same ones every run.
+    pub fn order(&self, puzzle: &mut Puzzle, limit: Option<usize>) -> usize {
+        if limit.is_some() {
+            return 0;
+        }
+        let mut known = 0;
+        for piece in puzzle.pieces.iter_mut() {
+            let shape = self.shapes[piece.piece_id];
+            let score = |bits: &Bitset| {
+                let (solutions, failures) = self
+                    .stats
+                    .get(&(shape, bits.clone()))
+                    .copied()
+                    .unwrap_or_default();
+                (Reverse(solutions), failures)
+            };
+            known += piece
+                .placements
+                .iter()
+                .filter(|bits| self.stats.contains_key(&(shape, (*bits).clone())))
+                .count();
+            piece.placements.sort_by_cached_key(score);
+        }
+        known
+    }
+
+    /// Notes a placement being made after `solutions` solutions.
+    pub fn enter(&mut self, solutions: usize) {
+        self.open.push(solutions);
+    }
+
+    /// Scores the placement being taken back now that `solutions` have been
+    /// found. A subtree cut short by a limit is not counted as a failure.
+    /// Scores are only added while `budget` has room for them.
+    pub fn leave(
+        &mut self,
+        piece_id: usize,
+        bits: Bitset,
+        solutions: usize,
+        stopped: bool,
+        budget: &mut Budget,
+    ) {
+        let Some(before) = self.open.pop() else {
+            return;
+        };
+        let found = (solutions - before) as u64;
+        if found == 0 && stopped {
+            return;
+        }
+        let key = (self.shapes[piece_id], bits);
+        if !self.stats.contains_key(&key)
+            && (self.stats.len() >= self.capacity
+                || !budget.reserve("warm start", Self::SCORE_BYTES))
+        {
+            return;
+        }
+        let entry = self.stats.entry(key).or_default();
+        match found {
+            0 => entry.1 += 1,
+            found => entry.0 += found,
+        }
+    }
+
+    pub fn learned_placements(&self) -> usize {
+        self.stats.len()
+    }
+
+    pub fn cached_pieces(&self) -> usize {
+        *self.hits.lock().unwrap()
+    }
+
+    pub fn dead_states(&self) -> usize {
+        self.dead.len()
+    }
+
+    pub fn remaining_key(&self, remaining: &[usize]) -> u64 {
+        remaining
+            .iter()
+            .fold(0, |key: u64, id| key.wrapping_add(self.shapes[*id]))
+    }
+
+    pub fn is_dead(&self, state: &(Bitset, u64)) -> bool {
+        self.dead.contains(state)
+    }
+
+    /// Remembers a dead state unless the table is already at capacity or
+    /// `budget` has no room for it.
+    pub fn mark_dead(&mut self, state: (Bitset, u64), budget: &mut Budget) {
+        if self.dead.len() < self.capacity
+            && !self.dead.contains(&state)
+            && budget.reserve("warm start", Self::DEAD_BYTES)
+        {
+            self.dead.insert(state);
+        }
+    }
+
+    /// Bytes held by the dead states and scores loaded so far.
+    pub fn bytes(&self) -> usize {
+        self.dead.len() * Self::DEAD_BYTES + self.stats.len() * Self::SCORE_BYTES
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(self.dir.join("pieces.txt"))?);
+        for (key, (orintations, placements)) in self.pieces.lock().unwrap().iter() {
+            let orintations: Vec<String> = orintations.iter().map(blocks_key).collect();
+            let placements: Vec<String> = placements
+                .iter()
+                .map(|bits| format!("{:x}", bits))
+                .collect();
+            writeln!(
+                out,
+                "{};{};{}",
+                key,
+                orintations.join("|"),
+                placements.join(",")
+            )?;
+        }
+        out.flush()?;
+
+        if let Some(path) = &self.dead_file {
+            let mut out = BufWriter::new(File::create(path)?);
+            for (occupied, remaining) in self.dead.iter() {
+                writeln!(out, "{:x} {:x}", occupied, remaining)?;
+            }
+            out.flush()?;
+        }
+        if let Some(path) = &self.stats_file {
+            let mut out = BufWriter::new(File::create(path)?);
+            for ((shape, bits), (solutions, failures)) in self.stats.iter() {
+                writeln!(out, "{:x} {:x} {} {}", shape, bits, solutions, failures)?;
+            }
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WarmStart;
+    use crate::{Bitset, Placement, Puzzle, Solver, DEFAULT_PALETTE};
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    fn parse(text: &str) -> Puzzle {
+        Puzzle::parse(text, DEFAULT_PALETTE).unwrap()
+    }
+
+    /// An empty cache directory of its own for each test.
+    fn cache(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("puzzle-cubes-warm-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// Searches `puzzle` with the cache in `dir` and saves what it learned,
+    /// giving the solutions found and the dead states known after.
+    fn search(dir: &Path, puzzle: &Puzzle, node_limit: Option<u64>) -> (usize, usize) {
+        let mut warm = WarmStart::load(dir, 1 << 10).unwrap();
+        warm.attach(puzzle).unwrap();
+        let mut solver = Solver::new();
+        solver.count_only = true;
+        solver.node_limit = node_limit;
+        solver.warm = Some(warm);
+        let remaining: Vec<usize> = (0..puzzle.pieces.len()).collect();
+        solver.solve(puzzle, &mut Placement::new(), &remaining);
+        let warm = solver.warm.unwrap();
+        warm.save().unwrap();
+        (solver.num_solutions, warm.dead_states())
+    }
+
+    // Two dominoes fill the board and leave the monomino no room.
+    const STUCK: &str = "stuck,2x2x1\nI,red,000-100\nJ,blue,000-100\nA,green,000\n";
+
+    #[test]
+    fn stopped_search_learns_no_dead_states() {
+        let dir = cache("stopped");
+        assert_eq!(search(&dir, &parse(STUCK), Some(1)).1, 0);
+        assert!(search(&dir, &parse(STUCK), None).1 > 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn dead_states_are_kept_per_puzzle() {
+        let dir = cache("fingerprint");
+        let (_, learned) = search(&dir, &parse(STUCK), None);
+        assert!(learned > 0);
+
+        // The same puzzle with its pieces in another order.
+        let reordered = parse("stuck,2x2x1\nA,green,000\nJ,blue,000-100\nI,red,000-100\n");
+        let mut warm = WarmStart::load(&dir, 1 << 10).unwrap();
+        warm.attach(&reordered).unwrap();
+        assert_eq!(warm.dead_states(), learned);
+
+        // The monomino made spare is another puzzle, with solutions.
+        let spare = parse(&STUCK.replace("000\n", "000,0-1\n"));
+        let mut warm = WarmStart::load(&dir, 1 << 10).unwrap();
+        warm.attach(&spare).unwrap();
+        assert_eq!(warm.dead_states(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn spare_pieces_keep_their_own_dead_states() {
+        // B may be left out and A may not, so a state dead with B left isn't
+        // dead with A left.
+        let dir = cache("spare");
+        let puzzle = parse("ng,3x1x1,200\nB,blue,000,0-1\nA,red,000\nC,green,000-100\n");
+        assert_eq!(search(&dir, &puzzle, None).0, 2);
+        assert_eq!(search(&dir, &puzzle, None).0, 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn limited_runs_find_the_same_solutions() {
+        let dir = cache("limit");
+        let run = || {
+            let path = Path::new("puzzles/soma.txt");
+            let mut puzzle = Puzzle::read(path, DEFAULT_PALETTE).unwrap();
+            let mut warm = WarmStart::load(&dir, 1 << 16).unwrap();
+            warm.attach(&puzzle).unwrap();
+            warm.order(&mut puzzle, Some(50));
+            let mut solver = Solver::new();
+            solver.solution_limit = Some(50);
+            solver.collected = Some(Vec::new());
+            solver.warm = Some(warm);
+            let remaining: Vec<usize> = (0..puzzle.pieces.len()).collect();
+            solver.solve(&puzzle, &mut Placement::new(), &remaining);
+            solver.warm.unwrap().save().unwrap();
+            let solutions = solver.collected.unwrap();
+            solutions
+                .into_iter()
+                .map(|solution| solution.placed)
+                .collect::<Vec<_>>()
+        };
+        let first = run();
+        assert_eq!(first.len(), 50);
+        assert_eq!(run(), first);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn mangled_piece_lines_are_left_out() {
+        let dir = cache("mangled");
+        fs::create_dir_all(&dir).unwrap();
+        let mut text = b"000-100;000-100|000-010;3,c\n000-100-200\n010;01|;\n".to_vec();
+        text.extend_from_slice(b"020-120;020-120;zz\n030;0\xff0;1\n");
+        fs::write(dir.join("pieces.txt"), text).unwrap();
+        let warm = WarmStart::load(&dir, 1 << 10).unwrap();
+        let pieces = warm.pieces.lock().unwrap();
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(pieces["000-100"].1, [Bitset::from(0x3), Bitset::from(0xc)]);
+        drop(pieces);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}